@@ -1,32 +1,177 @@
 // ABOUTME: Core application state and Model-View-Update logic for the SSH launcher
 // ABOUTME: Coordinates configuration, SSH parsing, and fuzzy search in a testable way
 
-use crate::config::Config;
-use crate::fuzzy::SearchEngine;
-use crate::ssh::parser::{HostEntry, parse_known_hosts, parse_ssh_config};
+use crate::Logger;
+use crate::config::{BroadcastLayout, Config};
+use crate::fuzzy::{MatchOptions, SearchEngine, scorer_for};
+use crate::history::{LaunchHistory, now_timestamp};
+use crate::ssh::agent::{AgentKeys, loaded_keys, missing_keys};
+use crate::ssh::details::HostDetails;
+use crate::ssh::dry_run::DryRun;
+use crate::ssh::files::{open_in_file_manager, scp_command};
+use crate::ssh::forward::check_ports_available;
+use crate::ssh::host_keys::{HostKey, KeyCheck, scan, stored_keys};
+use crate::ssh::launcher::{LaunchOptions, LaunchOutcome, TerminalLauncher};
+use crate::ssh::parser::{HostEntry, dedup_hosts, parse_known_hosts, parse_ssh_config};
+use crate::tunnel::TunnelSpec;
 use anyhow::Result;
-use std::path::Path;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 
 #[derive(Debug, Clone)]
 pub struct AppState {
     pub config: Config,
     pub hosts: Vec<HostEntry>,
+    /// The host part of the search box, before any `>`
     pub search_query: String,
+    /// Command typed after `>` in the search box, run on the host instead of a shell
+    pub remote_command: Option<String>,
     pub filtered_hosts: Vec<HostEntry>,
     pub selected_index: usize,
     pub is_loading: bool,
     pub error_message: Option<String>,
+    pub history: LaunchHistory,
+    /// Where launches are saved as they're recorded; `None` keeps history in memory only
+    pub history_path: Option<PathBuf>,
+    pub last_launch: Option<LaunchOutcome>,
+    /// Terminals opened by the last launch of several hosts
+    pub last_broadcast: Vec<LaunchOutcome>,
+    /// Background tunnel built by the last `StartTunnel`, for the menubar process to run
+    pub last_tunnel: Option<TunnelSpec>,
+    /// What the selected host would launch, shown instead of launching
+    pub dry_run: Option<DryRun>,
+    /// Keys in the ssh agent; `None` until checked or when no agent answered
+    pub agent_keys: Option<AgentKeys>,
+    /// `IdentityFile`s missing from the agent, for each host that has any
+    pub missing_keys: BTreeMap<String, Vec<PathBuf>>,
+    /// Details pane for the selected host, open until the next key
+    pub host_details: Option<HostDetails>,
+    /// ssh-keyscan results by host name; a changed key blocks launching the host
+    pub host_key_checks: BTreeMap<String, KeyCheck>,
+    /// Launch checked by the last launch message, for the caller to run and hand back to
+    /// [`AppState::finish_launch`]
+    pub pending_launch: Option<LaunchJob>,
+}
+
+/// A launch checked against the app state, ready to run
+///
+/// Running it blocks on hooks and the terminal's startup, so the launcher runs it on a
+/// background thread and hands the report back to [`AppState::finish_launch`].
+#[derive(Debug, Clone)]
+pub struct LaunchJob {
+    config: Config,
+    hosts: Vec<HostEntry>,
+    /// `None` for the selected host, otherwise how the marked hosts open
+    layout: Option<BroadcastLayout>,
+    options: LaunchOptions,
+    /// Hosts refused before launching, with the reason
+    refused: Vec<String>,
+}
+
+/// What a [`LaunchJob`] opened, and why it failed if it did
+pub struct LaunchReport {
+    layout: Option<BroadcastLayout>,
+    options: LaunchOptions,
+    launched: Vec<HostEntry>,
+    outcomes: Vec<LaunchOutcome>,
+    error: Option<anyhow::Error>,
+}
+
+impl LaunchJob {
+    /// Launch every host, continuing past failures in separate windows
+    pub fn run(self) -> LaunchReport {
+        let launcher = TerminalLauncher::new(&self.config);
+        let mut report = LaunchReport {
+            layout: self.layout,
+            options: self.options.clone(),
+            launched: Vec::new(),
+            outcomes: Vec::new(),
+            error: None,
+        };
+
+        match self.layout {
+            None => {
+                for host in self.hosts {
+                    let result = match &self.options.add_key {
+                        Some(key) => launcher.add_key(&host, key),
+                        None => launcher.launch(&host, &self.options),
+                    };
+                    match result {
+                        Ok(outcome) => {
+                            report.launched.push(host);
+                            report.outcomes.push(outcome);
+                        }
+                        Err(e) => report.error = Some(e),
+                    }
+                }
+            }
+            Some(BroadcastLayout::Tiled) => match launcher.launch_tiled(&self.hosts, &self.options)
+            {
+                Ok(outcome) => {
+                    report.launched = self.hosts;
+                    report.outcomes.push(outcome);
+                }
+                Err(e) => report.error = Some(e),
+            },
+            Some(BroadcastLayout::Windows) => {
+                let total = self.hosts.len() + self.refused.len();
+                let mut failures = self.refused;
+                let results = launcher.launch_each(&self.hosts, &self.options);
+                for (host, result) in self.hosts.into_iter().zip(results) {
+                    match result {
+                        Ok(outcome) => {
+                            report.launched.push(host);
+                            report.outcomes.push(outcome);
+                        }
+                        Err(e) => failures.push(format!("{}: {}", host.name, e)),
+                    }
+                }
+                if !failures.is_empty() {
+                    report.error = Some(anyhow::anyhow!(
+                        "Failed to launch {} of {} hosts: {}",
+                        failures.len(),
+                        total,
+                        failures.join("; ")
+                    ));
+                }
+            }
+        }
+        report
+    }
 }
 
 #[derive(Debug, Clone)]
 pub enum Message {
-    LoadConfig(Config),
+    LoadConfig(Box<Config>),
     UpdateSearchQuery(String),
     SelectNext,
     SelectPrevious,
     SelectHost(usize),
+    /// Check a launch of the selected host and leave it in `pending_launch` to run
     LaunchSelectedHost,
+    /// Launch with per-launch overrides, e.g. the alternate transport
+    LaunchSelectedHostWith(LaunchOptions),
+    /// Describe what launching the selected host would execute, without launching it
+    DryRunSelectedHost(LaunchOptions),
+    /// Launch several marked hosts at once, left in `pending_launch` like a single host
+    LaunchHosts {
+        hosts: Vec<HostEntry>,
+        layout: BroadcastLayout,
+        options: LaunchOptions,
+        /// Set once the user confirmed launching more than `confirm_above` hosts
+        confirmed: bool,
+    },
+    /// Hold the selected host's forwards open in the background instead of a terminal
+    StartTunnel(LaunchOptions),
+    /// Open the selected host's `file_manager_uri`
+    OpenInFileManager,
     RefreshHosts,
+    /// Ask the ssh agent which keys it holds and flag hosts whose key is missing
+    RefreshAgentKeys,
+    /// Show where the selected host connects and its known_hosts keys
+    ShowHostDetails,
+    /// Compare the selected host's live keys (ssh-keyscan) with known_hosts
+    VerifySelectedHostKey,
     ShowError(String),
     ClearError,
 }
@@ -37,22 +182,36 @@ impl AppState {
             config: Config::default(),
             hosts: Vec::new(),
             search_query: String::new(),
+            remote_command: None,
             filtered_hosts: Vec::new(),
             selected_index: 0,
             is_loading: false,
             error_message: None,
+            history: LaunchHistory::default(),
+            history_path: None,
+            last_launch: None,
+            last_broadcast: Vec::new(),
+            last_tunnel: None,
+            dry_run: None,
+            agent_keys: None,
+            missing_keys: BTreeMap::new(),
+            host_details: None,
+            host_key_checks: BTreeMap::new(),
+            pending_launch: None,
         }
     }
 
     pub fn update(&mut self, message: Message) -> Result<()> {
         match message {
             Message::LoadConfig(config) => {
-                self.config = config;
+                self.config = *config;
                 self.update(Message::RefreshHosts)?;
             }
 
             Message::UpdateSearchQuery(query) => {
-                self.search_query = query;
+                let (host_query, command) = split_command_query(&query);
+                self.search_query = host_query.to_string();
+                self.remote_command = command.map(str::to_string);
                 self.update_filtered_hosts();
                 self.selected_index = 0;
             }
@@ -80,8 +239,53 @@ impl AppState {
             }
 
             Message::LaunchSelectedHost => {
-                if let Some(host) = self.get_selected_host() {
-                    self.launch_host(host)?;
+                let options = LaunchOptions {
+                    remote_command: self.remote_command.clone(),
+                    ..Default::default()
+                };
+                self.update(Message::LaunchSelectedHostWith(options))?;
+            }
+
+            Message::LaunchSelectedHostWith(options) => {
+                self.pending_launch = self.prepare_selected_launch(options)?;
+            }
+
+            Message::DryRunSelectedHost(options) => {
+                if let Some(host) = self.get_selected_host().cloned() {
+                    let dry_run = TerminalLauncher::new(&self.config)
+                        .dry_run(&host, &options)
+                        .inspect_err(|e| self.show_launch_error(e))?;
+                    self.dry_run = Some(dry_run);
+                    self.error_message = None;
+                }
+            }
+
+            Message::LaunchHosts {
+                hosts,
+                layout,
+                options,
+                confirmed,
+            } => {
+                self.pending_launch =
+                    Some(self.prepare_broadcast(hosts, layout, options, confirmed)?);
+            }
+
+            Message::StartTunnel(options) => {
+                if let Some(host) = self.get_selected_host().cloned() {
+                    let tunnel = self
+                        .tunnel_for(&host, &options)
+                        .inspect_err(|e| self.show_launch_error(e))?;
+                    self.record_launches([&host], &options);
+                    self.last_tunnel = Some(tunnel);
+                    self.error_message = None;
+                }
+            }
+
+            Message::OpenInFileManager => {
+                if let Some(host) = self.get_selected_host().cloned() {
+                    open_in_file_manager(&self.config.files, &host)
+                        .inspect_err(|e| self.show_launch_error(e))?;
+                    self.error_message = None;
                 }
             }
 
@@ -90,6 +294,9 @@ impl AppState {
                 match self.load_hosts() {
                     Ok(hosts) => {
                         self.hosts = hosts;
+                        // known_hosts may have been fixed, so earlier checks no longer apply
+                        self.host_key_checks.clear();
+                        self.update_missing_keys();
                         self.update_filtered_hosts();
                         self.is_loading = false;
                         self.error_message = None;
@@ -101,6 +308,23 @@ impl AppState {
                 }
             }
 
+            Message::RefreshAgentKeys => {
+                self.set_agent_keys(loaded_keys(&self.config.agent));
+            }
+
+            Message::ShowHostDetails => {
+                if let Some(host) = self.get_selected_host() {
+                    self.host_details = Some(self.details_for(host));
+                }
+            }
+
+            Message::VerifySelectedHostKey => {
+                if let Some(host) = self.get_selected_host().cloned() {
+                    let scanned = scan(&self.config.host_keys, &host);
+                    self.finish_host_key_check(&host, scanned)?;
+                }
+            }
+
             Message::ShowError(message) => {
                 self.error_message = Some(message);
             }
@@ -137,17 +361,24 @@ impl AppState {
         }
 
         // Remove duplicates
-        all_hosts.sort_by(|a, b| a.name.cmp(&b.name));
-        all_hosts.dedup_by(|a, b| a.name == b.name);
+        dedup_hosts(&mut all_hosts);
 
         Ok(all_hosts)
     }
 
+    /// Build a search engine over the loaded hosts using the configured scorer and launch history
+    pub fn search_engine(&self) -> SearchEngine {
+        SearchEngine::new(self.hosts.clone())
+            .with_scorer(scorer_for(self.config.ui.scoring))
+            .with_frecency(self.history.frecency_scores(now_timestamp()))
+            .with_favorites(self.config.favorite_hosts())
+    }
+
     fn update_filtered_hosts(&mut self) {
-        let search_engine = SearchEngine::new(self.hosts.clone());
-        let results = search_engine.search(
+        let search_engine = self.search_engine();
+        let results = search_engine.search_with_options(
             &self.search_query,
-            self.config.ui.case_sensitive,
+            &MatchOptions::from_ui_config(&self.config.ui),
             self.config.ui.max_results,
         );
 
@@ -167,26 +398,219 @@ impl AppState {
         self.filtered_hosts.get(self.selected_index)
     }
 
-    fn launch_host(&self, host: &HostEntry) -> Result<()> {
-        use std::process::Command;
+    /// Remote commands to offer for a host: recently run ones, then those saved for its tags
+    pub fn command_suggestions(&self, host: &str) -> Vec<String> {
+        let mut commands: Vec<String> = self.history.recent_commands(host).to_vec();
+        for command in self.config.commands_for(host) {
+            if !commands.iter().any(|existing| existing == command) {
+                commands.push(command.to_string());
+            }
+        }
+        commands
+    }
+
+    /// Flag hosts against a fresh `ssh-add -L` listing; an unreachable agent flags none
+    pub fn set_agent_keys(&mut self, keys: Result<AgentKeys>) {
+        self.agent_keys = match keys {
+            Ok(keys) => {
+                Logger::debug(&format!("ssh agent holds {} keys", keys.len()));
+                Some(keys)
+            }
+            Err(e) => {
+                Logger::warn(&format!("Not checking host keys: {:#}", e));
+                None
+            }
+        };
+        self.update_missing_keys();
+    }
+
+    fn update_missing_keys(&mut self) {
+        self.missing_keys = match &self.agent_keys {
+            Some(keys) => self
+                .hosts
+                .iter()
+                .map(|host| (host.name.clone(), missing_keys(host, keys)))
+                .filter(|(_, missing)| !missing.is_empty())
+                .collect(),
+            None => BTreeMap::new(),
+        };
+    }
 
-        // Build the SSH command
-        let ssh_command = format!("{} {}", self.config.ssh.ssh_binary, host.name);
+    /// `IdentityFile`s of a host that aren't loaded in the ssh agent
+    pub fn missing_keys_for(&self, host: &str) -> &[PathBuf] {
+        self.missing_keys.get(host).map_or(&[], Vec::as_slice)
+    }
 
-        // Replace placeholder in terminal args
-        let mut terminal_args = self.config.terminal.args.clone();
-        for arg in &mut terminal_args {
-            *arg = arg.replace("{ssh_command}", &ssh_command);
+    /// Record launched hosts in the history and save it; the one place launches are recorded
+    fn record_launches<'a>(
+        &mut self,
+        hosts: impl IntoIterator<Item = &'a HostEntry>,
+        options: &LaunchOptions,
+    ) {
+        // Loading a key isn't a visit to the host
+        if options.add_key.is_some() {
+            return;
+        }
+        let now = now_timestamp();
+        for host in hosts {
+            self.history.record_launch(&host.name, now);
+            if let Some(command) = &options.remote_command {
+                self.history.record_command(&host.name, command);
+            }
+        }
+        if let Some(path) = &self.history_path
+            && let Err(e) = self.history.save(path)
+        {
+            Logger::warn(&format!("Failed to record launch history: {}", e));
         }
+    }
 
-        // Launch the terminal
-        Command::new(&self.config.terminal.program)
-            .args(&terminal_args)
-            .spawn()?;
+    /// The scp command for the selected host, to copy to the clipboard
+    pub fn selected_scp_command(&self) -> Option<String> {
+        self.get_selected_host()
+            .map(|host| scp_command(&self.config.files, host))
+    }
+
+    /// Check a launch of the selected host; `None` when nothing is selected
+    fn prepare_selected_launch(&mut self, options: LaunchOptions) -> Result<Option<LaunchJob>> {
+        let Some(host) = self.get_selected_host().cloned() else {
+            return Ok(None);
+        };
+        // ssh-add runs locally, so the host's key doesn't matter
+        if options.add_key.is_none() {
+            self.check_host_key(&host)
+                .inspect_err(|e| self.show_launch_error(e))?;
+        }
+        Ok(Some(LaunchJob {
+            config: self.config.clone(),
+            hosts: vec![host],
+            layout: None,
+            options,
+            refused: Vec::new(),
+        }))
+    }
+
+    /// Check a launch of several hosts at once
+    ///
+    /// In separate windows, hosts with a changed key are refused and the rest still
+    /// launch; a tiled window is all or nothing.
+    fn prepare_broadcast(
+        &mut self,
+        hosts: Vec<HostEntry>,
+        layout: BroadcastLayout,
+        options: LaunchOptions,
+        confirmed: bool,
+    ) -> Result<LaunchJob> {
+        if self.needs_broadcast_confirmation(hosts.len()) && !confirmed {
+            anyhow::bail!(
+                "Launching {} hosts needs confirmation (confirm_above = {})",
+                hosts.len(),
+                self.config.broadcast.confirm_above
+            );
+        }
 
+        let mut ready = Vec::new();
+        let mut refused = Vec::new();
+        for host in hosts {
+            match self.check_host_key(&host) {
+                Ok(()) => ready.push(host),
+                Err(e) if layout == BroadcastLayout::Windows => {
+                    refused.push(format!("{}: {}", host.name, e))
+                }
+                Err(e) => {
+                    self.show_launch_error(&e);
+                    return Err(e);
+                }
+            }
+        }
+        Ok(LaunchJob {
+            config: self.config.clone(),
+            hosts: ready,
+            layout: Some(layout),
+            options,
+            refused,
+        })
+    }
+
+    /// Record what a launch did and keep its error visible
+    pub fn finish_launch(&mut self, report: LaunchReport) -> Result<()> {
+        // Hosts that did launch are recorded even when others failed
+        self.record_launches(&report.launched, &report.options);
+        match report.layout {
+            None => self.last_launch = report.outcomes.into_iter().next(),
+            Some(_) => self.last_broadcast = report.outcomes,
+        }
+        match report.error {
+            Some(e) => {
+                self.show_launch_error(&e);
+                Err(e)
+            }
+            None => {
+                self.error_message = None;
+                Ok(())
+            }
+        }
+    }
+
+    /// Compare the keys `ssh-keyscan` got from a host with known_hosts and remember the result
+    ///
+    /// Scanning waits on the network, so the app scans in the background and hands the
+    /// keys here when it finishes.
+    pub fn finish_host_key_check(
+        &mut self,
+        host: &HostEntry,
+        scanned: Result<Vec<HostKey>>,
+    ) -> Result<()> {
+        let scanned = scanned.inspect_err(|e| self.show_launch_error(e))?;
+        let check = KeyCheck::compare(&stored_keys(host, &self.hosts), &scanned);
+        if check.is_changed() {
+            Logger::warn(&format!("{}: {}", host.name, check));
+            self.error_message = Some(format!("{}: {}", host.name, check));
+        } else {
+            self.error_message = None;
+        }
+        self.host_key_checks.insert(host.name.clone(), check);
+        self.host_details = Some(self.details_for(host));
         Ok(())
     }
 
+    /// Refuse to connect to a host whose last verification found a different key
+    fn check_host_key(&self, host: &HostEntry) -> Result<()> {
+        match self.host_key_checks.get(&host.name) {
+            Some(check) if check.is_changed() => anyhow::bail!(
+                "Not connecting to {}. {}\nFix known_hosts, then press Cmd+R.",
+                host.name,
+                check
+            ),
+            _ => Ok(()),
+        }
+    }
+
+    /// The details pane for a host, with its last verification if any
+    pub fn details_for(&self, host: &HostEntry) -> HostDetails {
+        HostDetails::new(
+            host,
+            &stored_keys(host, &self.hosts),
+            self.host_key_checks.get(&host.name),
+        )
+    }
+
+    fn tunnel_for(&self, host: &HostEntry, options: &LaunchOptions) -> Result<TunnelSpec> {
+        let tunnel = TerminalLauncher::new(&self.config).tunnel_for(host, options)?;
+        check_ports_available(&self.config.forward_presets(&options.forwards)?)?;
+        Ok(tunnel)
+    }
+
+    /// Keep a failed launch visible in the launcher, with its full cause chain
+    fn show_launch_error(&mut self, error: &anyhow::Error) {
+        self.error_message = Some(format!("{:#}", error));
+    }
+
+    /// Whether launching this many hosts at once should be confirmed first
+    pub fn needs_broadcast_confirmation(&self, count: usize) -> bool {
+        count > self.config.broadcast.confirm_above
+    }
+
     pub fn has_hosts(&self) -> bool {
         !self.filtered_hosts.is_empty()
     }
@@ -196,6 +620,20 @@ impl AppState {
     }
 }
 
+/// Split a search box query like `web > journalctl -f` into the host query and command
+///
+/// Host names never contain `>`, so everything after the first one is the command.
+/// An empty command counts as none.
+pub fn split_command_query(query: &str) -> (&str, Option<&str>) {
+    match query.split_once('>') {
+        Some((hosts, command)) => {
+            let command = command.trim();
+            (hosts.trim_end(), (!command.is_empty()).then_some(command))
+        }
+        None => (query, None),
+    }
+}
+
 impl Default for AppState {
     fn default() -> Self {
         Self::new()
@@ -205,11 +643,24 @@ impl Default for AppState {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::config::{ParsingConfig, SshConfig, TerminalConfig, UiConfig};
+    use crate::config::{ParsingConfig, ScoringStrategy, SshConfig, TerminalConfig, UiConfig};
+    #[cfg(unix)]
+    use crate::test_support::write_script;
     use std::fs;
     use std::io::Write;
+    use std::path::PathBuf;
+    use std::time::{Duration, Instant};
     use tempfile::TempDir;
 
+    /// Send a launch message and run the job it leaves, as the launcher window does
+    fn launch(app: &mut AppState, message: Message) -> Result<()> {
+        app.update(message)?;
+        match app.pending_launch.take() {
+            Some(job) => app.finish_launch(job.run()),
+            None => Ok(()),
+        }
+    }
+
     fn create_test_config(temp_dir: &TempDir) -> Config {
         let known_hosts_path = temp_dir.path().join("known_hosts");
         let ssh_config_path = temp_dir.path().join("config");
@@ -238,6 +689,13 @@ mod tests {
             terminal: TerminalConfig {
                 program: "/bin/echo".to_string(),
                 args: vec!["Launching:".to_string(), "{ssh_command}".to_string()],
+                default_profile: None,
+                profiles: Default::default(),
+                env: Default::default(),
+                inherit_login_shell_env: false,
+                working_directory: None,
+                tab_args: None,
+                split_args: None,
             },
             ssh: SshConfig {
                 known_hosts_path: known_hosts_path.to_string_lossy().to_string(),
@@ -253,7 +711,21 @@ mod tests {
             ui: UiConfig {
                 max_results: 10,
                 case_sensitive: false,
+                smart_case: true,
+                fold_diacritics: true,
+                scoring: ScoringStrategy::Tiered,
             },
+            transport: Default::default(),
+            session: Default::default(),
+            broadcast: Default::default(),
+            hooks: Default::default(),
+            files: Default::default(),
+            agent: Default::default(),
+            probe: Default::default(),
+            host_keys: Default::default(),
+            forwards: Default::default(),
+            hosts: Default::default(),
+            tags: Default::default(),
         }
     }
 
@@ -335,7 +807,7 @@ mod tests {
         let config = create_test_config(&temp_dir);
 
         let mut app = AppState::new();
-        app.update(Message::LoadConfig(config)).unwrap();
+        app.update(Message::LoadConfig(Box::new(config))).unwrap();
 
         // Should have loaded hosts from both files
         assert!(app.hosts.len() >= 2);
@@ -351,4 +823,771 @@ mod tests {
         app.filtered_hosts = vec![HostEntry::new("host1".to_string(), "ssh host1".to_string())];
         assert!(app.has_hosts());
     }
+
+    #[test]
+    fn test_launch_records_history() {
+        let mut app = AppState::new();
+        app.config.terminal.program = "/bin/echo".to_string();
+        app.filtered_hosts = vec![HostEntry::new("host1".to_string(), "ssh host1".to_string())];
+
+        launch(&mut app, Message::LaunchSelectedHost).unwrap();
+
+        assert_eq!(app.history.hosts["host1"].launch_count, 1);
+    }
+
+    #[test]
+    fn test_launch_message_leaves_job_to_run() {
+        let mut app = AppState::new();
+        app.config.terminal.program = "/bin/echo".to_string();
+        app.filtered_hosts = vec![HostEntry::new("host1".to_string(), "ssh host1".to_string())];
+
+        app.update(Message::LaunchSelectedHost).unwrap();
+
+        // Nothing runs until the caller takes the job
+        assert!(app.pending_launch.is_some());
+        assert!(app.last_launch.is_none());
+        assert!(app.history.hosts.is_empty());
+
+        let job = app.pending_launch.take().unwrap();
+        app.finish_launch(job.run()).unwrap();
+        assert_eq!(app.last_launch.as_ref().unwrap().host, "host1");
+    }
+
+    #[test]
+    fn test_launch_saves_history() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("history.toml");
+        let mut app = AppState::new();
+        app.history_path = Some(path.clone());
+        app.config.terminal.program = "/bin/echo".to_string();
+        app.filtered_hosts = vec![HostEntry::new("host1".to_string(), "ssh host1".to_string())];
+
+        launch(&mut app, Message::LaunchSelectedHost).unwrap();
+
+        let saved = LaunchHistory::load(&path).unwrap();
+        assert_eq!(saved.hosts["host1"].launch_count, 1);
+    }
+
+    #[test]
+    fn test_frecency_raises_ranking() {
+        let mut app = AppState::new();
+        app.hosts = vec![
+            HostEntry::new("web-a".to_string(), "ssh web-a".to_string()),
+            HostEntry::new("web-b".to_string(), "ssh web-b".to_string()),
+        ];
+        app.history.record_launch("web-b", now_timestamp());
+
+        app.update(Message::UpdateSearchQuery("web".to_string()))
+            .unwrap();
+
+        assert_eq!(app.filtered_hosts[0].name, "web-b");
+    }
+
+    /// Install a fake terminal program that writes its argv, one per line, to `argv.txt`
+    #[cfg(unix)]
+    fn fake_terminal(temp_dir: &TempDir) -> (String, PathBuf) {
+        let program = write_script(
+            temp_dir.path(),
+            "fake-terminal",
+            "printf '%s\\n' \"$@\" > \"$0.tmp\"\nmv \"$0.tmp\" \"$(dirname \"$0\")/argv.txt\"",
+        );
+        let record = temp_dir.path().join("argv.txt");
+
+        (program.to_string_lossy().to_string(), record)
+    }
+
+    /// Wait for the fake terminal to finish recording and return the argv it received
+    fn recorded_argv(record: &Path) -> Vec<String> {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while !record.exists() {
+            assert!(Instant::now() < deadline, "fake terminal never ran");
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        fs::read_to_string(record)
+            .unwrap()
+            .lines()
+            .map(str::to_string)
+            .collect()
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_launch_passes_argv_to_terminal() {
+        let temp_dir = TempDir::new().unwrap();
+        let (program, record) = fake_terminal(&temp_dir);
+
+        let mut app = AppState::new();
+        app.config.terminal.program = program.clone();
+        app.config.terminal.args = vec!["--new-window".to_string(), "{ssh_argv}".to_string()];
+        app.config.ssh.ssh_binary = "/opt/ssh/bin/ssh".to_string();
+        app.filtered_hosts = vec![HostEntry::new("web".to_string(), "ssh web".to_string())];
+
+        launch(&mut app, Message::LaunchSelectedHost).unwrap();
+
+        assert_eq!(
+            recorded_argv(&record),
+            vec!["--new-window", "/opt/ssh/bin/ssh", "web"]
+        );
+
+        let outcome = app.last_launch.as_ref().unwrap();
+        assert_eq!(outcome.host, "web");
+        assert_eq!(outcome.command.program, program);
+        assert_eq!(outcome.command.ssh_argv, vec!["/opt/ssh/bin/ssh", "web"]);
+        assert!(outcome.pid > 0);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_launch_quotes_ssh_command_for_shell() {
+        let temp_dir = TempDir::new().unwrap();
+        let (program, record) = fake_terminal(&temp_dir);
+
+        let mut app = AppState::new();
+        app.config.terminal.program = program;
+        app.config.terminal.args = vec![
+            "-e".to_string(),
+            "sh".to_string(),
+            "-c".to_string(),
+            "{ssh_command}".to_string(),
+        ];
+        app.config.ssh.ssh_binary = "/Applications/My Tools/ssh".to_string();
+        app.filtered_hosts = vec![HostEntry::new("db".to_string(), "ssh db".to_string())];
+
+        launch(&mut app, Message::LaunchSelectedHost).unwrap();
+
+        assert_eq!(
+            recorded_argv(&record),
+            vec!["-e", "sh", "-c", "'/Applications/My Tools/ssh' db"]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_failing_pre_launch_hook_aborts_launch() {
+        let temp_dir = TempDir::new().unwrap();
+        let (program, record) = fake_terminal(&temp_dir);
+
+        let mut app = AppState::new();
+        app.config.terminal.program = program;
+        app.config.hooks.pre_launch = Some("echo 'certificate expired' >&2; exit 1".to_string());
+        app.filtered_hosts = vec![HostEntry::new("web".to_string(), "ssh web".to_string())];
+
+        let error = launch(&mut app, Message::LaunchSelectedHost).unwrap_err();
+
+        let message = format!("{:#}", error);
+        assert!(
+            message.contains("pre_launch hook for 'web' failed"),
+            "{}",
+            message
+        );
+        assert!(message.contains("certificate expired"), "{}", message);
+        assert!(app.last_launch.is_none());
+        // Give a wrongly spawned terminal time to record before checking it never ran
+        std::thread::sleep(Duration::from_millis(200));
+        assert!(!record.exists());
+    }
+
+    #[test]
+    fn test_launch_failure_is_reported() {
+        let mut app = AppState::new();
+        app.config.terminal.program = "/nonexistent/terminal".to_string();
+        app.filtered_hosts = vec![HostEntry::new("web".to_string(), "ssh web".to_string())];
+
+        let result = launch(&mut app, Message::LaunchSelectedHost);
+
+        assert!(result.is_err());
+        assert!(app.last_launch.is_none());
+        assert!(app.history.hosts.is_empty());
+        assert!(app.error_message.is_some());
+    }
+
+    /// Install a fake terminal that prints to stderr and exits 1, like a terminal given bad args
+    #[cfg(unix)]
+    fn failing_terminal(temp_dir: &TempDir) -> String {
+        let program = write_script(
+            temp_dir.path(),
+            "failing-terminal",
+            "echo \"unknown option: $1\" >&2\nexit 1",
+        );
+        program.to_string_lossy().to_string()
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_terminal_exiting_early_fails_launch() {
+        let temp_dir = TempDir::new().unwrap();
+
+        let mut app = AppState::new();
+        app.config.terminal.program = failing_terminal(&temp_dir);
+        app.config.terminal.args = vec!["--bogus".to_string(), "{ssh_argv}".to_string()];
+        app.filtered_hosts = vec![HostEntry::new("web".to_string(), "ssh web".to_string())];
+
+        let result = launch(&mut app, Message::LaunchSelectedHost);
+
+        assert!(result.is_err());
+        assert!(app.last_launch.is_none());
+        assert!(app.history.hosts.is_empty());
+        let error = app.error_message.as_deref().unwrap();
+        assert!(error.contains("exit status: 1"), "{}", error);
+        assert!(error.contains("unknown option: --bogus"), "{}", error);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_successful_launch_clears_error() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut app = AppState::new();
+        app.config.terminal.program = failing_terminal(&temp_dir);
+        app.filtered_hosts = vec![HostEntry::new("web".to_string(), "ssh web".to_string())];
+        assert!(launch(&mut app, Message::LaunchSelectedHost).is_err());
+
+        app.config.terminal.program = "/bin/echo".to_string();
+        launch(&mut app, Message::LaunchSelectedHost).unwrap();
+
+        assert!(app.error_message.is_none());
+        assert!(app.last_launch.is_some());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_broadcast_reports_terminal_failures() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut app = AppState::new();
+        app.config.terminal.program = failing_terminal(&temp_dir);
+
+        let result = launch(
+            &mut app,
+            Message::LaunchHosts {
+                hosts: hosts(&["web1", "web2"]),
+                layout: BroadcastLayout::Windows,
+                options: LaunchOptions::default(),
+                confirmed: false,
+            },
+        );
+
+        assert!(result.is_err());
+        let error = app.error_message.as_deref().unwrap();
+        assert!(error.contains("Failed to launch 2 of 2 hosts"), "{}", error);
+        assert!(error.contains("web1: Terminal exited with"), "{}", error);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_broadcast_waits_for_terminal_startup_once() {
+        let temp_dir = TempDir::new().unwrap();
+        let program = write_script(temp_dir.path(), "slow-terminal", "exec sleep 3");
+        let mut app = AppState::new();
+        app.config.terminal.program = program.to_string_lossy().to_string();
+        app.config.broadcast.confirm_above = 10;
+
+        let started = std::time::Instant::now();
+        launch(
+            &mut app,
+            Message::LaunchHosts {
+                hosts: hosts(&["web1", "web2", "web3", "web4", "web5", "web6"]),
+                layout: BroadcastLayout::Windows,
+                options: LaunchOptions::default(),
+                confirmed: false,
+            },
+        )
+        .unwrap();
+
+        // Six still-running terminals, each watched for 500ms, all at once
+        assert_eq!(app.last_broadcast.len(), 6);
+        assert!(started.elapsed() < std::time::Duration::from_secs(2));
+    }
+
+    fn hosts(names: &[&str]) -> Vec<HostEntry> {
+        names
+            .iter()
+            .map(|name| HostEntry::new(name.to_string(), format!("ssh {}", name)))
+            .collect()
+    }
+
+    #[test]
+    fn test_launch_hosts_opens_one_terminal_each() {
+        let mut app = AppState::new();
+        app.config.terminal.program = "/bin/echo".to_string();
+
+        launch(
+            &mut app,
+            Message::LaunchHosts {
+                hosts: hosts(&["web1", "web2", "web3"]),
+                layout: BroadcastLayout::Windows,
+                options: LaunchOptions::default(),
+                confirmed: false,
+            },
+        )
+        .unwrap();
+
+        let launched: Vec<&str> = app
+            .last_broadcast
+            .iter()
+            .map(|outcome| outcome.host.as_str())
+            .collect();
+        assert_eq!(launched, vec!["web1", "web2", "web3"]);
+        assert_eq!(app.history.hosts["web2"].launch_count, 1);
+    }
+
+    #[test]
+    fn test_launch_hosts_above_threshold_needs_confirmation() {
+        let mut app = AppState::new();
+        app.config.terminal.program = "/bin/echo".to_string();
+        app.config.broadcast.confirm_above = 2;
+        let marked = hosts(&["web1", "web2", "web3"]);
+
+        let result = launch(
+            &mut app,
+            Message::LaunchHosts {
+                hosts: marked.clone(),
+                layout: BroadcastLayout::Windows,
+                options: LaunchOptions::default(),
+                confirmed: false,
+            },
+        );
+        assert!(result.unwrap_err().to_string().contains("confirmation"));
+        assert!(app.history.hosts.is_empty());
+
+        launch(
+            &mut app,
+            Message::LaunchHosts {
+                hosts: marked,
+                layout: BroadcastLayout::Windows,
+                options: LaunchOptions::default(),
+                confirmed: true,
+            },
+        )
+        .unwrap();
+        assert_eq!(app.last_broadcast.len(), 3);
+    }
+
+    #[test]
+    fn test_launch_hosts_reports_failures() {
+        let mut app = AppState::new();
+        app.config.terminal.program = "/nonexistent/terminal".to_string();
+
+        let error = launch(
+            &mut app,
+            Message::LaunchHosts {
+                hosts: hosts(&["web1", "web2"]),
+                layout: BroadcastLayout::Windows,
+                options: LaunchOptions::default(),
+                confirmed: false,
+            },
+        )
+        .unwrap_err();
+
+        assert!(error.to_string().contains("Failed to launch 2 of 2 hosts"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_launch_hosts_tiled_in_tmux() {
+        let temp_dir = TempDir::new().unwrap();
+        let (program, record) = fake_terminal(&temp_dir);
+
+        let mut app = AppState::new();
+        app.config.terminal.program = program;
+        app.config.terminal.args = vec!["-e".to_string(), "{ssh_argv}".to_string()];
+        // Any existing executable stands in for tmux; the fake terminal only records argv
+        app.config.broadcast.tmux_binary = "/bin/echo".to_string();
+
+        launch(
+            &mut app,
+            Message::LaunchHosts {
+                hosts: hosts(&["web1", "web2"]),
+                layout: BroadcastLayout::Tiled,
+                options: LaunchOptions::default(),
+                confirmed: false,
+            },
+        )
+        .unwrap();
+
+        assert_eq!(
+            recorded_argv(&record),
+            vec![
+                "-e",
+                "/bin/echo",
+                "new-session",
+                "/usr/bin/ssh web1",
+                ";",
+                "split-window",
+                "/usr/bin/ssh web2",
+                ";",
+                "select-layout",
+                "tiled",
+                ";",
+                "set-window-option",
+                "synchronize-panes",
+                "on"
+            ]
+        );
+        assert_eq!(app.last_broadcast[0].host, "web1, web2");
+        assert_eq!(app.history.hosts["web1"].launch_count, 1);
+    }
+
+    #[test]
+    fn test_start_tunnel_builds_background_tunnel() {
+        let mut app = AppState::new();
+        app.config.forwards.insert(
+            "postgres".to_string(),
+            crate::config::ForwardPreset {
+                kind: crate::config::ForwardKind::Local,
+                port: 54329,
+                target: Some("localhost:5432".to_string()),
+                forward_only: true,
+            },
+        );
+        app.filtered_hosts = vec![HostEntry::new("db".to_string(), "ssh db".to_string())];
+
+        app.update(Message::StartTunnel(LaunchOptions {
+            forwards: vec!["postgres".to_string()],
+            ..Default::default()
+        }))
+        .unwrap();
+
+        let tunnel = app.last_tunnel.as_ref().unwrap();
+        assert_eq!(tunnel.id(), "db:postgres");
+        assert_eq!(
+            tunnel.argv[..4],
+            ["/usr/bin/ssh", "-N", "-L", "54329:localhost:5432"]
+        );
+        assert!(app.last_launch.is_none());
+        assert_eq!(app.history.hosts["db"].launch_count, 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_file_actions_use_selected_host() {
+        let temp_dir = TempDir::new().unwrap();
+        let (opener, record) = fake_terminal(&temp_dir);
+
+        let mut app = AppState::new();
+        app.config.files.open_command = opener;
+        let mut host = HostEntry::new("db".to_string(), "ssh db".to_string());
+        host.hostname = Some("db.internal".to_string());
+        host.user = Some("deploy".to_string());
+        app.filtered_hosts = vec![host];
+
+        app.update(Message::OpenInFileManager).unwrap();
+        assert_eq!(
+            recorded_argv(&record),
+            vec!["sftp://deploy@db.internal:22/"]
+        );
+        assert_eq!(
+            app.selected_scp_command().as_deref(),
+            Some("scp -P 22 FILE deploy@db.internal:")
+        );
+
+        app.config.files.open_command = "false".to_string();
+        assert!(app.update(Message::OpenInFileManager).is_err());
+        assert!(app.error_message.is_some());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_hosts_flagged_when_key_not_in_agent() {
+        let temp_dir = TempDir::new().unwrap();
+        let (program, record) = fake_terminal(&temp_dir);
+        let key = temp_dir.path().join("id_work");
+        fs::write(
+            temp_dir.path().join("id_work.pub"),
+            "ssh-ed25519 AAAAWork work@laptop\n",
+        )
+        .unwrap();
+        let ssh_add = write_script(
+            temp_dir.path(),
+            "ssh-add",
+            "echo 'ssh-ed25519 AAAAHome me@home'",
+        );
+
+        let mut app = AppState::new();
+        app.config.terminal.program = program;
+        app.config.terminal.args = vec!["-e".to_string(), "{ssh_argv}".to_string()];
+        app.config.agent.ssh_add_binary = ssh_add.to_string_lossy().to_string();
+        let mut work = HostEntry::new("work".to_string(), "ssh work".to_string());
+        work.identity_files = vec![key.to_string_lossy().to_string()];
+        app.hosts = vec![
+            work.clone(),
+            HostEntry::new("web".to_string(), "ssh web".to_string()),
+        ];
+
+        // Nothing is flagged before the agent is asked
+        assert!(app.missing_keys_for("work").is_empty());
+
+        app.update(Message::RefreshAgentKeys).unwrap();
+        assert_eq!(app.missing_keys_for("work"), std::slice::from_ref(&key));
+        assert!(app.missing_keys_for("web").is_empty());
+
+        app.filtered_hosts = vec![work];
+        // Loading a key isn't a connection, so host launch hooks don't run
+        app.config.hooks.pre_launch = Some("exit 1".to_string());
+        launch(
+            &mut app,
+            Message::LaunchSelectedHostWith(LaunchOptions {
+                add_key: Some(key.clone()),
+                ..Default::default()
+            }),
+        )
+        .unwrap();
+        assert_eq!(
+            recorded_argv(&record),
+            vec![
+                "-e".to_string(),
+                ssh_add.to_string_lossy().to_string(),
+                key.to_string_lossy().to_string()
+            ]
+        );
+        assert!(app.history.hosts.is_empty());
+
+        // Without an agent to ask, nothing is flagged
+        app.config.agent.ssh_add_binary = "/nonexistent/ssh-add".to_string();
+        app.update(Message::RefreshAgentKeys).unwrap();
+        assert!(app.agent_keys.is_none());
+        assert!(app.missing_keys.is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_launch_sets_terminal_env_and_working_directory() {
+        let temp_dir = TempDir::new().unwrap();
+        let workdir = temp_dir.path().join("work");
+        fs::create_dir(&workdir).unwrap();
+        let record = temp_dir.path().join("env.txt");
+        let program = write_script(
+            temp_dir.path(),
+            "fake-terminal",
+            &format!(
+                "printf '%s\\n%s\\n' \"$SSH_AUTH_SOCK\" \"$(pwd)\" > '{}.tmp'\nmv '{}.tmp' '{}'",
+                record.display(),
+                record.display(),
+                record.display()
+            ),
+        );
+
+        let mut app = AppState::new();
+        app.config.terminal.program = program.to_string_lossy().to_string();
+        app.config.terminal.args = vec!["{ssh_argv}".to_string()];
+        app.config.terminal.env.insert(
+            "SSH_AUTH_SOCK".to_string(),
+            "/tmp/1password/agent.sock".to_string(),
+        );
+        app.config.terminal.working_directory = Some(workdir.to_string_lossy().to_string());
+        app.filtered_hosts = vec![HostEntry::new("web".to_string(), "ssh web".to_string())];
+
+        app.update(Message::DryRunSelectedHost(LaunchOptions::default()))
+            .unwrap();
+        let report = app.dry_run.as_ref().unwrap().to_string();
+        assert!(report.contains("SSH_AUTH_SOCK=\"/tmp/1password/agent.sock\""));
+        assert!(report.contains(&format!("working directory: {}", workdir.display())));
+
+        launch(&mut app, Message::LaunchSelectedHost).unwrap();
+        assert_eq!(
+            recorded_argv(&record),
+            vec![
+                "/tmp/1password/agent.sock".to_string(),
+                workdir
+                    .canonicalize()
+                    .unwrap()
+                    .to_string_lossy()
+                    .to_string()
+            ]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_changed_host_key_blocks_launch() {
+        use crate::ssh::host_keys::{HostKey, KnownKey};
+
+        const STORED: &str = "AAAAC3NzaC1lZDI1NTE5AAAAIPQVbVw/rj+RTMBVLm7CactYAToJ7Wo1rv11TcTKzixY";
+        const SPOOFED: &str =
+            "AAAAC3NzaC1lZDI1NTE5AAAAIHome000000000000000000000000000000000000000";
+
+        let temp_dir = TempDir::new().unwrap();
+        let (program, record) = fake_terminal(&temp_dir);
+        // The server's key is whatever the test writes to `served-key`
+        let served_key = temp_dir.path().join("served-key");
+        let keyscan = write_script(
+            temp_dir.path(),
+            "ssh-keyscan",
+            &format!("echo \"$5 ssh-ed25519 $(cat '{}')\"", served_key.display()),
+        );
+
+        let mut app = AppState::new();
+        app.config.terminal.program = program;
+        app.config.terminal.args = vec!["{ssh_argv}".to_string()];
+        app.config.host_keys.keyscan_binary = keyscan.to_string_lossy().to_string();
+        let mut host = HostEntry::new("web".to_string(), "ssh web".to_string());
+        host.host_keys = vec![KnownKey {
+            port: 22,
+            key: HostKey::parse("ssh-ed25519", STORED).unwrap(),
+        }];
+        app.hosts = vec![host.clone()];
+        app.filtered_hosts = vec![host];
+
+        app.update(Message::ShowHostDetails).unwrap();
+        let details = app.host_details.as_ref().unwrap().to_string();
+        assert!(details.contains("ED25519 SHA256:vthKWWjqXX8vAhdvFYJAOWZR8GGQKPz6WIoeZeaNb+4"));
+        assert!(details.contains("verified: not yet"));
+
+        fs::write(&served_key, STORED).unwrap();
+        app.update(Message::VerifySelectedHostKey).unwrap();
+        assert!(!app.host_key_checks["web"].is_changed());
+        assert_eq!(app.error_message, None);
+
+        fs::write(&served_key, SPOOFED).unwrap();
+        app.update(Message::VerifySelectedHostKey).unwrap();
+        assert!(app.host_key_checks["web"].is_changed());
+        assert!(
+            app.error_message
+                .as_deref()
+                .unwrap()
+                .starts_with("web: HOST KEY CHANGED")
+        );
+        assert!(
+            app.host_details
+                .as_ref()
+                .unwrap()
+                .to_string()
+                .contains("verified: HOST KEY CHANGED")
+        );
+
+        let error = launch(&mut app, Message::LaunchSelectedHost).unwrap_err();
+        assert!(error.to_string().starts_with("Not connecting to web."));
+        assert!(!record.exists());
+
+        // Verifying again after the key is back to normal unblocks the host
+        fs::write(&served_key, STORED).unwrap();
+        app.update(Message::VerifySelectedHostKey).unwrap();
+        launch(&mut app, Message::LaunchSelectedHost).unwrap();
+        assert_eq!(recorded_argv(&record), vec!["/usr/bin/ssh", "web"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_dry_run_describes_launch_without_spawning() {
+        let temp_dir = TempDir::new().unwrap();
+        let (program, record) = fake_terminal(&temp_dir);
+
+        let mut app = AppState::new();
+        app.config.terminal.program = program.clone();
+        app.config.terminal.args = vec!["-e".to_string(), "{ssh_argv}".to_string()];
+        app.config.hooks.pre_launch = Some("exit 1".to_string());
+        app.filtered_hosts = vec![HostEntry::new("web".to_string(), "ssh web".to_string())];
+
+        app.update(Message::DryRunSelectedHost(LaunchOptions {
+            remote_command: Some("htop".to_string()),
+            ..Default::default()
+        }))
+        .unwrap();
+
+        let dry_run = app.dry_run.as_ref().unwrap();
+        assert_eq!(dry_run.program, program);
+        assert_eq!(
+            dry_run.argv[1..],
+            ["-e", "/usr/bin/ssh", "-t", "web", "sh -c htop"]
+        );
+        assert_eq!(dry_run.working_dir, std::env::current_dir().unwrap());
+        let report = dry_run.to_string();
+        let fields: Vec<&str> = report
+            .lines()
+            .filter(|line| !line.starts_with(' '))
+            .map(|line| line.split(':').next().unwrap())
+            .collect();
+        assert_eq!(
+            fields,
+            vec![
+                "host",
+                "program",
+                "argv",
+                "environment",
+                "working directory",
+                "command line"
+            ]
+        );
+
+        // Nothing ran: not the hook, the terminal, or the history
+        std::thread::sleep(Duration::from_millis(200));
+        assert!(!record.exists());
+        assert!(app.last_launch.is_none());
+        assert!(app.history.hosts.is_empty());
+    }
+
+    #[test]
+    fn test_split_command_query() {
+        assert_eq!(split_command_query("web"), ("web", None));
+        assert_eq!(
+            split_command_query("web > journalctl -f -u app"),
+            ("web", Some("journalctl -f -u app"))
+        );
+        assert_eq!(
+            split_command_query("web>echo a > /tmp/b"),
+            ("web", Some("echo a > /tmp/b"))
+        );
+        assert_eq!(split_command_query("web > "), ("web", None));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_command_mode_runs_remote_command() {
+        let temp_dir = TempDir::new().unwrap();
+        let (program, record) = fake_terminal(&temp_dir);
+
+        let mut app = AppState::new();
+        app.hosts = vec![
+            HostEntry::new("web".to_string(), "ssh web".to_string()),
+            HostEntry::new("db".to_string(), "ssh db".to_string()),
+        ];
+        app.config.terminal.program = program;
+        app.config.terminal.args = vec!["-e".to_string(), "{ssh_argv}".to_string()];
+
+        app.update(Message::UpdateSearchQuery("web > htop".to_string()))
+            .unwrap();
+        assert_eq!(app.search_query, "web");
+        assert_eq!(app.get_selected_host().unwrap().name, "web");
+
+        launch(&mut app, Message::LaunchSelectedHost).unwrap();
+
+        assert_eq!(
+            recorded_argv(&record),
+            vec!["-e", "/usr/bin/ssh", "-t", "web", "sh -c htop"]
+        );
+        assert_eq!(app.history.recent_commands("web"), ["htop"]);
+    }
+
+    #[test]
+    fn test_command_suggestions_put_recent_first() {
+        let mut app = AppState::new();
+        app.config.hosts.insert(
+            "web".to_string(),
+            crate::config::HostSettings {
+                tags: vec!["app".to_string()],
+                ..Default::default()
+            },
+        );
+        app.config.tags.insert(
+            "app".to_string(),
+            crate::config::TagSettings {
+                commands: vec!["htop".to_string(), "sudo -i".to_string()],
+                ..Default::default()
+            },
+        );
+        app.history.record_command("web", "sudo -i");
+
+        assert_eq!(app.command_suggestions("web"), vec!["sudo -i", "htop"]);
+        assert!(app.command_suggestions("db").is_empty());
+    }
+
+    #[test]
+    fn test_start_tunnel_needs_forwards() {
+        let mut app = AppState::new();
+        app.filtered_hosts = vec![HostEntry::new("db".to_string(), "ssh db".to_string())];
+
+        let result = app.update(Message::StartTunnel(LaunchOptions::default()));
+
+        assert!(result.is_err());
+        assert!(app.last_tunnel.is_none());
+        assert!(app.error_message.is_some());
+    }
 }
@@ -3,6 +3,7 @@
 
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
 
@@ -12,12 +13,76 @@ pub struct Config {
     pub ssh: SshConfig,
     pub parsing: ParsingConfig,
     pub ui: UiConfig,
+    #[serde(default)]
+    pub transport: TransportConfig,
+    /// Terminal multiplexer session to attach to on connect
+    #[serde(default)]
+    pub session: SessionConfig,
+    /// Launching several marked hosts at once
+    #[serde(default)]
+    pub broadcast: BroadcastConfig,
+    /// Commands run before and after every launch
+    #[serde(default)]
+    pub hooks: HooksConfig,
+    /// sftp, file manager and scp actions
+    #[serde(default)]
+    pub files: FilesConfig,
+    /// ssh-agent checks for hosts with an `IdentityFile`
+    #[serde(default)]
+    pub agent: AgentConfig,
+    /// Reachability probes for the visible hosts
+    #[serde(default)]
+    pub probe: ProbeConfig,
+    /// Checking hosts' keys against known_hosts with ssh-keyscan
+    #[serde(default)]
+    pub host_keys: HostKeysConfig,
+    /// Named port forwards that hosts and tags can offer at launch
+    #[serde(default)]
+    pub forwards: BTreeMap<String, ForwardPreset>,
+    /// Per-host settings keyed by host name as shown in the launcher
+    #[serde(default)]
+    pub hosts: BTreeMap<String, HostSettings>,
+    /// Settings shared by every host carrying the tag
+    #[serde(default)]
+    pub tags: BTreeMap<String, TagSettings>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
 pub struct TerminalConfig {
     pub program: String,
     pub args: Vec<String>,
+    /// Profile used when a host doesn't choose one; `program`/`args` above when unset
+    #[serde(default)]
+    pub default_profile: Option<String>,
+    /// Named alternatives to `program`/`args`, e.g. a red theme for production
+    #[serde(default)]
+    pub profiles: BTreeMap<String, TerminalProfile>,
+    /// Variables set on launched terminals, e.g. `SSH_AUTH_SOCK` for an agent
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    /// Start terminals with the environment of `$SHELL -l`, captured once per process
+    #[serde(default)]
+    pub inherit_login_shell_env: bool,
+    /// Directory terminals start in; Trident's own when unset
+    #[serde(default)]
+    pub working_directory: Option<String>,
+    /// Args that open a new tab; built in for kitty, WezTerm, iTerm2 and tmux when unset
+    #[serde(default)]
+    pub tab_args: Option<Vec<String>>,
+    /// Args that open a split of the current window; built in like `tab_args` when unset
+    #[serde(default)]
+    pub split_args: Option<Vec<String>>,
+}
+
+/// A terminal program and its argument template
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct TerminalProfile {
+    pub program: String,
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub tab_args: Option<Vec<String>>,
+    #[serde(default)]
+    pub split_args: Option<Vec<String>>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
@@ -40,12 +105,485 @@ pub struct ParsingConfig {
 pub struct UiConfig {
     pub max_results: usize,
     pub case_sensitive: bool,
+    #[serde(default = "default_smart_case")]
+    pub smart_case: bool,
+    #[serde(default = "default_fold_diacritics")]
+    pub fold_diacritics: bool,
+    #[serde(default)]
+    pub scoring: ScoringStrategy,
+}
+
+/// Which fuzzy scoring algorithm ranks search results
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ScoringStrategy {
+    /// Exact, prefix, substring and subsequence matches in fixed tiers
+    #[default]
+    Tiered,
+    /// fzf-style alignment scoring with word-boundary and gap weighting
+    Fzf,
+}
+
+/// Program used to connect to a host inside the terminal
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Transport {
+    #[default]
+    Ssh,
+    Mosh,
+    /// Eternal Terminal
+    Et,
+    Autossh,
+    /// The `custom` command template from `[transport]`
+    Custom,
+}
+
+impl Transport {
+    pub fn name(self) -> &'static str {
+        match self {
+            Transport::Ssh => "ssh",
+            Transport::Mosh => "mosh",
+            Transport::Et => "et",
+            Transport::Autossh => "autossh",
+            Transport::Custom => "custom",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct TransportConfig {
+    /// Transport for hosts without their own `transport` setting
+    #[serde(default)]
+    pub default: Transport,
+    /// Transport used when launching with Alt+Enter
+    #[serde(default = "default_alternate_transport")]
+    pub alternate: Transport,
+    #[serde(default = "default_mosh_binary")]
+    pub mosh_binary: String,
+    #[serde(default = "default_et_binary")]
+    pub et_binary: String,
+    #[serde(default = "default_autossh_binary")]
+    pub autossh_binary: String,
+    /// Command template for the `custom` transport, with `{host}`, `{user}` and `{port}`
+    #[serde(default)]
+    pub custom: Option<String>,
+}
+
+impl Default for TransportConfig {
+    fn default() -> Self {
+        Self {
+            default: Transport::Ssh,
+            alternate: default_alternate_transport(),
+            mosh_binary: default_mosh_binary(),
+            et_binary: default_et_binary(),
+            autossh_binary: default_autossh_binary(),
+            custom: None,
+        }
+    }
+}
+
+/// Terminal multiplexer used to keep a remote session alive across disconnects
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Multiplexer {
+    /// Open a plain shell
+    #[default]
+    None,
+    Tmux,
+    Screen,
+    Zellij,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct SessionConfig {
+    #[serde(default)]
+    pub multiplexer: Multiplexer,
+    /// Session name template with `{host}` and `{user}` placeholders
+    #[serde(default = "default_session_name")]
+    pub name: String,
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self {
+            multiplexer: Multiplexer::None,
+            name: default_session_name(),
+        }
+    }
+}
+
+/// How a launch of several marked hosts is laid out
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BroadcastLayout {
+    /// One terminal per host
+    #[default]
+    Windows,
+    /// One terminal running a local tmux window with a tiled pane per host and
+    /// `synchronize-panes` on, so typing goes to every host
+    Tiled,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct BroadcastConfig {
+    #[serde(default)]
+    pub layout: BroadcastLayout,
+    /// Ask for confirmation before launching more than this many hosts at once
+    #[serde(default = "default_confirm_above")]
+    pub confirm_above: usize,
+    /// tmux used for the tiled layout
+    #[serde(default = "default_tmux_binary")]
+    pub tmux_binary: String,
+}
+
+impl Default for BroadcastConfig {
+    fn default() -> Self {
+        Self {
+            layout: BroadcastLayout::Windows,
+            confirm_above: default_confirm_above(),
+            tmux_binary: default_tmux_binary(),
+        }
+    }
+}
+
+/// Shell commands run around a launch with `TRIDENT_HOST`, `TRIDENT_USER` and `TRIDENT_PORT` set
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct HooksConfig {
+    /// Runs before the terminal opens; failing aborts the launch
+    #[serde(default)]
+    pub pre_launch: Option<String>,
+    /// Runs after the terminal opened
+    #[serde(default)]
+    pub post_launch: Option<String>,
+    /// Seconds a hook may run before it is killed and counted as failed
+    #[serde(default = "default_hook_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+impl Default for HooksConfig {
+    fn default() -> Self {
+        Self {
+            pre_launch: None,
+            post_launch: None,
+            timeout_secs: default_hook_timeout_secs(),
+        }
+    }
+}
+
+/// Moving files to and from a host, besides opening a shell
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct FilesConfig {
+    #[serde(default = "default_sftp_binary")]
+    pub sftp_binary: String,
+    /// URI opened in the file manager, with `{user}`, `{host}` and `{port}` placeholders
+    #[serde(default = "default_file_manager_uri")]
+    pub file_manager_uri: String,
+    /// Program that opens the URI in the default app
+    #[serde(default = "default_open_command")]
+    pub open_command: String,
+    /// scp command copied to the clipboard, with the same placeholders
+    #[serde(default = "default_scp_template")]
+    pub scp_template: String,
+}
+
+impl Default for FilesConfig {
+    fn default() -> Self {
+        Self {
+            sftp_binary: default_sftp_binary(),
+            file_manager_uri: default_file_manager_uri(),
+            open_command: default_open_command(),
+            scp_template: default_scp_template(),
+        }
+    }
+}
+
+/// Which keys the ssh agent holds, to flag hosts whose key isn't loaded
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct AgentConfig {
+    #[serde(default = "default_ssh_add_binary")]
+    pub ssh_add_binary: String,
+    /// Agent socket to ask, e.g. 1Password's; `SSH_AUTH_SOCK` when unset
+    #[serde(default)]
+    pub socket: Option<String>,
+}
+
+impl Default for AgentConfig {
+    fn default() -> Self {
+        Self {
+            ssh_add_binary: default_ssh_add_binary(),
+            socket: None,
+        }
+    }
+}
+
+/// TCP connect probes showing each visible host's latency or that it's unreachable
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct ProbeConfig {
+    #[serde(default = "default_probe_enabled")]
+    pub enabled: bool,
+    /// Limit for resolving and connecting together, and for reading the SSH banner
+    #[serde(default = "default_probe_timeout_ms")]
+    pub timeout_ms: u64,
+    /// Probes running at once
+    #[serde(default = "default_probe_concurrency")]
+    pub concurrency: usize,
+    /// How long a result is reused across launcher opens
+    #[serde(default = "default_probe_cache_seconds")]
+    pub cache_seconds: u64,
+}
+
+impl Default for ProbeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_probe_enabled(),
+            timeout_ms: default_probe_timeout_ms(),
+            concurrency: default_probe_concurrency(),
+            cache_seconds: default_probe_cache_seconds(),
+        }
+    }
+}
+
+/// How "Verify host key" asks a server for its keys
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct HostKeysConfig {
+    #[serde(default = "default_keyscan_binary")]
+    pub keyscan_binary: String,
+    /// Passed to `ssh-keyscan -T`; the scan is killed shortly after it
+    #[serde(default = "default_keyscan_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+impl Default for HostKeysConfig {
+    fn default() -> Self {
+        Self {
+            keyscan_binary: default_keyscan_binary(),
+            timeout_secs: default_keyscan_timeout_secs(),
+        }
+    }
+}
+
+/// Direction of an ssh port forward
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ForwardKind {
+    /// `-L`: a local port reaches `target` through the host
+    Local,
+    /// `-R`: a port on the host reaches `target` through this machine
+    Remote,
+    /// `-D`: a local SOCKS proxy through the host
+    Dynamic,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct ForwardPreset {
+    pub kind: ForwardKind,
+    /// Port that listens: local for `local` and `dynamic`, on the host for `remote`
+    pub port: u16,
+    /// `host:port` the forward connects to; not used by `dynamic`
+    #[serde(default)]
+    pub target: Option<String>,
+    /// Only hold the forward open (`ssh -N`) instead of starting a shell
+    #[serde(default)]
+    pub forward_only: bool,
+}
+
+impl ForwardPreset {
+    /// Check the ports and target, naming the preset in errors
+    pub fn validate(&self, name: &str) -> Result<()> {
+        if self.port == 0 {
+            anyhow::bail!("Forward '{}' needs a port greater than 0", name);
+        }
+        if self.port < 1024 {
+            anyhow::bail!(
+                "Forward '{}' listens on privileged port {}, which needs root. Use a port of 1024 or above.",
+                name,
+                self.port
+            );
+        }
+        match (self.kind, self.target.as_deref()) {
+            (ForwardKind::Dynamic, Some(_)) => {
+                anyhow::bail!("Forward '{}' is dynamic and takes no target", name)
+            }
+            (ForwardKind::Dynamic, None) => {}
+            (_, None) => anyhow::bail!(
+                "Forward '{}' needs a target such as \"localhost:5432\"",
+                name
+            ),
+            (_, Some(target)) => {
+                let valid = target.rsplit_once(':').is_some_and(|(host, port)| {
+                    !host.is_empty() && port.parse::<u16>().is_ok_and(|port| port > 0)
+                });
+                if !valid {
+                    anyhow::bail!(
+                        "Forward '{}' target '{}' must be host:port, e.g. \"localhost:5432\"",
+                        name,
+                        target
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The local port this forward binds, if any; two such forwards on one port conflict
+    pub fn local_port(&self) -> Option<u16> {
+        match self.kind {
+            ForwardKind::Local | ForwardKind::Dynamic => Some(self.port),
+            ForwardKind::Remote => None,
+        }
+    }
+}
+
+/// Fail if two of the forwards would listen on the same local port
+pub fn check_forward_conflicts(forwards: &[(&str, &ForwardPreset)]) -> Result<()> {
+    let mut seen: BTreeMap<u16, &str> = BTreeMap::new();
+    for (name, preset) in forwards {
+        if let Some(port) = preset.local_port()
+            && let Some(other) = seen.insert(port, name)
+        {
+            anyhow::bail!(
+                "Forwards '{}' and '{}' both listen on local port {}",
+                other,
+                name,
+                port
+            );
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
+pub struct TagSettings {
+    #[serde(default)]
+    pub session: Option<SessionConfig>,
+    /// Hooks for hosts with this tag, run after the global hooks
+    #[serde(default)]
+    pub hooks: Option<HooksConfig>,
+    /// Forward presets offered for hosts with this tag
+    #[serde(default)]
+    pub forwards: Vec<String>,
+    /// Remote commands offered in the Cmd+K menu of hosts with this tag
+    #[serde(default)]
+    pub commands: Vec<String>,
+    /// Terminal profile for hosts with this tag
+    #[serde(default)]
+    pub profile: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
+pub struct HostSettings {
+    /// Favorites rank ahead of other hosts with the same score
+    #[serde(default)]
+    pub favorite: bool,
+    /// Overrides `[transport] default` for this host
+    #[serde(default)]
+    pub transport: Option<Transport>,
+    /// Tags whose `[tags.<name>]` settings apply to this host, first match wins
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Overrides tag and global `session` settings for this host
+    #[serde(default)]
+    pub session: Option<SessionConfig>,
+    /// Terminal profile for this host, overriding tag rules and the default profile
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// Forward presets offered for this host, before those of its tags
+    #[serde(default)]
+    pub forwards: Vec<String>,
 }
 
 fn default_skip_hashed_hosts() -> bool {
     true
 }
 
+fn default_smart_case() -> bool {
+    true
+}
+
+fn default_fold_diacritics() -> bool {
+    true
+}
+
+fn default_alternate_transport() -> Transport {
+    Transport::Mosh
+}
+
+fn default_session_name() -> String {
+    "{host}".to_string()
+}
+
+fn default_mosh_binary() -> String {
+    "mosh".to_string()
+}
+
+fn default_et_binary() -> String {
+    "et".to_string()
+}
+
+fn default_autossh_binary() -> String {
+    "autossh".to_string()
+}
+
+fn default_confirm_above() -> usize {
+    5
+}
+
+fn default_tmux_binary() -> String {
+    "tmux".to_string()
+}
+
+fn default_hook_timeout_secs() -> u64 {
+    30
+}
+
+fn default_sftp_binary() -> String {
+    "sftp".to_string()
+}
+
+fn default_file_manager_uri() -> String {
+    "sftp://{user}@{host}:{port}/".to_string()
+}
+
+fn default_open_command() -> String {
+    if cfg!(target_os = "macos") {
+        "open".to_string()
+    } else {
+        "xdg-open".to_string()
+    }
+}
+
+fn default_scp_template() -> String {
+    "scp -P {port} FILE {user}@{host}:".to_string()
+}
+
+fn default_ssh_add_binary() -> String {
+    "ssh-add".to_string()
+}
+
+fn default_keyscan_binary() -> String {
+    "ssh-keyscan".to_string()
+}
+
+fn default_keyscan_timeout_secs() -> u64 {
+    5
+}
+
+fn default_probe_enabled() -> bool {
+    true
+}
+
+fn default_probe_timeout_ms() -> u64 {
+    800
+}
+
+fn default_probe_concurrency() -> usize {
+    8
+}
+
+fn default_probe_cache_seconds() -> u64 {
+    60
+}
+
 #[derive(Debug, Clone)]
 struct DetectedTerminal {
     name: String,
@@ -53,6 +591,41 @@ struct DetectedTerminal {
     args: Vec<String>,
 }
 
+/// Linux terminals in order of preference: display name, executable and args template
+const LINUX_TERMINALS: &[(&str, &str, &[&str])] = &[
+    ("GNOME Terminal", "gnome-terminal", &["--", "{ssh_argv}"]),
+    ("Konsole", "konsole", &["-e", "{ssh_argv}"]),
+    ("foot", "foot", &["{ssh_argv}"]),
+    ("Kitty", "kitty", &["{ssh_argv}"]),
+    ("Alacritty", "alacritty", &["-e", "{ssh_argv}"]),
+    ("WezTerm", "wezterm", &["start", "--", "{ssh_argv}"]),
+    ("xterm", "xterm", &["-e", "{ssh_argv}"]),
+    (
+        "x-terminal-emulator",
+        "x-terminal-emulator",
+        &["-e", "{ssh_argv}"],
+    ),
+];
+
+/// What terminal detection looks at, separated from the process so tests can fake it
+struct DetectionEnv {
+    macos: bool,
+    /// `$PATH`-style search list
+    path: std::ffi::OsString,
+    /// The user's preferred terminal from `$TERMINAL`
+    terminal: Option<String>,
+}
+
+impl DetectionEnv {
+    fn from_process() -> Self {
+        Self {
+            macos: cfg!(target_os = "macos"),
+            path: std::env::var_os("PATH").unwrap_or_default(),
+            terminal: std::env::var("TERMINAL").ok(),
+        }
+    }
+}
+
 impl Default for Config {
     fn default() -> Self {
         Config {
@@ -62,6 +635,13 @@ impl Default for Config {
                     "-c".to_string(),
                     "tell application \"iTerm2\" to create window with default profile command \"{ssh_command}\"".to_string(),
                 ],
+                default_profile: None,
+                profiles: BTreeMap::new(),
+                env: BTreeMap::new(),
+                inherit_login_shell_env: false,
+                working_directory: None,
+                tab_args: None,
+                split_args: None,
             },
             ssh: SshConfig {
                 known_hosts_path: "~/.ssh/known_hosts".to_string(),
@@ -77,7 +657,21 @@ impl Default for Config {
             ui: UiConfig {
                 max_results: 20,
                 case_sensitive: false,
+                smart_case: true,
+                fold_diacritics: true,
+                scoring: ScoringStrategy::Tiered,
             },
+            transport: TransportConfig::default(),
+            session: SessionConfig::default(),
+            broadcast: BroadcastConfig::default(),
+            hooks: HooksConfig::default(),
+            files: FilesConfig::default(),
+            agent: AgentConfig::default(),
+            probe: ProbeConfig::default(),
+            host_keys: HostKeysConfig::default(),
+            forwards: BTreeMap::new(),
+            hosts: BTreeMap::new(),
+            tags: BTreeMap::new(),
         }
     }
 }
@@ -85,7 +679,8 @@ impl Default for Config {
 impl Config {
     /// Generate a config.toml with the best available terminal auto-detected
     pub fn generate_default_config() -> String {
-        let terminal_config = Self::detect_best_terminal();
+        let env = DetectionEnv::from_process();
+        let terminal_config = Self::detect_terminal(&env);
         let home = dirs::home_dir()
             .map(|h| h.to_string_lossy().to_string())
             .unwrap_or_else(|| "~".to_string());
@@ -98,10 +693,31 @@ impl Config {
 # Your detected terminal (you can change this):
 program = "{}"
 args = {}
+# Args can also use {{host}}, {{alias}}, {{user}}, {{port}}, {{title}} and {{tag}}, and
+# sections like {{?user}}-l {{user}}{{/user}} that are kept only when the value is set.
+# Spaces inside a section split it into separate args; unknown {{names}} are errors.
 
 # Other terminal examples you can switch to:
 {}
 
+# Apps started at login don't see your shell's PATH, SSH_AUTH_SOCK or locale.
+# Start terminals with your login shell's environment ($SHELL -l -c env):
+# inherit_login_shell_env = true
+# Directory terminals start in:
+# working_directory = "~"
+# Variables for terminals, applied after the login shell's:
+# env = {{ SSH_AUTH_SOCK = "/Users/me/.1password/agent.sock" }}
+# osascript terminals get env and working_directory as `cd … && env …` in the command.
+
+# Named profiles, e.g. a visually distinct terminal for production hosts.
+# Hosts and tags choose one with `profile = "<name>"`; Cmd+K picks one at launch.
+# default_profile = "iterm-red"
+# [terminal.profiles.iterm-red]
+# program = "/Applications/iTerm.app/Contents/MacOS/iTerm2"
+# args = ["-c", "tell application \"iTerm2\" to create window with profile \"Production\" command \"{{ssh_command}}\""]
+# Tabs (Cmd+Enter) and splits (Shift+Enter) can have their own args, too:
+# tab_args = ["-c", "tell application \"iTerm2\" to tell current window to create tab with profile \"Production\" command \"{{ssh_command}}\""]
+
 [ssh]
 # SSH file locations (modify if you use custom locations)
 known_hosts_path = "{}/.ssh/known_hosts"
@@ -121,11 +737,104 @@ skip_hashed_hosts = true
 # User interface settings
 max_results = 20
 case_sensitive = false
+# Become case-sensitive only when the query contains an uppercase letter
+smart_case = true
+# Match accented letters against their base letter (e.g. "zurich" finds "zürich")
+fold_diacritics = true
+# Ranking algorithm: "tiered" (default) or "fzf"
+scoring = "tiered"
+
+[transport]
+# How to connect: "ssh", "mosh", "et" (Eternal Terminal), "autossh", or "custom"
+default = "ssh"
+# Used instead of the default when launching with Alt+Enter
+alternate = "mosh"
+# Command for the "custom" transport; {{host}}, {{user}} and {{port}} are filled in per host
+# custom = "ssh -p {{port}} {{user}}@{{host}}"
+
+[session]
+# Attach to (or create) a remote multiplexer session: "none", "tmux", "screen" or "zellij"
+multiplexer = "none"
+# Session name; {{host}} and {{user}} are filled in per host
+name = "{{host}}"
+
+[broadcast]
+# Marked hosts (Space, Ctrl+A) open as "windows" (one terminal each) or "tiled"
+# (one local tmux window with synchronized panes)
+layout = "windows"
+# Ask before launching more than this many hosts at once
+confirm_above = 5
+tmux_binary = "tmux"
+
+[hooks]
+# Shell commands run before and after each launch, with TRIDENT_HOST, TRIDENT_USER
+# and TRIDENT_PORT set. A failing or timed-out pre_launch hook cancels the launch.
+# pre_launch = "ssh-add -l || ssh-add"
+# post_launch = "logger -t trident launched $TRIDENT_HOST"
+timeout_secs = 30
+
+[files]
+# sftp opens in the terminal with Cmd+S
+sftp_binary = "sftp"
+# Opened with Cmd+O; {{user}}, {{host}} and {{port}} are filled in per host
+file_manager_uri = "sftp://{{user}}@{{host}}:{{port}}/"
+# Hands the URI to its default app ("xdg-open" on Linux)
+# open_command = "open"
+# Copied to the clipboard with Cmd+Shift+C
+scp_template = "scp -P {{port}} FILE {{user}}@{{host}}:"
+
+[agent]
+# Hosts whose IdentityFile key isn't loaded in the agent are flagged
+ssh_add_binary = "ssh-add"
+# Agent to ask instead of SSH_AUTH_SOCK, e.g. 1Password's
+# socket = "~/.1password/agent.sock"
+
+[probe]
+# Check that visible hosts accept connections and show their latency
+enabled = true
+# For resolving and connecting, then again for reading the SSH banner
+timeout_ms = 800
+concurrency = 8
+# Reuse results for this long across launcher opens
+cache_seconds = 60
+
+[host_keys]
+# "Verify host key" in the Cmd+K menu compares the server's keys with known_hosts
+keyscan_binary = "ssh-keyscan"
+timeout_secs = 5
+
+# Per-host settings, keyed by host name as shown in the launcher
+# [hosts."prod-db"]
+# favorite = true
+# transport = "mosh"
+# tags = ["prod"]
+# session = {{ multiplexer = "tmux", name = "main" }}
+# profile = "iterm-red"
+# forwards = ["postgres"]
+
+# Settings shared by every host with a tag
+# [tags.prod]
+# session = {{ multiplexer = "tmux" }}
+# profile = "iterm-red"
+# hooks = {{ pre_launch = "tsh login --proxy=teleport.example.com" }}
+# forwards = ["socks"]
+# commands = ["journalctl -f -u app", "sudo -i"]
+
+# Port forwards offered in the Cmd+K menu of hosts and tags that list them
+# [forwards.postgres]
+# kind = "local"              # "local" (-L), "remote" (-R) or "dynamic" (-D, SOCKS)
+# port = 5432                 # Listening port, 1024 or above
+# target = "localhost:5432"   # Where the forward connects; not used by "dynamic"
+#
+# [forwards.socks]
+# kind = "dynamic"
+# port = 1080
+# forward_only = true         # Hold the forward open (ssh -N) without a shell
 "#,
             terminal_config.name,
             terminal_config.program,
             Self::format_args_for_toml(&terminal_config.args),
-            Self::generate_terminal_examples(&terminal_config.name),
+            Self::generate_terminal_examples(&terminal_config.name, env.macos),
             home,
             home
         )
@@ -136,6 +845,9 @@ case_sensitive = false
 
 [terminal]
 # Specify your terminal program and how to launch SSH commands
+# Args can also use {host}, {alias}, {user}, {port}, {title} and {tag}, and
+# sections like {?user}-l {user}{/user} that are kept only when the value is set.
+# Spaces inside a section split it into separate args; unknown {names} are errors.
 # Examples for different terminals:
 
 # iTerm2 (default):
@@ -148,11 +860,29 @@ args = ["-c", "tell application \"iTerm2\" to create window with default profile
 
 # Alacritty:
 # program = "/Applications/Alacritty.app/Contents/MacOS/alacritty"
-# args = ["-e", "{ssh_command}"]
+# args = ["-e", "{ssh_argv}"]
 
 # Kitty:
 # program = "/Applications/kitty.app/Contents/MacOS/kitty"
-# args = ["--", "{ssh_command}"]
+# args = ["{ssh_argv}"]
+
+# Apps started at login don't see your shell's PATH, SSH_AUTH_SOCK or locale.
+# Start terminals with your login shell's environment ($SHELL -l -c env):
+# inherit_login_shell_env = true
+# Directory terminals start in:
+# working_directory = "~"
+# Variables for terminals, applied after the login shell's:
+# env = { SSH_AUTH_SOCK = "/Users/me/.1password/agent.sock" }
+# osascript terminals get env and working_directory as `cd … && env …` in the command.
+
+# Named profiles, e.g. a visually distinct terminal for production hosts.
+# Hosts and tags choose one with `profile = "<name>"`; Cmd+K picks one at launch.
+# default_profile = "iterm-red"
+# [terminal.profiles.iterm-red]
+# program = "/Applications/iTerm.app/Contents/MacOS/iTerm2"
+# args = ["-c", "tell application \"iTerm2\" to create window with profile \"Production\" command \"{ssh_command}\""]
+# Tabs (Cmd+Enter) and splits (Shift+Enter) can have their own args, too:
+# tab_args = ["-c", "tell application \"iTerm2\" to tell current window to create tab with profile \"Production\" command \"{ssh_command}\""]
 
 [ssh]
 # SSH file locations
@@ -173,6 +903,99 @@ skip_hashed_hosts = true
 # User interface settings
 max_results = 20
 case_sensitive = false
+# Become case-sensitive only when the query contains an uppercase letter
+smart_case = true
+# Match accented letters against their base letter (e.g. "zurich" finds "zürich")
+fold_diacritics = true
+# Ranking algorithm: "tiered" (default) or "fzf"
+scoring = "tiered"
+
+[transport]
+# How to connect: "ssh", "mosh", "et" (Eternal Terminal), "autossh", or "custom"
+default = "ssh"
+# Used instead of the default when launching with Alt+Enter
+alternate = "mosh"
+# Command for the "custom" transport; {host}, {user} and {port} are filled in per host
+# custom = "ssh -p {port} {user}@{host}"
+
+[session]
+# Attach to (or create) a remote multiplexer session: "none", "tmux", "screen" or "zellij"
+multiplexer = "none"
+# Session name; {host} and {user} are filled in per host
+name = "{host}"
+
+[broadcast]
+# Marked hosts (Space, Ctrl+A) open as "windows" (one terminal each) or "tiled"
+# (one local tmux window with synchronized panes)
+layout = "windows"
+# Ask before launching more than this many hosts at once
+confirm_above = 5
+tmux_binary = "tmux"
+
+[hooks]
+# Shell commands run before and after each launch, with TRIDENT_HOST, TRIDENT_USER
+# and TRIDENT_PORT set. A failing or timed-out pre_launch hook cancels the launch.
+# pre_launch = "ssh-add -l || ssh-add"
+# post_launch = "logger -t trident launched $TRIDENT_HOST"
+timeout_secs = 30
+
+[files]
+# sftp opens in the terminal with Cmd+S
+sftp_binary = "sftp"
+# Opened with Cmd+O; {user}, {host} and {port} are filled in per host
+file_manager_uri = "sftp://{user}@{host}:{port}/"
+# Hands the URI to its default app ("xdg-open" on Linux)
+# open_command = "open"
+# Copied to the clipboard with Cmd+Shift+C
+scp_template = "scp -P {port} FILE {user}@{host}:"
+
+[agent]
+# Hosts whose IdentityFile key isn't loaded in the agent are flagged
+ssh_add_binary = "ssh-add"
+# Agent to ask instead of SSH_AUTH_SOCK, e.g. 1Password's
+# socket = "~/.1password/agent.sock"
+
+[probe]
+# Check that visible hosts accept connections and show their latency
+enabled = true
+# For resolving and connecting, then again for reading the SSH banner
+timeout_ms = 800
+concurrency = 8
+# Reuse results for this long across launcher opens
+cache_seconds = 60
+
+[host_keys]
+# "Verify host key" in the Cmd+K menu compares the server's keys with known_hosts
+keyscan_binary = "ssh-keyscan"
+timeout_secs = 5
+
+# Per-host settings, keyed by host name as shown in the launcher
+# [hosts."prod-db"]
+# favorite = true
+# transport = "mosh"
+# tags = ["prod"]
+# session = { multiplexer = "tmux", name = "main" }
+# profile = "iterm-red"
+# forwards = ["postgres"]
+
+# Settings shared by every host with a tag
+# [tags.prod]
+# session = { multiplexer = "tmux" }
+# profile = "iterm-red"
+# hooks = { pre_launch = "tsh login --proxy=teleport.example.com" }
+# forwards = ["socks"]
+# commands = ["journalctl -f -u app", "sudo -i"]
+
+# Port forwards offered in the Cmd+K menu of hosts and tags that list them
+# [forwards.postgres]
+# kind = "local"              # "local" (-L), "remote" (-R) or "dynamic" (-D, SOCKS)
+# port = 5432                 # Listening port, 1024 or above
+# target = "localhost:5432"   # Where the forward connects; not used by "dynamic"
+#
+# [forwards.socks]
+# kind = "dynamic"
+# port = 1080
+# forward_only = true         # Hold the forward open (ssh -N) without a shell
 "#
     }
 
@@ -191,9 +1014,202 @@ case_sensitive = false
         Ok(config_dir.join("trident").join("config.toml"))
     }
 
+    /// Names of hosts marked `favorite = true`
+    pub fn favorite_hosts(&self) -> HashSet<String> {
+        self.hosts
+            .iter()
+            .filter(|(_, settings)| settings.favorite)
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+
+    /// Transport for a host: its own setting, or the configured default
+    pub fn transport_for(&self, host_name: &str) -> Transport {
+        self.hosts
+            .get(host_name)
+            .and_then(|settings| settings.transport)
+            .unwrap_or(self.transport.default)
+    }
+
+    /// Terminal profile for a host
+    ///
+    /// The first of: the `requested` profile (chosen at launch), the host's `profile`, the
+    /// first of its tags with a `profile`, `[terminal] default_profile`, and finally the
+    /// plain `[terminal]` program and args. Returns the profile name alongside, if any.
+    pub fn terminal_profile_for(
+        &self,
+        host_name: &str,
+        requested: Option<&str>,
+    ) -> Result<(Option<String>, TerminalProfile)> {
+        let host = self.hosts.get(host_name);
+        let from_tags = || {
+            host.into_iter()
+                .flat_map(|settings| &settings.tags)
+                .filter_map(|tag| self.tags.get(tag))
+                .find_map(|tag| tag.profile.as_deref())
+        };
+
+        let name = requested
+            .or_else(|| host.and_then(|settings| settings.profile.as_deref()))
+            .or_else(from_tags)
+            .or(self.terminal.default_profile.as_deref());
+
+        match name {
+            Some(name) => {
+                let profile = self.terminal.profiles.get(name).with_context(|| {
+                    format!(
+                        "Unknown terminal profile '{}' for host '{}'",
+                        name, host_name
+                    )
+                })?;
+                Ok((Some(name.to_string()), profile.clone()))
+            }
+            None => Ok((
+                None,
+                TerminalProfile {
+                    program: self.terminal.program.clone(),
+                    args: self.terminal.args.clone(),
+                    tab_args: self.terminal.tab_args.clone(),
+                    split_args: self.terminal.split_args.clone(),
+                },
+            )),
+        }
+    }
+
+    /// Session settings for a host: its own, then its first tag with one, then the global
+    ///
+    /// Returns `None` when the resolved multiplexer is `none`.
+    pub fn session_for(&self, host_name: &str) -> Option<&SessionConfig> {
+        let host = self.hosts.get(host_name);
+        let from_tags = || {
+            host.into_iter()
+                .flat_map(|settings| &settings.tags)
+                .filter_map(|tag| self.tags.get(tag))
+                .find_map(|tag| tag.session.as_ref())
+        };
+
+        let session = host
+            .and_then(|settings| settings.session.as_ref())
+            .or_else(from_tags)
+            .unwrap_or(&self.session);
+        (session.multiplexer != Multiplexer::None).then_some(session)
+    }
+
+    /// Hooks for a host: the global ones, then those of each of its tags in order
+    pub fn hooks_for(&self, host_name: &str) -> Vec<&HooksConfig> {
+        let tags = self
+            .hosts
+            .get(host_name)
+            .into_iter()
+            .flat_map(|settings| &settings.tags)
+            .filter_map(|tag| self.tags.get(tag))
+            .filter_map(|tag| tag.hooks.as_ref());
+        std::iter::once(&self.hooks).chain(tags).collect()
+    }
+
+    /// Forward presets offered for a host: its own, then its tags', without repeats
+    pub fn forwards_for(&self, host_name: &str) -> Vec<(&str, &ForwardPreset)> {
+        let host = self.hosts.get(host_name);
+        let from_tags = host
+            .into_iter()
+            .flat_map(|settings| &settings.tags)
+            .filter_map(|tag| self.tags.get(tag))
+            .flat_map(|tag| &tag.forwards);
+        let mut names: Vec<&str> = Vec::new();
+        for name in host
+            .into_iter()
+            .flat_map(|settings| &settings.forwards)
+            .chain(from_tags)
+        {
+            if !names.contains(&name.as_str()) {
+                names.push(name);
+            }
+        }
+        names
+            .into_iter()
+            .filter_map(|name| self.forwards.get_key_value(name))
+            .map(|(name, preset)| (name.as_str(), preset))
+            .collect()
+    }
+
+    /// Saved remote commands for a host from its tags, in tag order without repeats
+    pub fn commands_for(&self, host_name: &str) -> Vec<&str> {
+        let mut commands: Vec<&str> = Vec::new();
+        for command in self
+            .hosts
+            .get(host_name)
+            .into_iter()
+            .flat_map(|settings| &settings.tags)
+            .filter_map(|tag| self.tags.get(tag))
+            .flat_map(|tag| &tag.commands)
+        {
+            if !commands.contains(&command.as_str()) {
+                commands.push(command);
+            }
+        }
+        commands
+    }
+
+    /// Look up forward presets by name for a launch
+    pub fn forward_presets<'a>(
+        &'a self,
+        names: &'a [String],
+    ) -> Result<Vec<(&'a str, &'a ForwardPreset)>> {
+        names
+            .iter()
+            .map(|name| {
+                self.forwards
+                    .get(name)
+                    .map(|preset| (name.as_str(), preset))
+                    .with_context(|| format!("Unknown forward preset '{}'", name))
+            })
+            .collect()
+    }
+
+    /// Program a transport runs, or `None` for `custom` without a template
+    pub fn transport_program(&self, transport: Transport) -> Option<String> {
+        match transport {
+            Transport::Ssh => Some(self.ssh.ssh_binary.clone()),
+            Transport::Mosh => Some(self.transport.mosh_binary.clone()),
+            Transport::Et => Some(self.transport.et_binary.clone()),
+            Transport::Autossh => Some(self.transport.autossh_binary.clone()),
+            Transport::Custom => self
+                .transport
+                .custom
+                .as_deref()
+                .and_then(|template| template.split_whitespace().next())
+                .map(str::to_string),
+        }
+    }
+
+    /// Check that a transport is configured and, with `check_files`, that its program exists
+    pub fn validate_transport(&self, transport: Transport, check_files: bool) -> Result<()> {
+        let Some(program) = self.transport_program(transport) else {
+            anyhow::bail!(
+                "The custom transport needs a command template.\n\
+                Example: [transport] custom = \"ssh -p {{port}} {{user}}@{{host}}\""
+            );
+        };
+
+        if check_files && find_executable(&program).is_none() {
+            anyhow::bail!(
+                "Transport '{}' uses '{}', which was not found. Install it or set its path in [transport].",
+                transport.name(),
+                program
+            );
+        }
+        Ok(())
+    }
+
     pub fn expand_path(&mut self) -> Result<()> {
         self.ssh.known_hosts_path = expand_tilde(&self.ssh.known_hosts_path)?;
         self.ssh.config_path = expand_tilde(&self.ssh.config_path)?;
+        if let Some(dir) = &self.terminal.working_directory {
+            self.terminal.working_directory = Some(expand_tilde(dir)?);
+        }
+        if let Some(socket) = &self.agent.socket {
+            self.agent.socket = Some(expand_tilde(socket)?);
+        }
         Ok(())
     }
 
@@ -210,32 +1226,109 @@ case_sensitive = false
         }
 
         // Check if terminal program exists (only if file checks are enabled)
-        if check_files && !Path::new(&self.terminal.program).exists() {
+        if check_files && find_executable(&self.terminal.program).is_none() {
             anyhow::bail!(
                 "Terminal program '{}' does not exist. Please check the path or install the terminal.\n\
                 Common terminal paths:\n\
                 - iTerm2: /Applications/iTerm.app/Contents/MacOS/iTerm2\n\
                 - Terminal.app: /usr/bin/osascript\n\
                 - Alacritty: /Applications/Alacritty.app/Contents/MacOS/alacritty\n\
-                - Kitty: /Applications/kitty.app/Contents/MacOS/kitty",
+                - Kitty: /Applications/kitty.app/Contents/MacOS/kitty\n\
+                - Linux: gnome-terminal, konsole, foot, kitty, alacritty, wezterm or xterm (looked up on $PATH)",
                 self.terminal.program
             );
         }
 
-        // Check for {ssh_command} placeholder in args
-        let has_placeholder = self
-            .terminal
-            .args
-            .iter()
-            .any(|arg| arg.contains("{ssh_command}"));
-        if !has_placeholder && !self.terminal.args.is_empty() {
+        // Check for {ssh_argv} or {ssh_command} placeholder in args
+        if !has_ssh_placeholder(&self.terminal.args) && !self.terminal.args.is_empty() {
             anyhow::bail!(
-                "Terminal args must contain {{ssh_command}} placeholder.\n\
+                "Terminal args must contain an {{ssh_argv}} or {{ssh_command}} placeholder.\n\
                 Current args: {:?}\n\
-                Example: [\"-e\", \"{{ssh_command}}\"]",
+                Example: [\"-e\", \"{{ssh_argv}}\"]",
                 self.terminal.args
             );
         }
+        check_arg_templates("Terminal", &self.terminal.args)?;
+        check_target_args(
+            "Terminal",
+            &self.terminal.tab_args,
+            &self.terminal.split_args,
+        )?;
+
+        if check_files
+            && let Some(dir) = &self.terminal.working_directory
+            && !Path::new(dir).is_dir()
+        {
+            anyhow::bail!(
+                "Terminal working_directory '{}' is not a directory. Create it or remove the setting.",
+                dir
+            );
+        }
+
+        // Validate named terminal profiles
+        for (name, profile) in &self.terminal.profiles {
+            if profile.program.is_empty() {
+                anyhow::bail!("Terminal profile '{}' has an empty program", name);
+            }
+            if check_files && find_executable(&profile.program).is_none() {
+                anyhow::bail!(
+                    "Terminal program '{}' for profile '{}' does not exist",
+                    profile.program,
+                    name
+                );
+            }
+            if !has_ssh_placeholder(&profile.args) {
+                anyhow::bail!(
+                    "Terminal profile '{}' args must contain an {{ssh_argv}} or {{ssh_command}} placeholder.\n\
+                    Current args: {:?}",
+                    name,
+                    profile.args
+                );
+            }
+            let what = format!("Terminal profile '{}'", name);
+            check_arg_templates(&what, &profile.args)?;
+            check_target_args(&what, &profile.tab_args, &profile.split_args)?;
+        }
+
+        // Every profile a host, tag or the default refers to must exist
+        let profile_references = self
+            .terminal
+            .default_profile
+            .iter()
+            .chain(self.hosts.values().filter_map(|host| host.profile.as_ref()))
+            .chain(self.tags.values().filter_map(|tag| tag.profile.as_ref()));
+        for name in profile_references {
+            if !self.terminal.profiles.contains_key(name) {
+                anyhow::bail!(
+                    "Unknown terminal profile '{}'. Define it in a [terminal.profiles.{}] section.",
+                    name,
+                    name
+                );
+            }
+        }
+
+        // Validate forward presets, the references to them, and each host's combination
+        for (name, preset) in &self.forwards {
+            preset.validate(name)?;
+        }
+        let forward_references = self
+            .hosts
+            .values()
+            .flat_map(|host| &host.forwards)
+            .chain(self.tags.values().flat_map(|tag| &tag.forwards));
+        for name in forward_references {
+            if !self.forwards.contains_key(name) {
+                anyhow::bail!(
+                    "Unknown forward preset '{}'. Define it in a [forwards.{}] section.",
+                    name,
+                    name
+                );
+            }
+        }
+        for host_name in self.hosts.keys() {
+            check_forward_conflicts(&self.forwards_for(host_name))
+                .with_context(|| format!("Conflicting forwards for host '{}'", host_name))?;
+        }
 
         // Validate SSH configuration
         if self.ssh.ssh_binary.is_empty() {
@@ -249,6 +1342,25 @@ case_sensitive = false
             );
         }
 
+        // Check the transports used without a modifier key (ssh was checked above).
+        // The Alt+Enter alternate is optional, so it is only checked when used.
+        let mut transports: Vec<Transport> = self
+            .hosts
+            .values()
+            .filter_map(|settings| settings.transport)
+            .chain([self.transport.default])
+            .filter(|transport| *transport != Transport::Ssh)
+            .collect();
+        transports.sort_by_key(|transport| transport.name());
+        transports.dedup();
+        for transport in transports {
+            self.validate_transport(transport, check_files)?;
+        }
+
+        if self.probe.concurrency == 0 {
+            anyhow::bail!("probe concurrency must be greater than 0. Recommended value: 8");
+        }
+
         // Validate UI configuration
         if self.ui.max_results == 0 {
             anyhow::bail!("max_results must be greater than 0. Recommended value: 20");
@@ -316,13 +1428,71 @@ case_sensitive = false
         Ok(())
     }
 
-    /// Detect the best available terminal on the system
-    fn detect_best_terminal() -> DetectedTerminal {
+    /// Detect the best available terminal for the platform
+    fn detect_terminal(env: &DetectionEnv) -> DetectedTerminal {
+        if env.macos {
+            Self::detect_macos_terminal()
+        } else {
+            Self::detect_linux_terminal(env)
+        }
+    }
+
+    /// Prefer `$TERMINAL`, then the first known terminal found on `$PATH`
+    fn detect_linux_terminal(env: &DetectionEnv) -> DetectedTerminal {
+        let to_args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect();
+
+        if let Some(preferred) = env
+            .terminal
+            .as_deref()
+            .and_then(|terminal| terminal.split_whitespace().next())
+            && let Some(program) = find_executable_in(preferred, &env.path)
+        {
+            let file_name = program
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let program = program.to_string_lossy().to_string();
+
+            return match LINUX_TERMINALS.iter().find(|(_, exe, _)| *exe == file_name) {
+                Some((name, _, args)) => DetectedTerminal {
+                    name: name.to_string(),
+                    program,
+                    args: to_args(args),
+                },
+                // Unknown terminals almost all accept `-e command args...`
+                None => DetectedTerminal {
+                    name: file_name,
+                    program,
+                    args: to_args(&["-e", "{ssh_argv}"]),
+                },
+            };
+        }
+
+        for (name, exe, args) in LINUX_TERMINALS {
+            if let Some(program) = find_executable_in(exe, &env.path) {
+                return DetectedTerminal {
+                    name: name.to_string(),
+                    program: program.to_string_lossy().to_string(),
+                    args: to_args(args),
+                };
+            }
+        }
+
+        // Nothing found; xterm is the most likely to be installable
+        DetectedTerminal {
+            name: "xterm".to_string(),
+            program: "xterm".to_string(),
+            args: to_args(&["-e", "{ssh_argv}"]),
+        }
+    }
+
+    /// Detect the best available terminal among macOS application bundles
+    fn detect_macos_terminal() -> DetectedTerminal {
         let terminals = vec![
             DetectedTerminal {
                 name: "Ghostty".to_string(),
                 program: "/Applications/Ghostty.app/Contents/MacOS/ghostty".to_string(),
-                args: vec!["-e".to_string(), "sh".to_string(), "-c".to_string(), "{ssh_command}".to_string()],
+                args: vec!["-e".to_string(), "{ssh_argv}".to_string()],
             },
             DetectedTerminal {
                 name: "iTerm2".to_string(),
@@ -335,17 +1505,17 @@ case_sensitive = false
             DetectedTerminal {
                 name: "Alacritty".to_string(),
                 program: "/Applications/Alacritty.app/Contents/MacOS/alacritty".to_string(),
-                args: vec!["-e".to_string(), "sh".to_string(), "-c".to_string(), "{ssh_command}".to_string()],
+                args: vec!["-e".to_string(), "{ssh_argv}".to_string()],
             },
             DetectedTerminal {
                 name: "Kitty".to_string(),
                 program: "/Applications/kitty.app/Contents/MacOS/kitty".to_string(),
-                args: vec!["sh".to_string(), "-c".to_string(), "{ssh_command}".to_string()],
+                args: vec!["{ssh_argv}".to_string()],
             },
             DetectedTerminal {
                 name: "WezTerm".to_string(),
                 program: "/Applications/WezTerm.app/Contents/MacOS/wezterm".to_string(),
-                args: vec!["start".to_string(), "{ssh_command}".to_string()],
+                args: vec!["start".to_string(), "--".to_string(), "{ssh_argv}".to_string()],
             },
             DetectedTerminal {
                 name: "Hyper".to_string(),
@@ -381,13 +1551,29 @@ case_sensitive = false
         format!("[{}]", quoted_args.join(", "))
     }
 
-    /// Generate commented examples for other terminals
-    fn generate_terminal_examples(current_terminal: &str) -> String {
-        let examples = vec![
+    /// Generate commented examples for other terminals on the platform
+    fn generate_terminal_examples(current_terminal: &str, macos: bool) -> String {
+        if !macos {
+            return LINUX_TERMINALS
+                .iter()
+                .filter(|(name, _, _)| *name != current_terminal)
+                .map(|(_, exe, args)| {
+                    let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+                    format!(
+                        "# program = \"{}\"\n# args = {}",
+                        exe,
+                        Self::format_args_for_toml(&args)
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n\n");
+        }
+
+        let examples = [
             (
                 "Ghostty",
                 r#"# program = "/Applications/Ghostty.app/Contents/MacOS/ghostty"
-# args = ["-e", "sh", "-c", "{ssh_command}"]"#,
+# args = ["-e", "{ssh_argv}"]"#,
             ),
             (
                 "iTerm2",
@@ -402,17 +1588,17 @@ case_sensitive = false
             (
                 "Alacritty",
                 r#"# program = "/Applications/Alacritty.app/Contents/MacOS/alacritty"
-# args = ["-e", "sh", "-c", "{ssh_command}"]"#,
+# args = ["-e", "{ssh_argv}"]"#,
             ),
             (
                 "Kitty",
                 r#"# program = "/Applications/kitty.app/Contents/MacOS/kitty"
-# args = ["sh", "-c", "{ssh_command}"]"#,
+# args = ["{ssh_argv}"]"#,
             ),
             (
                 "WezTerm",
                 r#"# program = "/Applications/WezTerm.app/Contents/MacOS/wezterm"
-# args = ["start", "{ssh_command}"]"#,
+# args = ["start", "--", "{ssh_argv}"]"#,
             ),
             (
                 "Hyper",
@@ -430,18 +1616,77 @@ case_sensitive = false
     }
 }
 
-fn expand_tilde(path: &str) -> Result<String> {
-    if path.starts_with("~/") {
-        let home = dirs::home_dir().context("Failed to determine home directory")?;
-        Ok(home.join(&path[2..]).to_string_lossy().into_owned())
-    } else {
-        Ok(path.to_string())
+/// Report the first terminal arg with a malformed section or a misplaced `{ssh_argv}`
+fn check_arg_templates(what: &str, args: &[String]) -> Result<()> {
+    for (index, arg) in args.iter().enumerate() {
+        if let Err(e) = crate::ssh::template::check_arg(arg) {
+            anyhow::bail!("{} argument {} ({:?}): {}", what, index + 1, arg, e);
+        }
     }
+    Ok(())
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Tab and split args, when set, need a connection placeholder like `args`
+fn check_target_args(
+    what: &str,
+    tab_args: &Option<Vec<String>>,
+    split_args: &Option<Vec<String>>,
+) -> Result<()> {
+    for (name, args) in [("tab_args", tab_args), ("split_args", split_args)] {
+        let Some(args) = args else { continue };
+        if !has_ssh_placeholder(args) {
+            anyhow::bail!(
+                "{} {} must contain an {{ssh_argv}} or {{ssh_command}} placeholder.\n\
+                Current {}: {:?}",
+                what,
+                name,
+                name,
+                args
+            );
+        }
+        check_arg_templates(&format!("{} {}", what, name), args)?;
+    }
+    Ok(())
+}
+
+/// Whether terminal args contain a placeholder for the connection command
+fn has_ssh_placeholder(args: &[String]) -> bool {
+    args.iter()
+        .any(|arg| arg == "{ssh_argv}" || arg.contains("{ssh_command}"))
+}
+
+/// Resolve a program to an executable path, searching `$PATH` for bare names
+pub fn find_executable(program: &str) -> Option<PathBuf> {
+    let path_var = std::env::var_os("PATH").unwrap_or_default();
+    find_executable_in(program, &path_var)
+}
+
+/// Resolve a program against an explicit `PATH`-style search list
+pub fn find_executable_in(program: &str, path_var: &std::ffi::OsStr) -> Option<PathBuf> {
+    if program.is_empty() {
+        return None;
+    }
+    if program.contains('/') {
+        let path = PathBuf::from(program);
+        return path.is_file().then_some(path);
+    }
+    std::env::split_paths(path_var)
+        .map(|dir| dir.join(program))
+        .find(|candidate| candidate.is_file())
+}
+
+fn expand_tilde(path: &str) -> Result<String> {
+    if let Some(rest) = path.strip_prefix("~/") {
+        let home = dirs::home_dir().context("Failed to determine home directory")?;
+        Ok(home.join(rest).to_string_lossy().into_owned())
+    } else {
+        Ok(path.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
     #[test]
     fn test_parse_minimal_config() {
@@ -473,10 +1718,520 @@ case_sensitive = false
         );
         assert_eq!(config.terminal.args.len(), 0);
         assert_eq!(config.ssh.known_hosts_path, "~/.ssh/known_hosts");
-        assert_eq!(config.parsing.parse_known_hosts, true);
-        assert_eq!(config.parsing.skip_hashed_hosts, true); // Default value
+        assert!(config.parsing.parse_known_hosts);
+        assert!(config.parsing.skip_hashed_hosts); // Default value
         assert_eq!(config.ui.max_results, 20);
-        assert_eq!(config.ui.case_sensitive, false);
+        assert!(!config.ui.case_sensitive);
+        assert!(config.ui.smart_case); // Default value
+        assert!(config.ui.fold_diacritics); // Default value
+        assert_eq!(config.ui.scoring, ScoringStrategy::Tiered); // Default value
+        assert!(config.hosts.is_empty()); // Default value
+    }
+
+    #[test]
+    fn test_parse_host_settings() {
+        let config_str = format!(
+            "{}\n[hosts.\"prod-db\"]\nfavorite = true\n\n[hosts.staging]\n",
+            toml::to_string(&create_test_config()).unwrap()
+        );
+
+        let config = Config::load_from_str(&config_str).unwrap();
+
+        assert_eq!(config.hosts.len(), 2);
+        assert!(config.hosts["prod-db"].favorite);
+        assert!(!config.hosts["staging"].favorite);
+        assert_eq!(
+            config.favorite_hosts(),
+            HashSet::from(["prod-db".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_parse_transport_settings() {
+        let mut config = create_test_config();
+        config.transport.default = Transport::Autossh;
+        config.transport.custom = Some("tsh ssh {user}@{host}".to_string());
+        let config_str = format!(
+            "{}\n[hosts.flaky]\ntransport = \"mosh\"\n",
+            toml::to_string(&config).unwrap()
+        );
+
+        let config = Config::load_from_str(&config_str).unwrap();
+
+        assert_eq!(config.transport.default, Transport::Autossh);
+        assert_eq!(config.transport.alternate, Transport::Mosh);
+        assert_eq!(config.transport_for("flaky"), Transport::Mosh);
+        assert_eq!(config.transport_for("other"), Transport::Autossh);
+        assert_eq!(
+            config.transport_program(Transport::Custom).as_deref(),
+            Some("tsh")
+        );
+    }
+
+    #[test]
+    fn test_parse_broadcast_settings() {
+        // Older configs without the section get the defaults
+        let config_str = toml::to_string(&create_test_config()).unwrap();
+        let without_section = config_str.split("[broadcast]").next().unwrap();
+        let config = Config::load_from_str(without_section).unwrap();
+        assert_eq!(config.broadcast, BroadcastConfig::default());
+
+        let config_str = format!("{}[broadcast]\nlayout = \"tiled\"\n", without_section);
+        let config = Config::load_from_str(&config_str).unwrap();
+        assert_eq!(config.broadcast.layout, BroadcastLayout::Tiled);
+        assert_eq!(config.broadcast.confirm_above, 5);
+        assert_eq!(config.broadcast.tmux_binary, "tmux");
+    }
+
+    #[test]
+    fn test_validate_custom_transport_needs_template() {
+        let mut config = create_test_config();
+        config.transport.default = Transport::Custom;
+
+        let result = config.validate_with_file_checks(false);
+        assert!(result.unwrap_err().to_string().contains("command template"));
+
+        config.transport.custom = Some("tsh ssh {host}".to_string());
+        assert!(config.validate_with_file_checks(false).is_ok());
+    }
+
+    #[test]
+    fn test_parse_forward_presets() {
+        let config_str = format!(
+            "{}{}",
+            toml::to_string(&create_test_config()).unwrap(),
+            r#"
+[forwards.postgres]
+kind = "local"
+port = 5432
+target = "localhost:5432"
+
+[forwards.socks]
+kind = "dynamic"
+port = 1080
+forward_only = true
+
+[hosts.db]
+tags = ["prod"]
+forwards = ["postgres"]
+
+[tags.prod]
+forwards = ["socks", "postgres"]
+"#
+        );
+
+        let config = Config::load_from_str(&config_str).unwrap();
+        let names: Vec<&str> = config
+            .forwards_for("db")
+            .iter()
+            .map(|(name, _)| *name)
+            .collect();
+
+        assert_eq!(names, vec!["postgres", "socks"]);
+        assert!(config.forwards["socks"].forward_only);
+        assert!(config.forwards_for("other").is_empty());
+        assert!(config.validate_with_file_checks(false).is_ok());
+    }
+
+    #[test]
+    fn test_validate_forward_presets() {
+        let forward = |kind, port, target: Option<&str>| ForwardPreset {
+            kind,
+            port,
+            target: target.map(str::to_string),
+            forward_only: false,
+        };
+        let error = |preset: ForwardPreset| {
+            let mut config = create_test_config();
+            config.forwards.insert("test".to_string(), preset);
+            config
+                .validate_with_file_checks(false)
+                .unwrap_err()
+                .to_string()
+        };
+
+        assert!(
+            error(forward(ForwardKind::Local, 80, Some("localhost:80"))).contains("privileged")
+        );
+        assert!(error(forward(ForwardKind::Local, 5432, None)).contains("needs a target"));
+        assert!(error(forward(ForwardKind::Local, 5432, Some("localhost"))).contains("host:port"));
+        assert!(error(forward(ForwardKind::Remote, 8080, Some(":80"))).contains("host:port"));
+        assert!(error(forward(ForwardKind::Dynamic, 1080, Some("x:1"))).contains("no target"));
+        assert!(error(forward(ForwardKind::Dynamic, 0, None)).contains("greater than 0"));
+    }
+
+    #[test]
+    fn test_validate_forward_references_and_conflicts() {
+        let mut config = create_test_config();
+        config.hosts.insert(
+            "db".to_string(),
+            HostSettings {
+                forwards: vec!["postgres".to_string(), "replica".to_string()],
+                ..Default::default()
+            },
+        );
+        let result = config.validate_with_file_checks(false);
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("Unknown forward preset 'postgres'")
+        );
+
+        for name in ["postgres", "replica"] {
+            config.forwards.insert(
+                name.to_string(),
+                ForwardPreset {
+                    kind: ForwardKind::Local,
+                    port: 5432,
+                    target: Some(format!("{}:5432", name)),
+                    forward_only: false,
+                },
+            );
+        }
+        let error = format!("{:#}", config.validate_with_file_checks(false).unwrap_err());
+        assert!(
+            error.contains("both listen on local port 5432"),
+            "{}",
+            error
+        );
+
+        config.forwards.get_mut("replica").unwrap().port = 5433;
+        assert!(config.validate_with_file_checks(false).is_ok());
+    }
+
+    #[test]
+    fn test_validate_transport_binary_exists() {
+        let mut config = create_test_config();
+        config.transport.mosh_binary = "/nonexistent/mosh".to_string();
+        config.transport.et_binary = "/bin/sh".to_string();
+
+        let result = config.validate_transport(Transport::Mosh, true);
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("/nonexistent/mosh")
+        );
+        assert!(config.validate_transport(Transport::Et, true).is_ok());
+        // Without file checks only the configuration itself is validated
+        assert!(config.validate_transport(Transport::Mosh, false).is_ok());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_find_executable_in_path() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let bin = temp_dir.path().join("bin");
+        fs::create_dir(&bin).unwrap();
+        fs::write(bin.join("mosh"), "#!/bin/sh\n").unwrap();
+        let path_var = std::env::join_paths([temp_dir.path().join("empty"), bin.clone()]).unwrap();
+
+        assert_eq!(
+            find_executable_in("mosh", &path_var),
+            Some(bin.join("mosh"))
+        );
+        assert_eq!(find_executable_in("et", &path_var), None);
+        assert_eq!(find_executable_in("", &path_var), None);
+        assert_eq!(
+            find_executable_in("/bin/sh", &path_var),
+            Some(PathBuf::from("/bin/sh"))
+        );
+    }
+
+    #[test]
+    fn test_session_resolution_order() {
+        let config_str = format!(
+            "{}{}",
+            toml::to_string(&create_test_config()).unwrap().replace(
+                "[session]\nmultiplexer = \"none\"",
+                "[session]\nmultiplexer = \"screen\""
+            ),
+            r#"
+[hosts.db]
+tags = ["prod"]
+session = { multiplexer = "zellij", name = "work" }
+
+[hosts.web]
+tags = ["dev", "prod"]
+
+[hosts.plain]
+session = { multiplexer = "none" }
+
+[tags.prod]
+session = { multiplexer = "tmux" }
+"#
+        );
+
+        let config = Config::load_from_str(&config_str).unwrap();
+        let multiplexer = |host: &str| config.session_for(host).map(|s| s.multiplexer);
+
+        assert_eq!(multiplexer("db"), Some(Multiplexer::Zellij));
+        assert_eq!(config.session_for("db").unwrap().name, "work");
+        assert_eq!(multiplexer("web"), Some(Multiplexer::Tmux));
+        assert_eq!(config.session_for("web").unwrap().name, "{host}");
+        assert_eq!(multiplexer("other"), Some(Multiplexer::Screen));
+        assert_eq!(multiplexer("plain"), None);
+    }
+
+    #[test]
+    fn test_hooks_resolution_order() {
+        let config_str = format!(
+            "{}{}",
+            toml::to_string(&create_test_config()).unwrap(),
+            r#"
+[hosts.web]
+tags = ["teleport", "untagged", "prod"]
+
+[tags.teleport]
+hooks = { pre_launch = "tsh login", timeout_secs = 120 }
+
+[tags.prod]
+hooks = { post_launch = "notify" }
+"#
+        )
+        .replace(
+            "[hooks]\n",
+            "[hooks]\npre_launch = \"ssh-add -l || ssh-add\"\n",
+        );
+
+        let config = Config::load_from_str(&config_str).unwrap();
+        let hooks = config.hooks_for("web");
+
+        assert_eq!(hooks.len(), 3);
+        assert_eq!(
+            hooks[0].pre_launch.as_deref(),
+            Some("ssh-add -l || ssh-add")
+        );
+        assert_eq!(hooks[0].timeout_secs, 30);
+        assert_eq!(hooks[1].pre_launch.as_deref(), Some("tsh login"));
+        assert_eq!(hooks[1].timeout_secs, 120);
+        assert_eq!(hooks[2].post_launch.as_deref(), Some("notify"));
+        assert_eq!(config.hooks_for("other").len(), 1);
+    }
+
+    #[test]
+    fn test_saved_commands_from_tags() {
+        let config_str = format!(
+            "{}{}",
+            toml::to_string(&create_test_config()).unwrap(),
+            r#"
+[hosts.web]
+tags = ["app", "prod"]
+
+[tags.app]
+commands = ["journalctl -f -u app", "htop"]
+
+[tags.prod]
+commands = ["htop", "sudo -i"]
+"#
+        );
+
+        let config = Config::load_from_str(&config_str).unwrap();
+
+        assert_eq!(
+            config.commands_for("web"),
+            vec!["journalctl -f -u app", "htop", "sudo -i"]
+        );
+        assert!(config.commands_for("other").is_empty());
+    }
+
+    #[test]
+    fn test_generated_config_can_be_parsed() {
+        let config = Config::load_from_str(&Config::generate_default_config()).unwrap();
+        assert_eq!(config.session, SessionConfig::default());
+        assert_eq!(config.transport, TransportConfig::default());
+        assert_eq!(config.hooks, HooksConfig::default());
+        assert_eq!(config.files, FilesConfig::default());
+    }
+
+    /// A fake `PATH` directory holding empty executables with the given names
+    #[cfg(unix)]
+    fn fake_path(names: &[&str]) -> (tempfile::TempDir, std::ffi::OsString) {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        for name in names {
+            crate::test_support::write_script(temp_dir.path(), name, "");
+        }
+        let path = temp_dir.path().as_os_str().to_owned();
+        (temp_dir, path)
+    }
+
+    fn linux_env(path: std::ffi::OsString, terminal: Option<&str>) -> DetectionEnv {
+        DetectionEnv {
+            macos: false,
+            path,
+            terminal: terminal.map(str::to_string),
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_detect_linux_terminal_from_path() {
+        let (dir, path) = fake_path(&["xterm", "foot", "x-terminal-emulator"]);
+
+        let detected = Config::detect_terminal(&linux_env(path, None));
+
+        assert_eq!(detected.name, "foot");
+        assert_eq!(detected.program, dir.path().join("foot").to_string_lossy());
+        assert_eq!(detected.args, vec!["{ssh_argv}"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_detect_linux_terminal_templates() {
+        for (name, exe, args) in LINUX_TERMINALS {
+            let (_dir, path) = fake_path(&[exe]);
+
+            let detected = Config::detect_terminal(&linux_env(path, None));
+
+            assert_eq!(detected.name, *name);
+            assert_eq!(&detected.args, args);
+            assert!(detected.args.iter().any(|arg| arg == "{ssh_argv}"));
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_detect_linux_terminal_prefers_terminal_env() {
+        let (dir, path) = fake_path(&["gnome-terminal", "konsole", "myterm"]);
+
+        let detected = Config::detect_terminal(&linux_env(path.clone(), Some("konsole")));
+        assert_eq!(detected.name, "Konsole");
+        assert_eq!(detected.args, vec!["-e", "{ssh_argv}"]);
+
+        // Absolute paths and unknown terminals work too
+        let myterm = dir.path().join("myterm").to_string_lossy().to_string();
+        let detected = Config::detect_terminal(&linux_env(path.clone(), Some(&myterm)));
+        assert_eq!(detected.name, "myterm");
+        assert_eq!(detected.program, myterm);
+        assert_eq!(detected.args, vec!["-e", "{ssh_argv}"]);
+
+        // A $TERMINAL that isn't installed falls back to the search
+        let detected = Config::detect_terminal(&linux_env(path, Some("missing")));
+        assert_eq!(detected.name, "GNOME Terminal");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_detect_linux_terminal_fallback() {
+        let (_dir, path) = fake_path(&[]);
+
+        let detected = Config::detect_terminal(&linux_env(path, None));
+
+        assert_eq!(detected.program, "xterm");
+    }
+
+    #[test]
+    fn test_terminal_examples_match_platform() {
+        let linux = Config::generate_terminal_examples("Konsole", false);
+        assert!(
+            linux.contains("# program = \"gnome-terminal\"\n# args = [\"--\", \"{ssh_argv}\"]")
+        );
+        assert!(!linux.contains("konsole"));
+        assert!(!linux.contains("/Applications"));
+
+        let macos = Config::generate_terminal_examples("Ghostty", true);
+        assert!(macos.contains("/Applications/iTerm.app"));
+        assert!(!macos.contains("gnome-terminal"));
+    }
+
+    #[test]
+    fn test_parse_terminal_profiles() {
+        let config_str = r#"
+[terminal]
+program = "/usr/bin/xterm"
+args = ["-e", "{ssh_argv}"]
+default_profile = "kitty-tab"
+
+[terminal.profiles.iterm-red]
+program = "/Applications/iTerm.app/Contents/MacOS/iTerm2"
+args = ["-c", "create window with profile \"Production\" command \"{ssh_command}\""]
+tab_args = ["-c", "create tab with profile \"Production\" command \"{ssh_command}\""]
+
+[terminal.profiles.kitty-tab]
+program = "/usr/bin/kitty"
+args = ["{ssh_argv}"]
+
+[ssh]
+known_hosts_path = "~/.ssh/known_hosts"
+config_path = "~/.ssh/config"
+ssh_binary = "/usr/bin/ssh"
+
+[parsing]
+parse_known_hosts = true
+parse_ssh_config = true
+simple_config_parsing = true
+
+[ui]
+max_results = 20
+case_sensitive = false
+
+[hosts.prod-db]
+profile = "iterm-red"
+"#;
+
+        let config = Config::load_from_str(config_str).unwrap();
+
+        assert_eq!(config.terminal.profiles.len(), 2);
+        assert!(config.validate_with_file_checks(false).is_ok());
+
+        let (name, profile) = config.terminal_profile_for("prod-db", None).unwrap();
+        assert_eq!(name.as_deref(), Some("iterm-red"));
+        assert_eq!(
+            profile.program,
+            "/Applications/iTerm.app/Contents/MacOS/iTerm2"
+        );
+        assert_eq!(profile.tab_args.unwrap().len(), 2);
+        assert_eq!(profile.split_args, None);
+
+        let (name, _) = config.terminal_profile_for("other", None).unwrap();
+        assert_eq!(name.as_deref(), Some("kitty-tab"));
+    }
+
+    #[test]
+    fn test_validate_target_args_need_placeholder() {
+        let mut config = create_test_config();
+        config.terminal.split_args = Some(vec!["split-window".to_string()]);
+
+        let error = config.validate_with_file_checks(false).unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("Terminal split_args must contain")
+        );
+    }
+
+    #[test]
+    fn test_validate_unknown_terminal_profile() {
+        let mut config = create_test_config();
+        config.hosts.insert(
+            "prod-db".to_string(),
+            HostSettings {
+                profile: Some("iterm-red".to_string()),
+                ..Default::default()
+            },
+        );
+
+        let result = config.validate_with_file_checks(false);
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("Unknown terminal profile 'iterm-red'")
+        );
+    }
+
+    #[test]
+    fn test_parse_scoring_strategy() {
+        let mut config = create_test_config();
+        config.ui.scoring = ScoringStrategy::Fzf;
+
+        let serialized = toml::to_string(&config).unwrap();
+        assert!(serialized.contains("scoring = \"fzf\""));
+        assert_eq!(Config::load_from_str(&serialized).unwrap(), config);
+
+        let invalid = serialized.replace("scoring = \"fzf\"", "scoring = \"bogus\"");
+        assert!(Config::load_from_str(&invalid).is_err());
     }
 
     #[test]
@@ -509,10 +2264,10 @@ case_sensitive = true
             config.terminal.args,
             vec!["-e", "tell app \"Terminal\" to do script \"{ssh_command}\""]
         );
-        assert_eq!(config.parsing.parse_ssh_config, false);
-        assert_eq!(config.parsing.skip_hashed_hosts, false);
+        assert!(!config.parsing.parse_ssh_config);
+        assert!(!config.parsing.skip_hashed_hosts);
         assert_eq!(config.ui.max_results, 50);
-        assert_eq!(config.ui.case_sensitive, true);
+        assert!(config.ui.case_sensitive);
     }
 
     #[test]
@@ -615,6 +2370,75 @@ case_sensitive = false
         assert_eq!(config.ssh.ssh_binary, "/usr/bin/ssh");
     }
 
+    #[test]
+    fn test_terminal_env_settings() {
+        let config_str = r#"
+[terminal]
+program = "/bin/sh"
+args = ["-c", "{ssh_command}"]
+inherit_login_shell_env = true
+working_directory = "~/src"
+
+[terminal.env]
+LANG = "en_US.UTF-8"
+
+[ssh]
+known_hosts_path = "~/.ssh/known_hosts"
+config_path = "~/.ssh/config"
+ssh_binary = "/usr/bin/ssh"
+
+[parsing]
+parse_known_hosts = true
+parse_ssh_config = true
+simple_config_parsing = true
+
+[ui]
+max_results = 20
+case_sensitive = false
+"#;
+
+        let mut config = Config::load_from_str(config_str).unwrap();
+        config.expand_path().unwrap();
+
+        assert!(config.terminal.inherit_login_shell_env);
+        assert_eq!(config.terminal.env.get("LANG").unwrap(), "en_US.UTF-8");
+        assert_eq!(
+            config.terminal.working_directory.as_deref().unwrap(),
+            dirs::home_dir().unwrap().join("src").to_string_lossy()
+        );
+
+        config.terminal.working_directory = Some("/nonexistent/trident-dir".to_string());
+        let error = config.validate_with_file_checks(true).unwrap_err();
+        assert!(error.to_string().contains("is not a directory"));
+    }
+
+    #[test]
+    fn test_host_keys_settings() {
+        let config = create_test_config();
+        assert_eq!(config.host_keys.keyscan_binary, "ssh-keyscan");
+        assert_eq!(config.host_keys.timeout_secs, 5);
+
+        let host_keys: HostKeysConfig =
+            toml::from_str(r#"keyscan_binary = "/opt/homebrew/bin/ssh-keyscan""#).unwrap();
+        assert_eq!(host_keys.keyscan_binary, "/opt/homebrew/bin/ssh-keyscan");
+        assert_eq!(host_keys.timeout_secs, 5);
+    }
+
+    #[test]
+    fn test_probe_settings() {
+        let mut config = create_test_config();
+        assert_eq!(config.probe, ProbeConfig::default());
+        assert!(config.probe.enabled);
+
+        let probe: ProbeConfig = toml::from_str("timeout_ms = 300").unwrap();
+        assert_eq!(probe.timeout_ms, 300);
+        assert_eq!(probe.concurrency, 8);
+
+        config.probe.concurrency = 0;
+        let error = config.validate_with_file_checks(false).unwrap_err();
+        assert!(error.to_string().contains("probe concurrency"));
+    }
+
     #[test]
     fn test_default_config_path() {
         let path = Config::default_config_path().unwrap();
@@ -652,6 +2476,67 @@ case_sensitive = false
         );
     }
 
+    #[test]
+    fn test_validate_accepts_ssh_argv_placeholder() {
+        let mut config = create_test_config();
+        config.terminal.args = vec!["-e".to_string(), "{ssh_argv}".to_string()];
+
+        assert!(config.validate_with_file_checks(false).is_ok());
+    }
+
+    #[test]
+    fn test_validate_reports_section_error_position() {
+        let mut config = create_test_config();
+        config.terminal.args = vec![
+            "--title={alias}".to_string(),
+            "--class={?tag}{tag}{/user}".to_string(),
+            "{ssh_argv}".to_string(),
+        ];
+
+        let error = config.validate_with_file_checks(false).unwrap_err();
+        assert!(error.to_string().starts_with(
+            "Terminal argument 2 (\"--class={?tag}{tag}{/user}\"): '{/user}' closes '{?tag}'"
+        ));
+        assert!(error.to_string().contains("at column 20"));
+
+        config.terminal.args = vec!["{?user}-l {user}".to_string(), "{ssh_argv}".to_string()];
+        let error = config.validate_with_file_checks(false).unwrap_err();
+        assert!(error.to_string().contains("'{?user}' is never closed"));
+
+        config.terminal.args = vec!["--title={ssh_comand}".to_string(), "{ssh_argv}".to_string()];
+        let error = config.validate_with_file_checks(false).unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("Unknown placeholder '{ssh_comand}'")
+        );
+        assert!(error.to_string().contains("at column 9"));
+
+        config.terminal.args = vec![
+            "--title={{{alias}}} {?user}{user}@{/user}{host}:{port}".to_string(),
+            "{ssh_argv}".to_string(),
+        ];
+        assert!(config.validate_with_file_checks(false).is_ok());
+    }
+
+    #[test]
+    fn test_validate_keeps_literal_braces_in_existing_configs() {
+        // Braces that aren't placeholders loaded before placeholders existed
+        let toml = r#"
+program = "/usr/bin/osascript"
+args = [
+    "-e",
+    "tell app \"Terminal\" to do script \"echo ${HOME}; {ssh_command}\"",
+    "-e",
+    "tell app \"Terminal\" to set bounds of front window to {0, 0, 800, 600}",
+]
+"#;
+        let mut config = create_test_config();
+        config.terminal = toml::from_str(toml).unwrap();
+
+        assert!(config.validate_with_file_checks(false).is_ok());
+    }
+
     #[test]
     fn test_validate_zero_max_results() {
         let mut config = create_test_config();
@@ -708,6 +2593,13 @@ case_sensitive = false
             terminal: TerminalConfig {
                 program: "/usr/bin/terminal".to_string(),
                 args: vec!["-e".to_string(), "{ssh_command}".to_string()],
+                default_profile: None,
+                profiles: BTreeMap::new(),
+                env: BTreeMap::new(),
+                inherit_login_shell_env: false,
+                working_directory: None,
+                tab_args: None,
+                split_args: None,
             },
             ssh: SshConfig {
                 known_hosts_path: "~/.ssh/known_hosts".to_string(),
@@ -723,7 +2615,21 @@ case_sensitive = false
             ui: UiConfig {
                 max_results: 20,
                 case_sensitive: false,
+                smart_case: true,
+                fold_diacritics: true,
+                scoring: ScoringStrategy::Tiered,
             },
+            transport: TransportConfig::default(),
+            session: SessionConfig::default(),
+            broadcast: BroadcastConfig::default(),
+            hooks: HooksConfig::default(),
+            files: FilesConfig::default(),
+            agent: AgentConfig::default(),
+            probe: ProbeConfig::default(),
+            host_keys: HostKeysConfig::default(),
+            forwards: BTreeMap::new(),
+            hosts: BTreeMap::new(),
+            tags: BTreeMap::new(),
         }
     }
 }
@@ -1,107 +1,741 @@
 // ABOUTME: Fuzzy search implementation for matching user queries against SSH host entries
 // ABOUTME: Optimized for sub-50ms search performance with support for case-insensitive matching
 
+use crate::config::{ScoringStrategy, UiConfig};
 use crate::ssh::parser::HostEntry;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+/// How a query is compared against host names
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MatchOptions {
+    /// Always compare case-sensitively
+    pub case_sensitive: bool,
+    /// Compare case-sensitively only when the query contains an uppercase character
+    pub smart_case: bool,
+    /// Treat accented Latin letters as their base letter (é matches e)
+    pub fold_diacritics: bool,
+}
+
+impl MatchOptions {
+    pub fn from_ui_config(ui: &UiConfig) -> Self {
+        Self {
+            case_sensitive: ui.case_sensitive,
+            smart_case: ui.smart_case,
+            fold_diacritics: ui.fold_diacritics,
+        }
+    }
+
+    /// Resolve whether a particular query should be matched case-sensitively
+    pub fn is_case_sensitive_for(&self, query: &str) -> bool {
+        self.case_sensitive || (self.smart_case && query.chars().any(char::is_uppercase))
+    }
+
+    /// Whether `text` starts with `query`, normalized the same way searches compare them
+    pub fn is_prefix(&self, query: &str, text: &str) -> bool {
+        let case_sensitive = self.is_case_sensitive_for(query);
+        normalize(text, case_sensitive, self.fold_diacritics).starts_with(&normalize(
+            query,
+            case_sensitive,
+            self.fold_diacritics,
+        ))
+    }
+}
+
+/// Which kind of match produced a score
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchTier {
+    /// Empty query, every host listed without scoring
+    Unfiltered,
+    Exact,
+    Prefix,
+    Contains,
+    /// Query characters appear in order with gaps
+    Fuzzy,
+    /// fzf-style alignment score
+    Alignment,
+}
+
+impl fmt::Display for MatchTier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            MatchTier::Unfiltered => "unfiltered",
+            MatchTier::Exact => "exact",
+            MatchTier::Prefix => "prefix",
+            MatchTier::Contains => "contains",
+            MatchTier::Fuzzy => "fuzzy",
+            MatchTier::Alignment => "alignment",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Which part of a host entry a query matched
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MatchField {
+    /// The host alias shown in the list
+    #[default]
+    Alias,
+    /// Resolved `HostName` from the SSH config
+    HostName,
+    User,
+    /// `# comment` above the Host block
+    Description,
+}
+
+impl MatchField {
+    /// Fields searched for every host, in order of preference
+    pub const ALL: [MatchField; 4] = [
+        MatchField::Alias,
+        MatchField::HostName,
+        MatchField::User,
+        MatchField::Description,
+    ];
+
+    /// Percentage of the match score kept for a match in this field
+    pub fn weight(self) -> usize {
+        match self {
+            MatchField::Alias => 100,
+            MatchField::HostName => 80,
+            MatchField::User => 60,
+            MatchField::Description => 50,
+        }
+    }
+
+    /// The text of this field for a host, if it has one
+    pub fn value_of(self, host: &HostEntry) -> Option<&str> {
+        match self {
+            MatchField::Alias => Some(&host.name),
+            MatchField::HostName => host.hostname.as_deref(),
+            MatchField::User => host.user.as_deref(),
+            MatchField::Description => host.description.as_deref(),
+        }
+    }
+}
+
+impl fmt::Display for MatchField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            MatchField::Alias => "alias",
+            MatchField::HostName => "hostname",
+            MatchField::User => "user",
+            MatchField::Description => "description",
+        };
+        f.write_str(name)
+    }
+}
+
+/// How a host's score was put together, for debugging odd rankings
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScoreBreakdown {
+    /// Field the query matched; secondary fields keep only part of their score
+    pub field: MatchField,
+    pub tier: MatchTier,
+    pub base: usize,
+    pub length_bonus: usize,
+    pub boundary_bonus: usize,
+    pub consecutive_bonus: usize,
+    /// Deductions for late or gappy matches
+    pub penalty: usize,
+    pub frecency: usize,
+    /// Character indices in the matched field that matched the query
+    pub positions: Vec<usize>,
+}
+
+impl ScoreBreakdown {
+    fn new(tier: MatchTier, base: usize, positions: Vec<usize>) -> Self {
+        Self {
+            field: MatchField::Alias,
+            tier,
+            base,
+            length_bonus: 0,
+            boundary_bonus: 0,
+            consecutive_bonus: 0,
+            penalty: 0,
+            frecency: 0,
+            positions,
+        }
+    }
+
+    /// Score from the match alone; always positive for a match
+    pub fn match_score(&self) -> usize {
+        if self.tier == MatchTier::Unfiltered {
+            return 0;
+        }
+        let raw = (self.base + self.length_bonus + self.boundary_bonus + self.consecutive_bonus)
+            .saturating_sub(self.penalty);
+        (raw * self.field.weight() / 100).max(1)
+    }
+
+    pub fn total(&self) -> usize {
+        self.match_score() + self.frecency
+    }
+
+    /// Match quality before bonuses: the tier base weighted by field
+    ///
+    /// Results are grouped by this first, so frecency can only reorder hosts that matched
+    /// equally well and never lifts a prefix match above an exact one.
+    pub fn quality(&self) -> usize {
+        if self.tier == MatchTier::Unfiltered {
+            return 0;
+        }
+        self.base * self.field.weight() / 100
+    }
+}
+
+impl fmt::Display for ScoreBreakdown {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "total={} field={} tier={} base={} length=+{} boundary=+{} consecutive=+{} penalty=-{} frecency=+{} positions={:?}",
+            self.total(),
+            self.field,
+            self.tier,
+            self.base,
+            self.length_bonus,
+            self.boundary_bonus,
+            self.consecutive_bonus,
+            self.penalty,
+            self.frecency,
+            self.positions
+        )
+    }
+}
+
+/// A strategy for ranking a host name against a query
+///
+/// Both inputs are already normalized (case and diacritics), one `char` per character.
+/// Implementations return `None` when the query does not match; higher totals rank first.
+pub trait Scorer: Send + Sync {
+    fn explain(&self, target: &[char], query: &[char]) -> Option<ScoreBreakdown>;
+
+    /// Match score, or 0 when the query does not match
+    fn score(&self, target: &[char], query: &[char]) -> usize {
+        self.explain(target, query)
+            .map_or(0, |breakdown| breakdown.match_score())
+    }
+}
+
+/// Default scorer: exact, prefix, substring and subsequence matches in fixed tiers
+pub struct TieredScorer;
+
+impl Scorer for TieredScorer {
+    fn explain(&self, target: &[char], query: &[char]) -> Option<ScoreBreakdown> {
+        explain_tiered_score(target, query)
+    }
+}
+
+/// fzf-style scorer: Smith-Waterman-like local alignment with boundary and gap scoring
+///
+/// Finds the best-scoring placement of the query characters in order, rewarding
+/// matches at word boundaries and consecutive runs, and penalizing gaps between them.
+pub struct FzfScorer;
+
+const FZF_SCORE_MATCH: i32 = 16;
+const FZF_GAP_START: i32 = -3;
+const FZF_GAP_EXTENSION: i32 = -1;
+const FZF_BONUS_BOUNDARY: i32 = FZF_SCORE_MATCH / 2;
+const FZF_BONUS_CAMEL: i32 = FZF_BONUS_BOUNDARY + FZF_GAP_EXTENSION;
+const FZF_BONUS_CONSECUTIVE: i32 = -(FZF_GAP_START + FZF_GAP_EXTENSION);
+const FZF_BONUS_FIRST_CHAR_MULTIPLIER: i32 = 2;
+
+/// One dynamic-programming cell of the fzf alignment
+#[derive(Clone, Copy)]
+struct FzfCell {
+    score: i32,
+    /// Bonus of the first character in the consecutive run ending here
+    chunk_bonus: i32,
+    /// Bonus actually applied to this character
+    bonus: i32,
+    /// Gap penalty paid to reach this character (zero or negative)
+    gap: i32,
+    /// Target index of the previous query character's match
+    from: Option<usize>,
+}
+
+impl FzfScorer {
+    /// Bonus for matching the character at `index`, based on the character before it
+    fn position_bonus(target: &[char], index: usize) -> i32 {
+        let current = target[index];
+        if !current.is_alphanumeric() {
+            return FZF_BONUS_BOUNDARY;
+        }
+        match index.checked_sub(1).map(|i| target[i]) {
+            None => FZF_BONUS_BOUNDARY,
+            Some(prev) if !prev.is_alphanumeric() => FZF_BONUS_BOUNDARY,
+            Some(prev) if prev.is_lowercase() && current.is_uppercase() => FZF_BONUS_CAMEL,
+            Some(prev) if !prev.is_ascii_digit() && current.is_ascii_digit() => FZF_BONUS_CAMEL,
+            Some(_) => 0,
+        }
+    }
+}
+
+impl Scorer for FzfScorer {
+    fn explain(&self, target: &[char], query: &[char]) -> Option<ScoreBreakdown> {
+        if query.is_empty() || query.len() > target.len() {
+            return None;
+        }
+
+        let bonuses: Vec<i32> = (0..target.len())
+            .map(|j| Self::position_bonus(target, j))
+            .collect();
+
+        // rows[i][j]: best alignment with query[i] matched at target[j]
+        let mut rows: Vec<Vec<Option<FzfCell>>> = Vec::with_capacity(query.len());
+
+        for (i, &qc) in query.iter().enumerate() {
+            let mut row: Vec<Option<FzfCell>> = vec![None; target.len()];
+            // Best previous-row cell reachable across a gap ending just before j: (score, index)
+            let mut gap_best: Option<(i32, usize)> = None;
+
+            for j in 0..target.len() {
+                if i > 0 && j >= 2 {
+                    let extended = gap_best.map(|(g, k)| (g + FZF_GAP_EXTENSION, k));
+                    let started =
+                        rows[i - 1][j - 2].map(|cell| (cell.score + FZF_GAP_START, j - 2));
+                    gap_best = match (extended, started) {
+                        (Some(e), Some(s)) if s.0 > e.0 => Some(s),
+                        (Some(e), _) => Some(e),
+                        (None, s) => s,
+                    };
+                }
+
+                if target[j] != qc {
+                    continue;
+                }
+
+                if i == 0 {
+                    let bonus = bonuses[j] * FZF_BONUS_FIRST_CHAR_MULTIPLIER;
+                    row[j] = Some(FzfCell {
+                        score: FZF_SCORE_MATCH + bonus,
+                        chunk_bonus: bonuses[j],
+                        bonus,
+                        gap: 0,
+                        from: None,
+                    });
+                    continue;
+                }
+
+                // Characters in a consecutive run inherit the bonus of the run's first character
+                let consecutive = j
+                    .checked_sub(1)
+                    .and_then(|prev| rows[i - 1][prev])
+                    .map(|cell| {
+                        let bonus =
+                            if bonuses[j] >= FZF_BONUS_BOUNDARY && bonuses[j] > cell.chunk_bonus {
+                                bonuses[j]
+                            } else {
+                                bonuses[j].max(cell.chunk_bonus).max(FZF_BONUS_CONSECUTIVE)
+                            };
+                        FzfCell {
+                            score: cell.score + FZF_SCORE_MATCH + bonus,
+                            chunk_bonus: bonus,
+                            bonus,
+                            gap: 0,
+                            from: Some(j - 1),
+                        }
+                    });
+                let gapped = gap_best.map(|(g, k)| {
+                    let previous = rows[i - 1][k].map_or(0, |cell| cell.score);
+                    FzfCell {
+                        score: g + FZF_SCORE_MATCH + bonuses[j],
+                        chunk_bonus: bonuses[j],
+                        bonus: bonuses[j],
+                        gap: g - previous,
+                        from: Some(k),
+                    }
+                });
+
+                row[j] = match (consecutive, gapped) {
+                    (Some(c), Some(g)) if g.score > c.score => Some(g),
+                    (Some(c), _) => Some(c),
+                    (None, g) => g,
+                };
+            }
+
+            rows.push(row);
+        }
+
+        // Pick the best final cell, preferring the earliest on ties
+        let last = rows.last()?;
+        let mut end: Option<usize> = None;
+        for (j, cell) in last.iter().enumerate() {
+            if let Some(cell) = cell
+                && end.is_none_or(|e| cell.score > last[e].map_or(i32::MIN, |c| c.score))
+            {
+                end = Some(j);
+            }
+        }
+
+        // Walk back through the alignment to recover positions and bonus components
+        let mut breakdown = ScoreBreakdown::new(
+            MatchTier::Alignment,
+            FZF_SCORE_MATCH as usize * query.len(),
+            Vec::with_capacity(query.len()),
+        );
+        let mut cursor = end;
+        for row in rows.iter().rev() {
+            let j = cursor?;
+            let cell = row[j]?;
+            let position_bonus = if cell.from.is_none() {
+                bonuses[j] * FZF_BONUS_FIRST_CHAR_MULTIPLIER
+            } else {
+                bonuses[j]
+            };
+            breakdown.positions.push(j);
+            breakdown.boundary_bonus += position_bonus as usize;
+            breakdown.consecutive_bonus += (cell.bonus - position_bonus) as usize;
+            breakdown.penalty += (-cell.gap) as usize;
+            cursor = cell.from;
+        }
+        breakdown.positions.reverse();
+
+        Some(breakdown)
+    }
+}
+
+/// Build the scorer selected in configuration
+pub fn scorer_for(strategy: ScoringStrategy) -> Box<dyn Scorer> {
+    match strategy {
+        ScoringStrategy::Tiered => Box::new(TieredScorer),
+        ScoringStrategy::Fzf => Box::new(FzfScorer),
+    }
+}
+
+/// A search result together with how its score was computed
+#[derive(Debug, Clone)]
+pub struct RankedHost<'a> {
+    pub host: &'a HostEntry,
+    pub breakdown: ScoreBreakdown,
+}
 
 pub struct SearchEngine {
     hosts: Vec<HostEntry>,
+    scorer: Box<dyn Scorer>,
+    frecency: HashMap<String, usize>,
+    favorites: HashSet<String>,
 }
 
 impl SearchEngine {
     pub fn new(hosts: Vec<HostEntry>) -> Self {
-        Self { hosts }
+        Self {
+            hosts,
+            scorer: Box::new(TieredScorer),
+            frecency: HashMap::new(),
+            favorites: HashSet::new(),
+        }
     }
 
+    /// Replace the scoring strategy (defaults to `TieredScorer`)
+    pub fn with_scorer(mut self, scorer: Box<dyn Scorer>) -> Self {
+        self.scorer = scorer;
+        self
+    }
+
+    /// Add per-host frecency points (keyed by host name) on top of match scores
+    pub fn with_frecency(mut self, frecency: HashMap<String, usize>) -> Self {
+        self.frecency = frecency;
+        self
+    }
+
+    /// Host names that win ties against non-favorites with the same score
+    pub fn with_favorites(mut self, favorites: HashSet<String>) -> Self {
+        self.favorites = favorites;
+        self
+    }
+
+    /// Search hosts, best match first
+    ///
+    /// Ordering is fully deterministic. Results are grouped by match quality (tier and field),
+    /// then ranked by total score (match score plus frecency) within a group; equal scores
+    /// are ordered by frecency, then favorites first, then shorter
+    /// names (in characters), then alphabetically. An empty query lists hosts in the same
+    /// tie-break order, so the most frequently and recently used hosts come first.
     pub fn search(&self, query: &str, case_sensitive: bool, max_results: usize) -> Vec<&HostEntry> {
+        let options = MatchOptions {
+            case_sensitive,
+            ..MatchOptions::default()
+        };
+        self.search_with_options(query, &options, max_results)
+    }
+
+    /// Search with explicit match options; ordered as described on [`SearchEngine::search`]
+    pub fn search_with_options(
+        &self,
+        query: &str,
+        options: &MatchOptions,
+        max_results: usize,
+    ) -> Vec<&HostEntry> {
+        self.explain(query, options, max_results)
+            .into_iter()
+            .map(|ranked| ranked.host)
+            .collect()
+    }
+
+    /// Search and return each result with its score breakdown
+    pub fn explain(
+        &self,
+        query: &str,
+        options: &MatchOptions,
+        max_results: usize,
+    ) -> Vec<RankedHost<'_>> {
         if query.is_empty() {
             // Return all hosts up to max_results
-            return self.hosts.iter().take(max_results).collect();
+            let mut results: Vec<RankedHost<'_>> = self
+                .hosts
+                .iter()
+                .map(|host| RankedHost {
+                    host,
+                    breakdown: self.with_host_frecency(
+                        host,
+                        ScoreBreakdown::new(MatchTier::Unfiltered, 0, Vec::new()),
+                    ),
+                })
+                .collect();
+            results.sort_by(|a, b| self.compare_ranked(a, b));
+            results.truncate(max_results);
+            return results;
         }
 
-        let query_lower = if case_sensitive {
-            query.to_string()
-        } else {
-            query.to_lowercase()
-        };
+        let case_sensitive = options.is_case_sensitive_for(query);
+        let query_normalized = normalize(query, case_sensitive, options.fold_diacritics);
 
-        let mut results: Vec<(&HostEntry, usize)> = self
+        let mut results: Vec<RankedHost<'_>> = self
             .hosts
             .iter()
             .filter_map(|host| {
-                let score = calculate_fuzzy_score(&host.name, &query_lower, case_sensitive);
-                if score > 0 { Some((host, score)) } else { None }
+                let breakdown = self.best_field_match(
+                    host,
+                    &query_normalized,
+                    case_sensitive,
+                    options.fold_diacritics,
+                )?;
+                Some(RankedHost {
+                    host,
+                    breakdown: self.with_host_frecency(host, breakdown),
+                })
             })
             .collect();
 
-        // Sort by score (higher is better)
-        results.sort_by(|a, b| b.1.cmp(&a.1));
+        // Sort by score (higher is better), with a total tie-break order
+        results.sort_by(|a, b| self.compare_ranked(a, b));
 
-        // Return only the entries, limited by max_results
+        results.truncate(max_results);
         results
-            .into_iter()
-            .take(max_results)
-            .map(|(entry, _)| entry)
-            .collect()
+    }
+
+    /// Score every searchable field of a host and keep the best weighted match
+    ///
+    /// On equal scores the earlier field in [`MatchField::ALL`] wins, so alias matches
+    /// are reported in preference to secondary fields.
+    fn best_field_match(
+        &self,
+        host: &HostEntry,
+        query: &[char],
+        case_sensitive: bool,
+        fold_diacritics: bool,
+    ) -> Option<ScoreBreakdown> {
+        let mut best: Option<ScoreBreakdown> = None;
+        for field in MatchField::ALL {
+            let Some(value) = field.value_of(host) else {
+                continue;
+            };
+            let target = normalize(value, case_sensitive, fold_diacritics);
+            let Some(mut breakdown) = self.scorer.explain(&target, query) else {
+                continue;
+            };
+            breakdown.field = field;
+            if best
+                .as_ref()
+                .is_none_or(|current| breakdown.match_score() > current.match_score())
+            {
+                best = Some(breakdown);
+            }
+        }
+        best
+    }
+
+    /// Total order over results: score, frecency, favorite, shorter name, then alphabetical
+    fn compare_ranked(&self, a: &RankedHost<'_>, b: &RankedHost<'_>) -> Ordering {
+        let is_favorite = |ranked: &RankedHost<'_>| self.favorites.contains(&ranked.host.name);
+
+        b.breakdown
+            .quality()
+            .cmp(&a.breakdown.quality())
+            .then_with(|| b.breakdown.total().cmp(&a.breakdown.total()))
+            .then_with(|| b.breakdown.frecency.cmp(&a.breakdown.frecency))
+            .then_with(|| is_favorite(b).cmp(&is_favorite(a)))
+            .then_with(|| {
+                a.host
+                    .name
+                    .chars()
+                    .count()
+                    .cmp(&b.host.name.chars().count())
+            })
+            .then_with(|| a.host.name.cmp(&b.host.name))
+    }
+
+    fn with_host_frecency(
+        &self,
+        host: &HostEntry,
+        mut breakdown: ScoreBreakdown,
+    ) -> ScoreBreakdown {
+        breakdown.frecency = self.frecency.get(&host.name).copied().unwrap_or(0);
+        breakdown
     }
 }
 
-fn calculate_fuzzy_score(target: &str, query: &str, case_sensitive: bool) -> usize {
-    let target_normalized = if case_sensitive {
-        target.to_string()
-    } else {
-        target.to_lowercase()
-    };
+/// Render ranked results with their score breakdowns, one host per block
+pub fn format_explanation(results: &[RankedHost<'_>]) -> String {
+    results
+        .iter()
+        .enumerate()
+        .map(|(rank, ranked)| {
+            format!(
+                "{:>3}. {}\n     {}\n",
+                rank + 1,
+                ranked.host.name,
+                ranked.breakdown
+            )
+        })
+        .collect()
+}
 
-    let query_normalized = if case_sensitive {
-        query.to_string()
-    } else {
-        query.to_lowercase()
-    };
+/// Normalize text into characters for comparison
+///
+/// Every input character maps to exactly one output character, so indices into the
+/// result are character positions in the original text.
+fn normalize(text: &str, case_sensitive: bool, fold_diacritics: bool) -> Vec<char> {
+    text.chars()
+        .map(|c| {
+            let c = if fold_diacritics {
+                fold_diacritic(c)
+            } else {
+                c
+            };
+            if case_sensitive {
+                c
+            } else {
+                // Multi-char lowercase forms (e.g. U+0130) keep only their base letter
+                c.to_lowercase().next().unwrap_or(c)
+            }
+        })
+        .collect()
+}
+
+/// Map precomposed Latin letters with diacritics to their base letter
+fn fold_diacritic(c: char) -> char {
+    match c {
+        'À'..='Å' | 'Ā' | 'Ă' | 'Ą' => 'A',
+        'à'..='å' | 'ā' | 'ă' | 'ą' => 'a',
+        'Ç' | 'Ć' | 'Ĉ' | 'Ċ' | 'Č' => 'C',
+        'ç' | 'ć' | 'ĉ' | 'ċ' | 'č' => 'c',
+        'Ď' | 'Đ' => 'D',
+        'ď' | 'đ' => 'd',
+        'È'..='Ë' | 'Ē' | 'Ĕ' | 'Ė' | 'Ę' | 'Ě' => 'E',
+        'è'..='ë' | 'ē' | 'ĕ' | 'ė' | 'ę' | 'ě' => 'e',
+        'Ĝ' | 'Ğ' | 'Ġ' | 'Ģ' => 'G',
+        'ĝ' | 'ğ' | 'ġ' | 'ģ' => 'g',
+        'Ĥ' | 'Ħ' => 'H',
+        'ĥ' | 'ħ' => 'h',
+        'Ì'..='Ï' | 'Ĩ' | 'Ī' | 'Ĭ' | 'Į' | 'İ' => 'I',
+        'ì'..='ï' | 'ĩ' | 'ī' | 'ĭ' | 'į' | 'ı' => 'i',
+        'Ĵ' => 'J',
+        'ĵ' => 'j',
+        'Ķ' => 'K',
+        'ķ' => 'k',
+        'Ĺ' | 'Ļ' | 'Ľ' | 'Ŀ' | 'Ł' => 'L',
+        'ĺ' | 'ļ' | 'ľ' | 'ŀ' | 'ł' => 'l',
+        'Ñ' | 'Ń' | 'Ņ' | 'Ň' => 'N',
+        'ñ' | 'ń' | 'ņ' | 'ň' => 'n',
+        'Ò'..='Ö' | 'Ø' | 'Ō' | 'Ŏ' | 'Ő' => 'O',
+        'ò'..='ö' | 'ø' | 'ō' | 'ŏ' | 'ő' => 'o',
+        'Ŕ' | 'Ŗ' | 'Ř' => 'R',
+        'ŕ' | 'ŗ' | 'ř' => 'r',
+        'Ś' | 'Ŝ' | 'Ş' | 'Š' => 'S',
+        'ś' | 'ŝ' | 'ş' | 'š' => 's',
+        'Ţ' | 'Ť' | 'Ŧ' => 'T',
+        'ţ' | 'ť' | 'ŧ' => 't',
+        'Ù'..='Ü' | 'Ũ' | 'Ū' | 'Ŭ' | 'Ů' | 'Ű' | 'Ų' => 'U',
+        'ù'..='ü' | 'ũ' | 'ū' | 'ŭ' | 'ů' | 'ű' | 'ų' => 'u',
+        'Ŵ' => 'W',
+        'ŵ' => 'w',
+        'Ý' | 'Ŷ' | 'Ÿ' => 'Y',
+        'ý' | 'ÿ' | 'ŷ' => 'y',
+        'Ź' | 'Ż' | 'Ž' => 'Z',
+        'ź' | 'ż' | 'ž' => 'z',
+        _ => c,
+    }
+}
 
+/// Character index of the first occurrence of `needle` in `haystack`
+fn find_chars(haystack: &[char], needle: &[char]) -> Option<usize> {
+    if needle.is_empty() {
+        return Some(0);
+    }
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// Highest base a fuzzy match can reach, below the contains tier's 700
+///
+/// The base grows by 100 per query character, so without a cap a long fuzzy match would
+/// group above prefix and contains matches of the same query.
+const FUZZY_BASE_MAX: usize = 600;
+
+fn explain_tiered_score(target: &[char], query: &[char]) -> Option<ScoreBreakdown> {
     // Exact match gets highest score
-    if target_normalized == query_normalized {
-        return 1000;
+    if target == query {
+        return Some(ScoreBreakdown::new(
+            MatchTier::Exact,
+            1000,
+            (0..target.len()).collect(),
+        ));
     }
 
     // Prefix match gets high score
-    if target_normalized.starts_with(&query_normalized) {
+    if target.starts_with(query) {
         // Base score of 900, with bonus for shorter strings
-        let length_bonus = (50 - target.len().min(50)) as usize;
-        let mut score = 900 + length_bonus;
+        let mut breakdown = ScoreBreakdown::new(MatchTier::Prefix, 900, (0..query.len()).collect());
+        breakdown.length_bonus = 50 - target.len().min(50);
 
         // Bonus if query is followed by a word boundary
-        if target_normalized.len() > query_normalized.len() {
-            let next_char = target_normalized
-                .chars()
-                .nth(query_normalized.len())
-                .unwrap();
-            if !next_char.is_alphanumeric() {
-                score += 50; // Bonus for word boundary
-            }
+        if target
+            .get(query.len())
+            .is_some_and(|next_char| !next_char.is_alphanumeric())
+        {
+            breakdown.boundary_bonus = 50;
         }
 
-        return score;
+        return Some(breakdown);
     }
 
     // Contains match gets medium score
-    if target_normalized.contains(&query_normalized) {
-        let position = target_normalized.find(&query_normalized).unwrap();
+    if let Some(position) = find_chars(target, query) {
         // Base score of 700, minus position (earlier is better)
-        return 700 - position.min(100); // Cap position penalty at 100
+        let mut breakdown = ScoreBreakdown::new(
+            MatchTier::Contains,
+            700,
+            (position..position + query.len()).collect(),
+        );
+        breakdown.penalty = position.min(100); // Cap position penalty at 100
+        return Some(breakdown);
     }
 
     // Fuzzy match: all query characters appear in order
-    let mut score = 0;
-    let mut query_chars = query_normalized.chars();
+    let mut breakdown = ScoreBreakdown::new(MatchTier::Fuzzy, 0, Vec::new());
+    let mut query_chars = query.iter();
     let mut current_query_char = query_chars.next();
     let mut consecutive_matches = 0;
-    let mut match_positions = Vec::new();
 
-    for (i, target_char) in target_normalized.chars().enumerate() {
+    for (i, target_char) in target.iter().enumerate() {
         if let Some(qc) = current_query_char {
             if target_char == qc {
-                match_positions.push(i);
-                score += 100 + consecutive_matches * 10; // Bonus for consecutive matches
+                breakdown.positions.push(i);
+                breakdown.base += 100;
+                breakdown.consecutive_bonus += consecutive_matches * 10; // Bonus for consecutive matches
                 consecutive_matches += 1;
                 current_query_char = query_chars.next();
             } else {
@@ -111,20 +745,22 @@ fn calculate_fuzzy_score(target: &str, query: &str, case_sensitive: bool) -> usi
     }
 
     // Only return score if all query characters were found
-    if current_query_char.is_none() && !match_positions.is_empty() {
+    if current_query_char.is_none() && !breakdown.positions.is_empty() {
+        breakdown.base = breakdown.base.min(FUZZY_BASE_MAX);
         // Bonus for matches at the beginning
-        if match_positions[0] == 0 {
-            score += 50;
+        if breakdown.positions[0] == 0 {
+            breakdown.boundary_bonus = 50;
         }
-        score
+        Some(breakdown)
     } else {
-        0
+        None
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::ScoringStrategy;
     use std::time::Instant;
 
     fn create_test_hosts(count: usize) -> Vec<HostEntry> {
@@ -152,6 +788,21 @@ mod tests {
         assert_eq!(results.len(), 3);
     }
 
+    #[test]
+    fn test_empty_query_keeps_max_results() {
+        let hosts: Vec<HostEntry> = (0..30)
+            .map(|n| HostEntry::new(format!("server{}", n), format!("ssh server{}", n)))
+            .collect();
+        let engine =
+            SearchEngine::new(hosts).with_frecency(HashMap::from([("server29".to_string(), 1)]));
+
+        let results = engine.search("", false, 20);
+        assert_eq!(results.len(), 20);
+        // The cap keeps the hosts that rank first, not the first ones in the file
+        assert_eq!(results[0].name, "server29");
+        assert_eq!(engine.search("server", false, 20).len(), 20);
+    }
+
     #[test]
     fn test_exact_match() {
         let hosts = vec![
@@ -179,9 +830,9 @@ mod tests {
         let results = engine.search("prod", false, 10);
 
         assert_eq!(results.len(), 2);
-        // Both are prefix matches, ordering may vary by length
-        assert!(results.iter().any(|h| h.name == "production"));
-        assert!(results.iter().any(|h| h.name == "prod-backup"));
+        // Both are prefix matches; the word boundary after "prod" ranks prod-backup first
+        assert_eq!(results[0].name, "prod-backup");
+        assert_eq!(results[1].name, "production");
     }
 
     #[test]
@@ -263,9 +914,8 @@ mod tests {
         let results = engine.search("git", false, 10);
 
         // Debug: Print scores
-        // for host in &engine.hosts {
-        //     let score = calculate_fuzzy_score(&host.name, "git", false);
-        //     println!("{}: score={}", host.name, score);
+        // for ranked in engine.explain("git", &MatchOptions::default(), 10) {
+        //     println!("{}: {}", ranked.host.name, ranked.breakdown);
         // }
 
         // All three hosts with "git" should be found
@@ -278,4 +928,666 @@ mod tests {
         assert!(results.iter().any(|h| h.name == "github.com"));
         assert!(results.iter().any(|h| h.name == "gitlab.company.com"));
     }
+
+    #[test]
+    fn test_smart_case() {
+        let hosts = vec![
+            HostEntry::new("Server1".to_string(), "ssh Server1".to_string()),
+            HostEntry::new("server2".to_string(), "ssh server2".to_string()),
+        ];
+
+        let engine = SearchEngine::new(hosts);
+        let options = MatchOptions {
+            smart_case: true,
+            ..MatchOptions::default()
+        };
+
+        // Lowercase query matches both
+        let results = engine.search_with_options("server", &options, 10);
+        assert_eq!(results.len(), 2);
+
+        // Uppercase in the query switches to case-sensitive matching
+        let results = engine.search_with_options("Server", &options, 10);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].name, "Server1");
+    }
+
+    #[test]
+    fn test_case_sensitive_overrides_smart_case() {
+        let options = MatchOptions {
+            case_sensitive: true,
+            smart_case: true,
+            fold_diacritics: false,
+        };
+
+        assert!(options.is_case_sensitive_for("server"));
+        assert!(options.is_case_sensitive_for("Server"));
+        assert!(!MatchOptions::default().is_case_sensitive_for("Server"));
+    }
+
+    #[test]
+    fn test_diacritic_folding() {
+        let hosts = vec![
+            HostEntry::new("zürich-db".to_string(), "ssh zürich-db".to_string()),
+            HostEntry::new(
+                "münchen.bücher.example".to_string(),
+                "ssh münchen.bücher.example".to_string(),
+            ),
+            HostEntry::new("oslo".to_string(), "ssh oslo".to_string()),
+        ];
+
+        let engine = SearchEngine::new(hosts);
+        let folding = MatchOptions {
+            fold_diacritics: true,
+            ..MatchOptions::default()
+        };
+
+        let results = engine.search_with_options("zurich", &folding, 10);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].name, "zürich-db");
+
+        // Accented queries also fold to match
+        let results = engine.search_with_options("Büch", &folding, 10);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].name, "münchen.bücher.example");
+
+        // Without folding the plain query does not match
+        let results = engine.search_with_options("zurich", &MatchOptions::default(), 10);
+        assert!(results.is_empty());
+    }
+
+    #[test]
+    fn test_is_prefix_normalizes_like_search() {
+        let options = MatchOptions {
+            smart_case: true,
+            fold_diacritics: true,
+            ..MatchOptions::default()
+        };
+
+        assert!(options.is_prefix("zur", "zürich-db"));
+        assert!(options.is_prefix("ZÜR", "ZURICH-db"));
+        // An uppercase query is case-sensitive under smart case
+        assert!(!options.is_prefix("Zur", "zürich-db"));
+        assert!(!MatchOptions::default().is_prefix("zur", "zürich-db"));
+    }
+
+    #[test]
+    fn test_multibyte_prefix_match() {
+        // Byte-based indexing previously panicked or misread the next character here
+        let hosts = vec![
+            HostEntry::new("über-gateway".to_string(), "ssh über-gateway".to_string()),
+            HostEntry::new("überall".to_string(), "ssh überall".to_string()),
+        ];
+
+        let engine = SearchEngine::new(hosts);
+        let results = engine.search("über", false, 10);
+
+        assert_eq!(results.len(), 2);
+        // Word boundary bonus is found by character position, not byte position
+        assert_eq!(results[0].name, "über-gateway");
+    }
+
+    #[test]
+    fn test_multibyte_scores_use_character_counts() {
+        let target = normalize("ñandú-1", false, false);
+        let query = normalize("ñan", false, false);
+
+        // 7 characters (9 bytes) leaves a length bonus of 43
+        assert_eq!(TieredScorer.score(&target, &query), 900 + 43);
+
+        // Contains position is a character index
+        let target = normalize("café-ñandú", false, false);
+        let query = normalize("ñan", false, false);
+        assert_eq!(TieredScorer.score(&target, &query), 700 - 5);
+    }
+
+    #[test]
+    fn test_normalize_is_one_char_per_char() {
+        let text = "İstanbul-Ærø";
+        assert_eq!(normalize(text, false, true).len(), text.chars().count());
+        assert_eq!(
+            normalize("ÉCOLE", false, true),
+            "ecole".chars().collect::<Vec<_>>()
+        );
+    }
+
+    // Conformance suite: every scorer must satisfy these properties
+
+    fn all_scorers() -> Vec<(&'static str, Box<dyn Scorer>)> {
+        vec![
+            ("tiered", scorer_for(ScoringStrategy::Tiered)),
+            ("fzf", scorer_for(ScoringStrategy::Fzf)),
+        ]
+    }
+
+    fn score_with(scorer: &dyn Scorer, target: &str, query: &str) -> usize {
+        scorer.score(
+            &normalize(target, false, false),
+            &normalize(query, false, false),
+        )
+    }
+
+    #[test]
+    fn test_conformance_non_matching_scores_zero() {
+        for (name, scorer) in all_scorers() {
+            assert_eq!(score_with(&*scorer, "production", "xyz"), 0, "{}", name);
+            // Characters present but out of order
+            assert_eq!(score_with(&*scorer, "production", "dorp"), 0, "{}", name);
+            // Query longer than target
+            assert_eq!(score_with(&*scorer, "db", "db01"), 0, "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_conformance_subsequence_matches() {
+        for (name, scorer) in all_scorers() {
+            assert!(score_with(&*scorer, "production", "prd") > 0, "{}", name);
+            assert!(
+                score_with(&*scorer, "web-01.example.com", "w1e") > 0,
+                "{}",
+                name
+            );
+            assert!(score_with(&*scorer, "über-gateway", "ügw") > 0, "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_conformance_prefix_beats_scattered() {
+        for (name, scorer) in all_scorers() {
+            let prefix = score_with(&*scorer, "devops", "dev");
+            let scattered = score_with(&*scorer, "xdxexvx", "dev");
+            assert!(prefix > scattered, "{}: {} <= {}", name, prefix, scattered);
+        }
+    }
+
+    #[test]
+    fn test_conformance_contiguous_beats_gappy() {
+        for (name, scorer) in all_scorers() {
+            let contiguous = score_with(&*scorer, "xx-prod-xx", "prod");
+            let gappy = score_with(&*scorer, "xx-p-r-o-d", "prod");
+            assert!(contiguous > gappy, "{}: {} <= {}", name, contiguous, gappy);
+        }
+    }
+
+    #[test]
+    fn test_conformance_search_engine_ranking() {
+        for (name, scorer) in all_scorers() {
+            let hosts = vec![
+                HostEntry::new("bitbucket.org".to_string(), "ssh bitbucket.org".to_string()),
+                HostEntry::new("legitimate".to_string(), "ssh legitimate".to_string()),
+                HostEntry::new("git.internal".to_string(), "ssh git.internal".to_string()),
+                HostEntry::new("g-i-t".to_string(), "ssh g-i-t".to_string()),
+            ];
+
+            let engine = SearchEngine::new(hosts).with_scorer(scorer);
+            let results = engine.search("git", false, 10);
+
+            assert_eq!(results.len(), 3, "{}", name);
+            assert_eq!(results[0].name, "git.internal", "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_tiered_breakdown_components() {
+        let target = normalize("prod-db", false, false);
+        let query = normalize("prod", false, false);
+        let breakdown = TieredScorer.explain(&target, &query).unwrap();
+
+        assert_eq!(breakdown.tier, MatchTier::Prefix);
+        assert_eq!(breakdown.base, 900);
+        assert_eq!(breakdown.length_bonus, 43);
+        assert_eq!(breakdown.boundary_bonus, 50);
+        assert_eq!(breakdown.positions, vec![0, 1, 2, 3]);
+        assert_eq!(breakdown.total(), 993);
+
+        let target = normalize("my-prod", false, false);
+        let breakdown = TieredScorer.explain(&target, &query).unwrap();
+        assert_eq!(breakdown.tier, MatchTier::Contains);
+        assert_eq!(breakdown.penalty, 3);
+        assert_eq!(breakdown.positions, vec![3, 4, 5, 6]);
+
+        let target = normalize("p-r-od", false, false);
+        let breakdown = TieredScorer.explain(&target, &query).unwrap();
+        assert_eq!(breakdown.tier, MatchTier::Fuzzy);
+        assert_eq!(breakdown.base, 400);
+        assert_eq!(breakdown.consecutive_bonus, 10);
+        assert_eq!(breakdown.boundary_bonus, 50);
+        assert_eq!(breakdown.positions, vec![0, 2, 4, 5]);
+    }
+
+    #[test]
+    fn test_fzf_breakdown_sums_to_score() {
+        let cases = [
+            ("git.internal", "git"),
+            ("xx-p-r-o-d", "prod"),
+            ("legitimate", "git"),
+            ("web-01.example.com", "w1e"),
+        ];
+
+        for (target, query) in cases {
+            let target = normalize(target, false, false);
+            let query = normalize(query, false, false);
+            let breakdown = FzfScorer.explain(&target, &query).unwrap();
+
+            assert_eq!(breakdown.tier, MatchTier::Alignment);
+            assert_eq!(breakdown.positions.len(), query.len());
+            for (&position, qc) in breakdown.positions.iter().zip(&query) {
+                assert_eq!(target[position], *qc);
+            }
+            assert!(breakdown.positions.windows(2).all(|w| w[0] < w[1]));
+        }
+
+        // "prod" spread over boundaries: each character keeps its boundary bonus
+        let target = normalize("xx-p-r-o-d", false, false);
+        let query = normalize("prod", false, false);
+        let breakdown = FzfScorer.explain(&target, &query).unwrap();
+        assert_eq!(breakdown.positions, vec![3, 5, 7, 9]);
+        assert_eq!(breakdown.base, 64);
+        assert_eq!(breakdown.boundary_bonus, 16 + 8 * 3);
+        assert_eq!(breakdown.penalty, 9);
+        assert_eq!(breakdown.total(), 64 + 40 - 9);
+    }
+
+    #[test]
+    fn test_explain_includes_frecency() {
+        let hosts = vec![
+            HostEntry::new("web-a".to_string(), "ssh web-a".to_string()),
+            HostEntry::new("web-b".to_string(), "ssh web-b".to_string()),
+        ];
+        let frecency = HashMap::from([("web-b".to_string(), 15)]);
+
+        let engine = SearchEngine::new(hosts).with_frecency(frecency);
+        let results = engine.explain("web", &MatchOptions::default(), 10);
+
+        assert_eq!(results[0].host.name, "web-b");
+        assert_eq!(results[0].breakdown.frecency, 15);
+        assert_eq!(
+            results[0].breakdown.total(),
+            results[0].breakdown.match_score() + 15
+        );
+        assert_eq!(results[1].breakdown.frecency, 0);
+    }
+
+    #[test]
+    fn test_format_explanation() {
+        let hosts = vec![HostEntry::new(
+            "prod-db".to_string(),
+            "ssh prod-db".to_string(),
+        )];
+        let engine = SearchEngine::new(hosts);
+        let results = engine.explain("prod", &MatchOptions::default(), 10);
+
+        assert_eq!(
+            format_explanation(&results),
+            "  1. prod-db\n     total=993 field=alias tier=prefix base=900 length=+43 boundary=+50 \
+             consecutive=+0 penalty=-0 frecency=+0 positions=[0, 1, 2, 3]\n"
+        );
+    }
+
+    #[test]
+    fn test_tie_break_order() {
+        // All four are prefix matches of equal length bonus, so only tie-breaks decide
+        let hosts = vec![
+            HostEntry::new("web-d".to_string(), "ssh web-d".to_string()),
+            HostEntry::new("web-c".to_string(), "ssh web-c".to_string()),
+            HostEntry::new("web-b".to_string(), "ssh web-b".to_string()),
+            HostEntry::new("web-a".to_string(), "ssh web-a".to_string()),
+        ];
+
+        let engine = SearchEngine::new(hosts.clone());
+        let names: Vec<&str> = engine
+            .search("web", false, 10)
+            .iter()
+            .map(|h| h.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["web-a", "web-b", "web-c", "web-d"]);
+
+        let engine = SearchEngine::new(hosts)
+            .with_favorites(HashSet::from(["web-c".to_string()]))
+            .with_frecency(HashMap::from([("web-d".to_string(), 3)]));
+        let names: Vec<&str> = engine
+            .search("web", false, 10)
+            .iter()
+            .map(|h| h.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["web-d", "web-c", "web-a", "web-b"]);
+    }
+
+    #[test]
+    fn test_frecency_breaks_equal_totals() {
+        // "ab-x" scores 5 more than "ab-xxxx" on length; frecency evens the totals out
+        let hosts = vec![
+            HostEntry::new("ab-x".to_string(), "ssh ab-x".to_string()),
+            HostEntry::new("ab-xxxxx".to_string(), "ssh ab-xxxxx".to_string()),
+        ];
+        let engine =
+            SearchEngine::new(hosts).with_frecency(HashMap::from([("ab-xxxxx".to_string(), 4)]));
+
+        let results = engine.explain("ab", &MatchOptions::default(), 10);
+        assert_eq!(results[0].breakdown.total(), results[1].breakdown.total());
+        assert_eq!(results[0].host.name, "ab-xxxxx");
+    }
+
+    #[test]
+    fn test_frecency_does_not_lift_prefix_over_exact() {
+        let hosts = vec![
+            HostEntry::new("db".to_string(), "ssh db".to_string()),
+            HostEntry::new("db-replica".to_string(), "ssh db-replica".to_string()),
+        ];
+        let engine = SearchEngine::new(hosts)
+            .with_frecency(HashMap::from([("db-replica".to_string(), 100)]));
+
+        let results = engine.explain("db", &MatchOptions::default(), 10);
+        assert!(results[1].breakdown.total() > results[0].breakdown.total());
+        assert_eq!(results[0].host.name, "db");
+        assert_eq!(results[0].breakdown.tier, MatchTier::Exact);
+    }
+
+    #[test]
+    fn test_shorter_name_breaks_ties() {
+        let target_long = normalize("xx-dbbb", false, false);
+        let target_short = normalize("xx-db", false, false);
+        let query = normalize("db", false, false);
+        assert_eq!(
+            TieredScorer.score(&target_long, &query),
+            TieredScorer.score(&target_short, &query)
+        );
+
+        let hosts = vec![
+            HostEntry::new("xx-dbbb".to_string(), "ssh xx-dbbb".to_string()),
+            HostEntry::new("xx-db".to_string(), "ssh xx-db".to_string()),
+        ];
+        let engine = SearchEngine::new(hosts);
+        let results = engine.search("db", false, 10);
+        assert_eq!(results[0].name, "xx-db");
+    }
+
+    #[test]
+    fn test_empty_query_uses_tie_break_order() {
+        let hosts = vec![
+            HostEntry::new("gamma".to_string(), "ssh gamma".to_string()),
+            HostEntry::new("alpha".to_string(), "ssh alpha".to_string()),
+            HostEntry::new("beta".to_string(), "ssh beta".to_string()),
+        ];
+        let engine =
+            SearchEngine::new(hosts).with_frecency(HashMap::from([("gamma".to_string(), 1)]));
+
+        let names: Vec<&str> = engine
+            .search("", false, 10)
+            .iter()
+            .map(|h| h.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["gamma", "beta", "alpha"]);
+    }
+
+    // Property tests: random host sets and queries from a fixed-seed generator
+
+    /// Small xorshift generator so property tests are reproducible without extra crates
+    struct TestRng(u64);
+
+    impl TestRng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn below(&mut self, n: usize) -> usize {
+            (self.next() % n as u64) as usize
+        }
+
+        fn word(&mut self, max_len: usize) -> String {
+            const ALPHABET: &[char] = &['a', 'b', 'c', 'd', '-', '.', '1', 'é'];
+            (0..self.below(max_len) + 1)
+                .map(|_| ALPHABET[self.below(ALPHABET.len())])
+                .collect()
+        }
+
+        fn shuffle<T>(&mut self, items: &mut [T]) {
+            for i in (1..items.len()).rev() {
+                items.swap(i, self.below(i + 1));
+            }
+        }
+    }
+
+    fn random_engine_inputs(
+        rng: &mut TestRng,
+    ) -> (Vec<HostEntry>, HashMap<String, usize>, HashSet<String>) {
+        let mut names: Vec<String> = (0..rng.below(30) + 1).map(|_| rng.word(8)).collect();
+        names.sort();
+        names.dedup();
+
+        let mut frecency = HashMap::new();
+        for name in &names {
+            if rng.below(4) == 0 {
+                frecency.insert(name.clone(), rng.below(3));
+            }
+        }
+        let favorites = names
+            .iter()
+            .filter(|_| rng.below(4) == 0)
+            .cloned()
+            .collect();
+        let hosts = names
+            .into_iter()
+            .map(|name| HostEntry::new(name.clone(), format!("ssh {}", name)))
+            .collect();
+
+        (hosts, frecency, favorites)
+    }
+
+    #[test]
+    fn test_property_order_independent_of_input_order() {
+        let mut rng = TestRng(0x5eed_1234_abcd_0001);
+
+        for _ in 0..200 {
+            let (hosts, frecency, favorites) = random_engine_inputs(&mut rng);
+            let query = if rng.below(5) == 0 {
+                String::new()
+            } else {
+                rng.word(3)
+            };
+            let mut shuffled = hosts.clone();
+            rng.shuffle(&mut shuffled);
+
+            for strategy in [ScoringStrategy::Tiered, ScoringStrategy::Fzf] {
+                let build = |hosts: Vec<HostEntry>| {
+                    SearchEngine::new(hosts)
+                        .with_scorer(scorer_for(strategy))
+                        .with_frecency(frecency.clone())
+                        .with_favorites(favorites.clone())
+                };
+                let original = build(hosts.clone());
+                let reordered = build(shuffled.clone());
+
+                let expected: Vec<&str> = original
+                    .search(&query, false, 100)
+                    .iter()
+                    .map(|h| h.name.as_str())
+                    .collect();
+                let actual: Vec<&str> = reordered
+                    .search(&query, false, 100)
+                    .iter()
+                    .map(|h| h.name.as_str())
+                    .collect();
+                assert_eq!(expected, actual, "query {:?} with {:?}", query, strategy);
+            }
+        }
+    }
+
+    #[test]
+    fn test_property_results_follow_tie_break_keys() {
+        let mut rng = TestRng(0x5eed_1234_abcd_0002);
+
+        for _ in 0..200 {
+            let (hosts, frecency, favorites) = random_engine_inputs(&mut rng);
+            let query = rng.word(3);
+
+            for strategy in [ScoringStrategy::Tiered, ScoringStrategy::Fzf] {
+                let engine = SearchEngine::new(hosts.clone())
+                    .with_scorer(scorer_for(strategy))
+                    .with_frecency(frecency.clone())
+                    .with_favorites(favorites.clone());
+                let results = engine.explain(&query, &MatchOptions::default(), 100);
+
+                for pair in results.windows(2) {
+                    let (a, b) = (&pair[0], &pair[1]);
+                    let key = |r: &RankedHost<'_>| {
+                        (
+                            std::cmp::Reverse(r.breakdown.total()),
+                            std::cmp::Reverse(r.breakdown.frecency),
+                            !favorites.contains(&r.host.name),
+                            r.host.name.chars().count(),
+                            r.host.name.clone(),
+                        )
+                    };
+                    assert!(
+                        key(a) < key(b),
+                        "{:?} ranked before {:?} for {:?}",
+                        a.host.name,
+                        b.host.name,
+                        query
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_property_max_results_is_a_prefix() {
+        let mut rng = TestRng(0x5eed_1234_abcd_0003);
+
+        for _ in 0..100 {
+            let (hosts, frecency, favorites) = random_engine_inputs(&mut rng);
+            let query = rng.word(2);
+            let engine = SearchEngine::new(hosts)
+                .with_frecency(frecency)
+                .with_favorites(favorites);
+
+            let all = engine.search(&query, false, 100);
+            let limit = rng.below(5) + 1;
+            let limited = engine.search(&query, false, limit);
+            assert_eq!(limited[..], all[..limit.min(all.len())]);
+        }
+    }
+
+    #[test]
+    fn test_property_long_fuzzy_matches_stay_below_prefix() {
+        let mut rng = TestRng(0x5eed_1234_abcd_0004);
+        let rank = |tier: MatchTier| match tier {
+            MatchTier::Exact => 0,
+            MatchTier::Prefix => 1,
+            MatchTier::Contains => 2,
+            _ => 3,
+        };
+
+        for _ in 0..200 {
+            // Queries long enough that 100 per character passes the prefix base of 900
+            let query: String = (0..rng.below(8) + 9).map(|_| rng.word(1)).collect();
+            let spread: String = query.chars().flat_map(|c| [c, '_']).collect();
+            let mut names = vec![
+                format!("{}{}", query, rng.word(4)),
+                format!("{}{}", rng.word(4), query),
+                spread,
+                rng.word(12),
+            ];
+            rng.shuffle(&mut names);
+            let hosts = names
+                .iter()
+                .map(|name| HostEntry::new(name.clone(), format!("ssh {}", name)))
+                .collect();
+
+            let engine = SearchEngine::new(hosts);
+            let results = engine.explain(&query, &MatchOptions::default(), 100);
+            assert!(
+                results
+                    .iter()
+                    .any(|ranked| ranked.breakdown.tier == MatchTier::Fuzzy),
+                "no fuzzy match for {:?}",
+                query
+            );
+            for pair in results.windows(2) {
+                assert!(
+                    rank(pair[0].breakdown.tier) <= rank(pair[1].breakdown.tier),
+                    "{:?} ({}) ranked before {:?} ({}) for {:?}",
+                    pair[0].host.name,
+                    pair[0].breakdown.tier,
+                    pair[1].host.name,
+                    pair[1].breakdown.tier,
+                    query
+                );
+            }
+        }
+    }
+
+    fn host_with_fields(name: &str, hostname: &str, user: &str, description: &str) -> HostEntry {
+        let mut host = HostEntry::new(name.to_string(), format!("ssh {}", name));
+        host.hostname = Some(hostname.to_string());
+        host.user = Some(user.to_string());
+        host.description = Some(description.to_string());
+        host
+    }
+
+    #[test]
+    fn test_search_matches_secondary_fields() {
+        let hosts = vec![
+            host_with_fields("db", "10.2.0.15", "postgres", "Primary database"),
+            host_with_fields(
+                "bastion",
+                "ec2-54-12-34-56.compute.amazonaws.com",
+                "ubuntu",
+                "Jump host",
+            ),
+        ];
+        let engine = SearchEngine::new(hosts);
+        let options = MatchOptions::default();
+
+        let results = engine.explain("10.2.", &options, 10);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].host.name, "db");
+        assert_eq!(results[0].breakdown.field, MatchField::HostName);
+        assert_eq!(results[0].breakdown.positions, vec![0, 1, 2, 3, 4]);
+
+        let results = engine.explain("ec2-54-", &options, 10);
+        assert_eq!(results[0].host.name, "bastion");
+        assert_eq!(results[0].breakdown.field, MatchField::HostName);
+
+        let results = engine.explain("jump host", &options, 10);
+        assert_eq!(results[0].host.name, "bastion");
+        assert_eq!(results[0].breakdown.field, MatchField::Description);
+
+        let results = engine.explain("postgres", &options, 10);
+        assert_eq!(results[0].breakdown.field, MatchField::User);
+    }
+
+    #[test]
+    fn test_alias_match_outranks_secondary_field() {
+        let hosts = vec![
+            host_with_fields("web", "10.0.0.1", "deploy", "Frontend"),
+            host_with_fields("cache", "web.internal", "deploy", "Redis"),
+        ];
+
+        for (label, scorer) in all_scorers() {
+            let engine = SearchEngine::new(hosts.clone()).with_scorer(scorer);
+            let results = engine.explain("web", &MatchOptions::default(), 10);
+
+            let names: Vec<&str> = results.iter().map(|r| r.host.name.as_str()).collect();
+            assert_eq!(names, vec!["web", "cache"], "{label}");
+            assert_eq!(results[0].breakdown.field, MatchField::Alias);
+            assert_eq!(results[1].breakdown.field, MatchField::HostName);
+        }
+    }
+
+    #[test]
+    fn test_field_weight_scales_match_score() {
+        let mut breakdown = ScoreBreakdown::new(MatchTier::Exact, 1000, vec![0]);
+        assert_eq!(breakdown.match_score(), 1000);
+
+        breakdown.field = MatchField::HostName;
+        assert_eq!(breakdown.match_score(), 800);
+
+        breakdown.field = MatchField::Description;
+        assert_eq!(breakdown.match_score(), 500);
+    }
 }
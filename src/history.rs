@@ -0,0 +1,217 @@
+// ABOUTME: Launch history persisted between sessions for frecency-based ranking
+// ABOUTME: Records how often and how recently each host was launched, and its recent remote commands
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const DAY_SECS: u64 = 24 * 60 * 60;
+
+/// Launches counted towards frecency; older habits stop adding weight beyond this
+const MAX_COUNTED_LAUNCHES: u32 = 20;
+
+/// Remote commands remembered per host for recall
+const MAX_RECENT_COMMANDS: usize = 10;
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
+pub struct LaunchHistory {
+    #[serde(default)]
+    pub hosts: HashMap<String, HostHistory>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
+pub struct HostHistory {
+    pub launch_count: u32,
+    /// Seconds since the Unix epoch
+    pub last_launched: u64,
+    /// Remote commands run on the host, most recent first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub commands: Vec<String>,
+}
+
+impl LaunchHistory {
+    pub fn default_path() -> Result<PathBuf> {
+        let config_dir = dirs::config_dir().context("Failed to determine config directory")?;
+        Ok(config_dir.join("trident").join("history.toml"))
+    }
+
+    /// Load history, treating a missing file as empty history
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read history file: {}", path.display()))?;
+        toml::from_str(&content)
+            .with_context(|| format!("Failed to parse history file: {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).with_context(|| {
+                format!("Failed to create history directory: {}", parent.display())
+            })?;
+        }
+
+        let content = toml::to_string(self).context("Failed to serialize launch history")?;
+        fs::write(path, content)
+            .with_context(|| format!("Failed to write history file: {}", path.display()))
+    }
+
+    pub fn record_launch(&mut self, host: &str, now: u64) {
+        let entry = self.hosts.entry(host.to_string()).or_default();
+        entry.launch_count = entry.launch_count.saturating_add(1);
+        entry.last_launched = now;
+    }
+
+    /// Remember a remote command run on a host, moving a repeated command to the front
+    pub fn record_command(&mut self, host: &str, command: &str) {
+        let commands = &mut self.hosts.entry(host.to_string()).or_default().commands;
+        commands.retain(|existing| existing != command);
+        commands.insert(0, command.to_string());
+        commands.truncate(MAX_RECENT_COMMANDS);
+    }
+
+    /// Remote commands recently run on a host, most recent first
+    pub fn recent_commands(&self, host: &str) -> &[String] {
+        self.hosts
+            .get(host)
+            .map(|entry| entry.commands.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Frecency points for a host: launch count weighted by how recently it was used (0-100)
+    pub fn frecency(&self, host: &str, now: u64) -> usize {
+        let Some(entry) = self.hosts.get(host) else {
+            return 0;
+        };
+
+        let age = now.saturating_sub(entry.last_launched);
+        let recency_weight = if age < DAY_SECS {
+            5
+        } else if age < 7 * DAY_SECS {
+            3
+        } else if age < 30 * DAY_SECS {
+            2
+        } else {
+            1
+        };
+
+        entry.launch_count.min(MAX_COUNTED_LAUNCHES) as usize * recency_weight
+    }
+
+    /// Frecency points for every host with history, keyed by host name
+    pub fn frecency_scores(&self, now: u64) -> HashMap<String, usize> {
+        self.hosts
+            .keys()
+            .map(|host| (host.clone(), self.frecency(host, now)))
+            .collect()
+    }
+}
+
+/// Current time in seconds since the Unix epoch
+pub fn now_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const NOW: u64 = 1_700_000_000;
+
+    #[test]
+    fn test_record_launch() {
+        let mut history = LaunchHistory::default();
+        history.record_launch("prod", NOW - 10);
+        history.record_launch("prod", NOW);
+
+        let entry = &history.hosts["prod"];
+        assert_eq!(entry.launch_count, 2);
+        assert_eq!(entry.last_launched, NOW);
+    }
+
+    #[test]
+    fn test_frecency_weights_recent_use() {
+        let mut history = LaunchHistory::default();
+        history.record_launch("today", NOW);
+        history.record_launch("last-week", NOW - 3 * DAY_SECS);
+        history.record_launch("last-year", NOW - 365 * DAY_SECS);
+
+        assert_eq!(history.frecency("today", NOW), 5);
+        assert_eq!(history.frecency("last-week", NOW), 3);
+        assert_eq!(history.frecency("last-year", NOW), 1);
+        assert_eq!(history.frecency("never", NOW), 0);
+    }
+
+    #[test]
+    fn test_frecency_is_capped() {
+        let mut history = LaunchHistory::default();
+        for _ in 0..500 {
+            history.record_launch("busy", NOW);
+        }
+
+        assert_eq!(history.frecency("busy", NOW), 100);
+    }
+
+    #[test]
+    fn test_save_and_load_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("nested").join("history.toml");
+
+        let mut history = LaunchHistory::default();
+        history.record_launch("web.example.com", NOW);
+        history.save(&path).unwrap();
+
+        let loaded = LaunchHistory::load(&path).unwrap();
+        assert_eq!(loaded, history);
+    }
+
+    #[test]
+    fn test_recent_commands_most_recent_first() {
+        let mut history = LaunchHistory::default();
+        history.record_command("web", "htop");
+        history.record_command("web", "journalctl -f -u app");
+        history.record_command("web", "htop");
+        for n in 0..20 {
+            history.record_command("busy", &format!("echo {}", n));
+        }
+
+        assert_eq!(
+            history.recent_commands("web"),
+            ["htop", "journalctl -f -u app"]
+        );
+        assert_eq!(history.recent_commands("busy").len(), MAX_RECENT_COMMANDS);
+        assert_eq!(history.recent_commands("busy")[0], "echo 19");
+        assert!(history.recent_commands("never").is_empty());
+    }
+
+    #[test]
+    fn test_commands_survive_save_and_load() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("history.toml");
+
+        let mut history = LaunchHistory::default();
+        history.record_launch("web", NOW);
+        history.record_command("web", "sudo -i");
+        history.save(&path).unwrap();
+
+        let loaded = LaunchHistory::load(&path).unwrap();
+        assert_eq!(loaded.recent_commands("web"), ["sudo -i"]);
+    }
+
+    #[test]
+    fn test_load_missing_file_is_empty() {
+        let temp_dir = TempDir::new().unwrap();
+        let history = LaunchHistory::load(&temp_dir.path().join("missing.toml")).unwrap();
+        assert!(history.hosts.is_empty());
+    }
+}
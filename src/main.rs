@@ -3,17 +3,33 @@
 mod app;
 mod config;
 mod fuzzy;
+mod history;
 mod menubar;
 mod ssh;
+#[cfg(all(test, unix))]
+mod test_support;
+mod tunnel;
 mod ui;
 
 use anyhow::Result;
-use app::AppState;
-use config::Config;
+use app::{AppState, LaunchJob, Message};
+use config::{BroadcastLayout, Config};
+use fuzzy::ScoreBreakdown;
 use gpui::*;
-use ssh::{HostEntry, TerminalLauncher, parse_known_hosts, parse_ssh_config};
+#[cfg(not(test))]
+use gpui::prelude::FluentBuilder;
+use history::LaunchHistory;
+use ssh::probe::{ProbeCache, probe_hosts};
+use ssh::{
+    HostEntry, LaunchOptions, LaunchTarget, dedup_hosts, environment, parse_known_hosts,
+    parse_ssh_config,
+};
+use std::collections::BTreeSet;
 use std::path::Path;
-use ui::{HostList, SearchInput};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tunnel::{Backoff, TunnelStore, TunnelSupervisor};
+use ui::{ActionMenu, HostAction, HostList, SearchInput};
 
 // Define actions for the SSH launcher
 actions!(trident, [ShowLauncher, QuitApp, ToggleLauncher]);
@@ -35,10 +51,14 @@ impl Logger {
     }
 
     pub fn debug(msg: &str) {
-        if std::env::var("TRIDENT_DEBUG").is_ok() {
+        if Self::debug_enabled() {
             eprintln!("[DEBUG] {}", msg);
         }
     }
+
+    pub fn debug_enabled() -> bool {
+        std::env::var("TRIDENT_DEBUG").is_ok()
+    }
 }
 
 // Zed-like theme colors for dark mode
@@ -108,8 +128,19 @@ struct TridentApp {
     state: AppState,
     search_input: SearchInput,
     host_list: HostList,
-    terminal_launcher: TerminalLauncher,
+    // Per-launch choices for the selected host, open while Cmd+K is active
+    action_menu: Option<ActionMenu>,
+    // Marked-host launch above `confirm_above`, waiting for a second Enter
+    pending_broadcast: Option<(BroadcastLayout, LaunchOptions)>,
     focus_handle: FocusHandle,
+    // Score breakdowns for the visible hosts, only collected when TRIDENT_DEBUG is set
+    score_breakdowns: Vec<ScoreBreakdown>,
+    // Hosts probed, or being probed, since the launcher opened
+    probed_hosts: BTreeSet<String>,
+    // probes.toml, loaded by the first probe; batches merge into it and save one at a time
+    probe_cache: Arc<Mutex<Option<ProbeCache>>>,
+    // A launch is running in the background; further launches wait for it
+    launching: bool,
 }
 
 impl TridentApp {
@@ -136,6 +167,8 @@ impl TridentApp {
         // Create state with loaded config
         let mut state = AppState::new();
         state.config = config.clone();
+        state.history = Self::load_history();
+        state.history_path = LaunchHistory::default_path().ok();
 
         // Load SSH hosts from files
         let hosts = Self::load_ssh_hosts(&config);
@@ -145,14 +178,40 @@ impl TridentApp {
         let mut search_input = SearchInput::new("Search SSH hosts...".to_string());
         search_input.set_focused(true);
 
-        let terminal_launcher = TerminalLauncher::new(config.terminal.clone());
-
-        Self {
+        let mut app = Self {
             state,
             search_input,
             host_list: HostList::new(hosts),
-            terminal_launcher,
+            action_menu: None,
+            pending_broadcast: None,
             focus_handle: cx.focus_handle(),
+            score_breakdowns: Vec::new(),
+            probed_hosts: BTreeSet::new(),
+            probe_cache: Arc::new(Mutex::new(None)),
+            launching: false,
+        };
+
+        // Rank the initial (empty query) list so it matches what later searches show
+        app.update_search();
+        app.probe_visible_hosts(cx);
+        // Flag hosts whose IdentityFile isn't loaded in the ssh agent
+        app.refresh_agent_keys(cx);
+
+        // Capture the login shell environment before the first launch needs it
+        let terminal = config.terminal.clone();
+        cx.background_executor()
+            .spawn(async move { environment::preload_login_env(&terminal) })
+            .detach();
+        app
+    }
+
+    fn load_history() -> LaunchHistory {
+        match LaunchHistory::default_path().and_then(|path| LaunchHistory::load(&path)) {
+            Ok(history) => history,
+            Err(e) => {
+                Logger::warn(&format!("Failed to load launch history: {}", e));
+                LaunchHistory::default()
+            }
         }
     }
 
@@ -256,8 +315,7 @@ impl TridentApp {
         }
 
         // Remove duplicates and sort
-        all_hosts.sort_by(|a, b| a.name.cmp(&b.name));
-        all_hosts.dedup_by(|a, b| a.name == b.name);
+        dedup_hosts(&mut all_hosts);
 
         // Fallback to examples if no hosts found
         if all_hosts.is_empty() {
@@ -280,6 +338,31 @@ impl TridentApp {
     }
 
     fn handle_key_event(&mut self, event: &KeyDownEvent, cx: &mut Context<Self>) {
+        if self.action_menu.is_some() {
+            self.handle_action_menu_key(event, cx);
+            return;
+        }
+
+        // Any key dismisses the last launch error, dry run or host details
+        if self.state.error_message.is_some()
+            || self.state.dry_run.is_some()
+            || self.state.host_details.is_some()
+        {
+            self.state.error_message = None;
+            self.state.dry_run = None;
+            self.state.host_details = None;
+            cx.notify();
+        }
+
+        // A second Enter confirms a large marked-host launch; any other key cancels it
+        if let Some((layout, options)) = self.pending_broadcast.take() {
+            if event.keystroke.key == "enter" {
+                self.launch_marked_hosts(layout, options, true, cx);
+            }
+            cx.notify();
+            return;
+        }
+
         match event.keystroke.key.as_str() {
             "up" => {
                 if !self.host_list.is_empty() {
@@ -294,14 +377,51 @@ impl TridentApp {
                 }
             }
             "enter" => {
-                if let Some(host) = self.host_list.get_selected_host() {
-                    if let Err(e) = self.launch_host(host) {
-                        Logger::error(&format!("Failed to launch host: {}", e));
+                if !self.host_list.marked.is_empty() || self.host_list.get_selected_host().is_some()
+                {
+                    let modifiers = &event.keystroke.modifiers;
+                    // Enter opens a window, Cmd+Enter a tab and Shift+Enter a split.
+                    // Alt+Enter connects with the alternate transport (mosh by default).
+                    let target = if modifiers.platform {
+                        LaunchTarget::Tab
+                    } else if modifiers.shift {
+                        LaunchTarget::Split
+                    } else {
+                        LaunchTarget::Window
+                    };
+                    let options = LaunchOptions {
+                        transport: modifiers
+                            .alt
+                            .then_some(self.state.config.transport.alternate),
+                        target,
+                        // `host > command` in the search box runs the command instead of a shell
+                        remote_command: self.state.remote_command.clone(),
+                        ..Default::default()
+                    };
+                    if modifiers.control {
+                        // Ctrl+Enter shows what would run for the selection instead of running it
+                        self.dry_run_selected_host(options, cx);
+                        return;
+                    }
+                    if !self.host_list.marked.is_empty() {
+                        // Marked hosts launch together instead of the selection
+                        let layout = self.state.config.broadcast.layout;
+                        self.launch_marked_hosts(layout, options, false, cx);
+                        return;
                     }
-                    // Close window after launching
-                    cx.quit();
+                    self.launch_selected_host(options, cx);
                 }
             }
+            "space" if !self.search_input.is_typing_command() => {
+                // Mark the selected host for a multi-host launch
+                self.host_list.toggle_marked();
+                cx.notify();
+            }
+            "a" if event.keystroke.modifiers.control => {
+                // Mark every result (Ctrl+A)
+                self.host_list.toggle_mark_all();
+                cx.notify();
+            }
             "escape" => {
                 // Close window on escape
                 cx.quit();
@@ -312,9 +432,48 @@ impl TridentApp {
                 self.update_search();
                 cx.notify();
             }
+            "k" if event.keystroke.modifiers.platform => {
+                // Open the action menu for the selected host (Cmd+K)
+                self.open_action_menu();
+                cx.notify();
+            }
+            "i" if event.keystroke.modifiers.platform => {
+                // Show the selected host's address and host key fingerprints (Cmd+I)
+                if self.host_list.get_selected_host().is_some() {
+                    self.update_selected_host(Message::ShowHostDetails, cx);
+                }
+            }
+            "s" if event.keystroke.modifiers.platform => {
+                // Browse the selected host's files with sftp in the terminal (Cmd+S)
+                if self.host_list.get_selected_host().is_some() {
+                    self.open_sftp(cx);
+                }
+            }
+            "o" if event.keystroke.modifiers.platform => {
+                // Open the selected host in the file manager (Cmd+O)
+                if self.host_list.get_selected_host().is_some() {
+                    self.open_in_file_manager(cx);
+                }
+            }
+            "c" if event.keystroke.modifiers.platform && event.keystroke.modifiers.shift => {
+                // Copy an scp command for the selected host (Cmd+Shift+C)
+                if self.host_list.get_selected_host().is_some() {
+                    self.copy_scp_command(cx);
+                }
+            }
+            "c" if event.keystroke.modifiers.platform => {
+                // Copy the query like any text field (Cmd+C); it has no partial selection
+                if !self.search_input.query.is_empty() {
+                    cx.write_to_clipboard(ClipboardItem::new_string(
+                        self.search_input.query.clone(),
+                    ));
+                }
+            }
             "r" if event.keystroke.modifiers.platform => {
                 // Reload configuration (Cmd+R)
                 self.reload_config_and_hosts();
+                // Keys may have been added to the agent since the launcher opened
+                self.refresh_agent_keys(cx);
                 cx.notify();
             }
             "backspace" => {
@@ -322,39 +481,178 @@ impl TridentApp {
                 self.update_search();
                 cx.notify();
             }
-            text => {
+            key => {
                 // Handle regular character input
-                if text.len() == 1 {
-                    if let Some(ch) = text.chars().next() {
-                        if ch.is_ascii_graphic() || ch == ' ' {
-                            self.search_input.handle_input(text);
-                            self.update_search();
-                            cx.notify();
-                        }
+                if self.search_input.handle_key(key) {
+                    self.update_search();
+                    cx.notify();
+                }
+            }
+        }
+
+        // A new search or a reload can bring unprobed hosts into view
+        self.probe_visible_hosts(cx);
+    }
+
+    fn open_action_menu(&mut self) {
+        let Some(host) = self.host_list.get_selected_host() else {
+            return;
+        };
+
+        // Keys that aren't loaded come first, since the connection would likely fail
+        let mut actions: Vec<HostAction> = self
+            .state
+            .missing_keys_for(&host.name)
+            .iter()
+            .cloned()
+            .map(HostAction::AddKey)
+            .collect();
+        actions.extend([
+            HostAction::OpenSftp,
+            HostAction::OpenInFileManager(ssh::files::file_manager_uri(
+                &self.state.config.files,
+                host,
+            )),
+            HostAction::CopyScpCommand(ssh::files::scp_command(&self.state.config.files, host)),
+            HostAction::ShowDetails,
+            HostAction::VerifyHostKey,
+        ]);
+        actions.extend(
+            self.state
+                .command_suggestions(&host.name)
+                .into_iter()
+                .map(HostAction::RunCommand),
+        );
+        let count = self.host_list.marked.len();
+        if count > 0 {
+            actions.push(HostAction::OpenMarked {
+                layout: BroadcastLayout::Windows,
+                count,
+            });
+            actions.push(HostAction::OpenMarked {
+                layout: BroadcastLayout::Tiled,
+                count,
+            });
+        }
+        actions.extend(
+            self.state
+                .config
+                .terminal
+                .profiles
+                .keys()
+                .map(|name| HostAction::OpenWithProfile(name.clone())),
+        );
+
+        // Each forward preset the host offers, then all of them together, in a terminal
+        // or as a background tunnel
+        let forwards = self.state.config.forwards_for(&host.name);
+        let mut choices: Vec<(Vec<String>, String)> = forwards
+            .iter()
+            .map(|(name, preset)| (vec![name.to_string()], ssh::forward::describe(preset)))
+            .collect();
+        if forwards.len() > 1 {
+            choices.push((
+                forwards.iter().map(|(name, _)| name.to_string()).collect(),
+                forwards
+                    .iter()
+                    .map(|(_, preset)| ssh::forward::describe(preset))
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            ));
+        }
+        for (forwards, description) in choices {
+            actions.push(HostAction::OpenWithForwards {
+                forwards: forwards.clone(),
+                description: description.clone(),
+            });
+            actions.push(HostAction::StartTunnel {
+                forwards,
+                description,
+            });
+        }
+        self.action_menu = Some(ActionMenu::new(host.name.clone(), actions));
+    }
+
+    fn handle_action_menu_key(&mut self, event: &KeyDownEvent, cx: &mut Context<Self>) {
+        let Some(menu) = self.action_menu.as_mut() else {
+            return;
+        };
+
+        match event.keystroke.key.as_str() {
+            "up" => menu.select_previous(),
+            "down" => menu.select_next(),
+            "escape" => self.action_menu = None,
+            "enter" => {
+                let action = menu.selected_action().cloned();
+                self.action_menu = None;
+                match action {
+                    Some(HostAction::OpenSftp) => self.open_sftp(cx),
+                    Some(HostAction::OpenInFileManager(_)) => self.open_in_file_manager(cx),
+                    Some(HostAction::CopyScpCommand(_)) => self.copy_scp_command(cx),
+                    Some(HostAction::ShowDetails) => {
+                        self.update_selected_host(Message::ShowHostDetails, cx)
+                    }
+                    Some(HostAction::VerifyHostKey) => self.verify_selected_host_key(cx),
+                    Some(HostAction::AddKey(key)) => {
+                        let options = LaunchOptions {
+                            add_key: Some(key),
+                            ..Default::default()
+                        };
+                        self.launch_selected_host(options, cx);
+                    }
+                    Some(HostAction::RunCommand(command)) => {
+                        let options = LaunchOptions {
+                            remote_command: Some(command),
+                            ..Default::default()
+                        };
+                        self.launch_selected_host(options, cx);
+                    }
+                    Some(HostAction::OpenWithProfile(profile)) => {
+                        let options = LaunchOptions {
+                            profile: Some(profile),
+                            ..Default::default()
+                        };
+                        self.launch_selected_host(options, cx);
+                    }
+                    Some(HostAction::OpenWithForwards { forwards, .. }) => {
+                        let options = LaunchOptions {
+                            forwards,
+                            ..Default::default()
+                        };
+                        self.launch_selected_host(options, cx);
+                    }
+                    Some(HostAction::StartTunnel { forwards, .. }) => {
+                        let options = LaunchOptions {
+                            forwards,
+                            ..Default::default()
+                        };
+                        let result = self.start_tunnel(options);
+                        self.finish_launch(result, cx);
                     }
+                    Some(HostAction::OpenMarked { layout, .. }) => {
+                        self.launch_marked_hosts(layout, LaunchOptions::default(), false, cx);
+                    }
+                    None => {}
                 }
             }
+            _ => {}
         }
+        cx.notify();
     }
 
     fn handle_host_click(&mut self, host_index: usize, cx: &mut Context<Self>) {
         // Select and launch the clicked host
         self.host_list.select_index(host_index);
-        if let Some(host) = self.host_list.get_selected_host() {
-            if let Err(e) = self.launch_host(host) {
-                Logger::error(&format!("Failed to launch host: {}", e));
-            }
-            // Close window after launching
-            cx.quit();
+        if self.host_list.get_selected_host().is_some() {
+            self.launch_selected_host(LaunchOptions::default(), cx);
         }
     }
 
-    fn handle_host_double_click(&mut self, host_index: usize, _cx: &mut Context<Self>) {
+    fn handle_host_double_click(&mut self, host_index: usize, cx: &mut Context<Self>) {
         // Launch the double-clicked host
-        if let Some(host) = self.host_list.hosts.get(host_index) {
-            if let Err(e) = self.launch_host(host) {
-                Logger::error(&format!("Failed to launch host: {}", e));
-            }
+        if host_index < self.host_list.hosts.len() {
+            self.host_list.select_index(host_index);
+            self.launch_selected_host(LaunchOptions::default(), cx);
         }
     }
 
@@ -373,15 +671,8 @@ impl TridentApp {
 
     #[cfg(not(test))]
     fn render_host_list_always(&self, _cx: &mut Context<Self>) -> impl IntoElement {
-        // Create a host list with the correct hosts to display
-        let hosts_to_show = if self.search_input.query.is_empty() {
-            self.state.hosts.clone()
-        } else {
-            self.host_list.hosts.clone()
-        };
-
-        let mut display_list = HostList::new(hosts_to_show);
-        display_list.selected_index = self.host_list.selected_index;
+        // Ranked results; an empty query lists all hosts in tie-break order
+        let display_list = self.host_list.clone();
 
         div()
             .flex()
@@ -390,25 +681,229 @@ impl TridentApp {
             .child(display_list)
     }
 
+    #[cfg(not(test))]
+    fn render_debug_overlay(&self, _cx: &mut Context<Self>) -> impl IntoElement {
+        // Score breakdown for the selected host, shown only when TRIDENT_DEBUG is set
+        let breakdown = self
+            .score_breakdowns
+            .get(self.host_list.selected_index)
+            .map(|breakdown| breakdown.to_string())
+            .unwrap_or_else(|| "No score for selection".to_string());
+
+        div()
+            .w_full()
+            .px_3()
+            .py_2()
+            .border_t_1()
+            .border_color(ZedTheme::border())
+            .bg(ZedTheme::editor_background())
+            .text_color(ZedTheme::text_muted())
+            .text_size(px(11.0))
+            .font_family("Menlo")
+            .child(breakdown)
+    }
+
+    #[cfg(not(test))]
+    fn render_error(&self, error: String) -> impl IntoElement {
+        div()
+            .w_full()
+            .px_3()
+            .py_2()
+            .border_t_1()
+            .border_color(ZedTheme::border())
+            .bg(ZedTheme::editor_background())
+            .text_color(rgb(0xf48771)) // Zed error text
+            .text_size(px(13.0))
+            .child(error)
+    }
+
+    #[cfg(not(test))]
+    fn render_dry_run(&self, report: String) -> impl IntoElement {
+        div()
+            .w_full()
+            .px_3()
+            .py_2()
+            .border_t_1()
+            .border_color(ZedTheme::border())
+            .bg(ZedTheme::editor_background())
+            .text_color(ZedTheme::text())
+            .text_size(px(12.0))
+            .font_family("Menlo")
+            .children(
+                report
+                    .lines()
+                    .map(|line| div().child(line.to_string()))
+                    .collect::<Vec<_>>(),
+            )
+            .child(
+                div()
+                    .pt_1()
+                    .text_color(ZedTheme::text_muted())
+                    .child("Copied to the clipboard. Press any key to dismiss."),
+            )
+    }
+
+    #[cfg(not(test))]
+    fn render_host_details(&self, report: String) -> impl IntoElement {
+        div()
+            .w_full()
+            .px_3()
+            .py_2()
+            .border_t_1()
+            .border_color(ZedTheme::border())
+            .bg(ZedTheme::editor_background())
+            .text_color(ZedTheme::text())
+            .text_size(px(12.0))
+            .font_family("Menlo")
+            .children(
+                report
+                    .lines()
+                    .map(|line| {
+                        div()
+                            .when(line.contains("HOST KEY CHANGED"), |line| {
+                                line.text_color(rgb(0xf48771)) // Zed error text
+                            })
+                            .child(line.to_string())
+                    })
+                    .collect::<Vec<_>>(),
+            )
+            .child(
+                div()
+                    .pt_1()
+                    .text_color(ZedTheme::text_muted())
+                    .child("Press any key to dismiss."),
+            )
+    }
+
+    #[cfg(not(test))]
+    fn render_broadcast_prompt(&self, _cx: &mut Context<Self>) -> impl IntoElement {
+        div()
+            .w_full()
+            .px_3()
+            .py_2()
+            .border_t_1()
+            .border_color(ZedTheme::border())
+            .bg(ZedTheme::editor_background())
+            .text_color(ZedTheme::text_accent())
+            .text_size(px(13.0))
+            .child(format!(
+                "Launch {} hosts? Press Enter to confirm, any other key to cancel",
+                self.host_list.marked.len()
+            ))
+    }
+
     fn update_search(&mut self) {
-        // Update the app state with the current search query
-        self.state.search_query = self.search_input.query.clone();
+        // Search hosts with the part before any `>`; the rest is a remote command
+        let (host_query, command) = app::split_command_query(&self.search_input.query);
+        self.state.search_query = host_query.to_string();
+        self.state.remote_command = command.map(str::to_string);
 
         // Use the real search functionality
-        let search_engine = fuzzy::SearchEngine::new(self.state.hosts.clone());
-        let results = search_engine.search(
+        let search_engine = self.state.search_engine();
+        let results = search_engine.explain(
             &self.state.search_query,
-            self.state.config.ui.case_sensitive,
+            &fuzzy::MatchOptions::from_ui_config(&self.state.config.ui),
             self.state.config.ui.max_results,
         );
 
         // Convert search results to owned hosts
-        let filtered_hosts: Vec<HostEntry> = results.into_iter().cloned().collect();
+        let filtered_hosts: Vec<HostEntry> =
+            results.iter().map(|ranked| ranked.host.clone()).collect();
         self.host_list.set_hosts(filtered_hosts.clone());
+        self.host_list
+            .set_missing_keys(self.state.missing_keys.keys().cloned().collect());
+        self.host_list
+            .set_changed_host_keys(self.changed_host_keys());
+        self.host_list.set_matched_fields(
+            results
+                .iter()
+                .map(|ranked| ranked.breakdown.field)
+                .collect(),
+        );
+
+        if Logger::debug_enabled() {
+            self.score_breakdowns = results.into_iter().map(|ranked| ranked.breakdown).collect();
+        }
 
         // Find and set autocomplete suggestion
         let suggestion = self.find_autocomplete_suggestion(&filtered_hosts);
-        self.search_input.set_suggestion(suggestion);
+        let options = fuzzy::MatchOptions::from_ui_config(&self.state.config.ui);
+        self.search_input.set_suggestion(suggestion, options);
+    }
+
+    /// Probe the visible hosts not probed yet, off the UI thread, and show the results
+    fn probe_visible_hosts(&mut self, cx: &mut Context<Self>) {
+        let config = self.state.config.probe.clone();
+        if !config.enabled {
+            return;
+        }
+        let hosts: Vec<HostEntry> = self
+            .host_list
+            .visible_hosts()
+            .iter()
+            .filter(|host| self.probed_hosts.insert(host.name.clone()))
+            .cloned()
+            .collect();
+        if hosts.is_empty() {
+            return;
+        }
+
+        let shared = self.probe_cache.clone();
+        cx.spawn(async move |this, cx| {
+            let probes = cx
+                .background_executor()
+                .spawn(async move {
+                    // Probe a copy so other batches can read the cache in the meantime
+                    let mut cache = shared
+                        .lock()
+                        .unwrap()
+                        .get_or_insert_with(Self::load_probe_cache)
+                        .clone();
+                    let probes = probe_hosts(&hosts, &config, &mut cache, history::now_timestamp());
+
+                    let mut shared = shared.lock().unwrap();
+                    let merged = shared.get_or_insert_with(ProbeCache::default);
+                    merged.merge(cache);
+                    if let Ok(path) = ProbeCache::default_path()
+                        && let Err(e) = merged.save(&path)
+                    {
+                        Logger::warn(&format!("Failed to save probe cache: {}", e));
+                    }
+                    probes
+                })
+                .await;
+            let _ = this.update(cx, |app, cx| {
+                app.host_list.set_probes(probes);
+                cx.notify();
+            });
+        })
+        .detach();
+    }
+
+    fn load_probe_cache() -> ProbeCache {
+        match ProbeCache::default_path().and_then(|path| ProbeCache::load(&path)) {
+            Ok(cache) => cache,
+            Err(e) => {
+                Logger::warn(&format!("Failed to load probe cache: {}", e));
+                ProbeCache::default()
+            }
+        }
+    }
+
+    /// List the agent's keys in the background; a stuck agent can take seconds to time out
+    fn refresh_agent_keys(&mut self, cx: &mut Context<Self>) {
+        let agent = self.state.config.agent.clone();
+        cx.spawn(async move |this, cx| {
+            let keys = cx
+                .background_executor()
+                .spawn(async move { ssh::agent::loaded_keys(&agent) })
+                .await;
+            let _ = this.update(cx, |app, cx| {
+                app.state.set_agent_keys(keys);
+                cx.notify();
+            });
+        })
+        .detach();
     }
 
     fn find_autocomplete_suggestion(&self, filtered_hosts: &[HostEntry]) -> Option<String> {
@@ -419,22 +914,212 @@ impl TridentApp {
             return None;
         }
 
-        // Find the best prefix match from results
-        let query_lower = query.to_lowercase();
+        // Suggest the first result whose name extends the query, compared like the search does
+        let options = fuzzy::MatchOptions::from_ui_config(&self.state.config.ui);
+        filtered_hosts
+            .iter()
+            .find(|host| {
+                options.is_prefix(query, &host.name)
+                    && host.name.chars().count() > query.chars().count()
+            })
+            .map(|host| host.name.clone())
+    }
 
-        // Look for exact prefix matches first
-        for host in filtered_hosts {
-            let host_name_lower = host.name.to_lowercase();
-            if host_name_lower.starts_with(&query_lower) && host.name.len() > query.len() {
-                return Some(host.name.clone());
-            }
+    /// Point the app state's selection at the host selected in the list
+    fn sync_selected_host(&mut self) -> Result<()> {
+        self.state.filtered_hosts = self.host_list.hosts.clone();
+        self.state
+            .update(Message::SelectHost(self.host_list.selected_index))
+    }
+
+    fn launch_selected_host(&mut self, options: LaunchOptions, cx: &mut Context<Self>) {
+        if self.launching {
+            return;
+        }
+        // Route through the app state so every launch path builds the same command
+        let result = self
+            .sync_selected_host()
+            .and_then(|()| self.state.update(Message::LaunchSelectedHostWith(options)));
+        self.run_pending_launch(result, cx);
+    }
+
+    /// Run the launch the last launch message left in the app state, or show why it failed
+    fn run_pending_launch(&mut self, result: Result<()>, cx: &mut Context<Self>) {
+        match result.map(|()| self.state.pending_launch.take()) {
+            Ok(Some(job)) => self.run_launch(job, cx),
+            Ok(None) => {}
+            Err(e) => self.finish_launch(Err(e), cx),
+        }
+    }
+
+    /// Run a launch on the background executor: hooks and the terminal's startup check
+    /// can take seconds, and the window should keep drawing meanwhile
+    fn run_launch(&mut self, job: LaunchJob, cx: &mut Context<Self>) {
+        self.launching = true;
+        cx.spawn(async move |this, cx| {
+            let report = cx
+                .background_executor()
+                .spawn(async move { job.run() })
+                .await;
+            let _ = this.update(cx, |app, cx| {
+                app.launching = false;
+                let result = app.state.finish_launch(report);
+                if result.is_ok()
+                    && let Some(outcome) = &app.state.last_launch
+                {
+                    Logger::debug(&format!(
+                        "Launched {} (pid {}): {} {:?}",
+                        outcome.host, outcome.pid, outcome.command.program, outcome.command.args
+                    ));
+                }
+                app.finish_launch(result, cx);
+            });
+        })
+        .detach();
+    }
+
+    fn open_sftp(&mut self, cx: &mut Context<Self>) {
+        let options = LaunchOptions {
+            sftp: true,
+            ..Default::default()
+        };
+        self.launch_selected_host(options, cx);
+    }
+
+    fn open_in_file_manager(&mut self, cx: &mut Context<Self>) {
+        let result = self
+            .sync_selected_host()
+            .and_then(|_| self.state.update(Message::OpenInFileManager));
+        self.finish_launch(result, cx);
+    }
+
+    fn copy_scp_command(&mut self, cx: &mut Context<Self>) {
+        let _ = self.sync_selected_host();
+        if let Some(command) = self.state.selected_scp_command() {
+            Logger::info(&format!("Copied to clipboard: {}", command));
+            cx.write_to_clipboard(ClipboardItem::new_string(command));
+            cx.quit();
+        }
+    }
+
+    /// Show and copy what launching the selected host would execute, keeping the window open
+    fn dry_run_selected_host(&mut self, options: LaunchOptions, cx: &mut Context<Self>) {
+        let result = self
+            .sync_selected_host()
+            .and_then(|_| self.state.update(Message::DryRunSelectedHost(options)));
+        if let Err(e) = result {
+            Logger::error(&format!("Dry run failed: {:#}", e));
+        } else if let Some(dry_run) = &self.state.dry_run {
+            Logger::debug(&format!("Dry run:\n{}", dry_run));
+            cx.write_to_clipboard(ClipboardItem::new_string(dry_run.to_string()));
         }
+        cx.notify();
+    }
+
+    /// Run a message against the host selected in the list, keeping the window open
+    fn update_selected_host(&mut self, message: Message, cx: &mut Context<Self>) {
+        let result = self
+            .sync_selected_host()
+            .and_then(|_| self.state.update(message));
+        if let Err(e) = result {
+            Logger::error(&format!("{:#}", e));
+        }
+        self.host_list
+            .set_changed_host_keys(self.changed_host_keys());
+        cx.notify();
+    }
+
+    /// Scan the selected host's keys in the background and show the result when it's in
+    ///
+    /// ssh-keyscan waits up to its timeout for slow or silent servers.
+    fn verify_selected_host_key(&mut self, cx: &mut Context<Self>) {
+        // Open the details pane right away; the verification line fills in later
+        self.update_selected_host(Message::ShowHostDetails, cx);
+        let Some(host) = self.state.get_selected_host().cloned() else {
+            return;
+        };
+        let config = self.state.config.host_keys.clone();
+        cx.spawn(async move |this, cx| {
+            let scanned = cx
+                .background_executor()
+                .spawn({
+                    let host = host.clone();
+                    async move { ssh::host_keys::scan(&config, &host) }
+                })
+                .await;
+            let _ = this.update(cx, |app, cx| {
+                if let Err(e) = app.state.finish_host_key_check(&host, scanned) {
+                    Logger::error(&format!("{:#}", e));
+                }
+                app.host_list.set_changed_host_keys(app.changed_host_keys());
+                cx.notify();
+            });
+        })
+        .detach();
+    }
+
+    /// Hosts whose last verification found a different key
+    fn changed_host_keys(&self) -> BTreeSet<String> {
+        self.state
+            .host_key_checks
+            .iter()
+            .filter(|(_, check)| check.is_changed())
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+
+    /// Hand a tunnel for the selected host to the menubar app, which runs it after we quit
+    fn start_tunnel(&mut self, options: LaunchOptions) -> Result<()> {
+        self.sync_selected_host()?;
+        self.state.update(Message::StartTunnel(options))?;
+
+        if let Some(tunnel) = self.state.last_tunnel.clone() {
+            let label = tunnel.label();
+            TunnelStore::default_path()
+                .and_then(|path| TunnelStore::modify(&path, |store| store.add(tunnel)))
+                .inspect_err(|e| self.state.error_message = Some(format!("{:#}", e)))?;
+            Logger::info(&format!("Started tunnel {} in the background", label));
+        }
+        Ok(())
+    }
 
-        None
+    fn launch_marked_hosts(
+        &mut self,
+        layout: BroadcastLayout,
+        options: LaunchOptions,
+        confirmed: bool,
+        cx: &mut Context<Self>,
+    ) {
+        if self.launching {
+            return;
+        }
+        let hosts = self.host_list.marked_hosts();
+        if !confirmed && self.state.needs_broadcast_confirmation(hosts.len()) {
+            // Ask first rather than opening dozens of terminals by accident
+            self.pending_broadcast = Some((layout, options));
+            cx.notify();
+            return;
+        }
+
+        let result = self.state.update(Message::LaunchHosts {
+            hosts,
+            layout,
+            options,
+            confirmed,
+        });
+        self.run_pending_launch(result, cx);
     }
 
-    fn launch_host(&self, host: &HostEntry) -> Result<()> {
-        self.terminal_launcher.launch(host)
+    /// Close the launcher after a successful launch; on failure stay open so the
+    /// error in `AppState::error_message` is visible
+    fn finish_launch(&mut self, result: Result<()>, cx: &mut Context<Self>) {
+        match result {
+            Ok(()) => cx.quit(),
+            Err(e) => {
+                Logger::error(&format!("Launch failed: {:#}", e));
+                cx.notify();
+            }
+        }
     }
 
     fn reload_config_and_hosts(&mut self) {
@@ -470,13 +1155,13 @@ impl TridentApp {
                     // Update app state with new config
                     self.state.config = new_config.clone();
 
-                    // Update terminal launcher with new config
-                    self.terminal_launcher = TerminalLauncher::new(new_config.terminal.clone());
-
                     // Reload SSH hosts with new config
                     let new_hosts = Self::load_ssh_hosts(&new_config);
                     self.state.hosts = new_hosts.clone();
                     self.state.filtered_hosts = new_hosts.clone();
+                    // Host addresses may have changed, so probe again
+                    self.probed_hosts.clear();
+                    self.host_list.probes.clear();
 
                     // Update host list and clear current search
                     self.host_list.set_hosts(new_hosts.clone());
@@ -533,7 +1218,27 @@ impl Render for TridentApp {
                     .shadow_lg()
                     .overflow_hidden()
                     .child(self.render_search_input(cx))
-                    .child(self.render_host_list_always(cx)),
+                    .map(|this| match self.action_menu.clone() {
+                        Some(menu) => this.child(menu),
+                        None => this.child(self.render_host_list_always(cx)),
+                    })
+                    .when_some(self.state.error_message.clone(), |this, error| {
+                        this.child(self.render_error(error))
+                    })
+                    .when_some(
+                        self.state.dry_run.as_ref().map(ToString::to_string),
+                        |this, report| this.child(self.render_dry_run(report)),
+                    )
+                    .when_some(
+                        self.state.host_details.as_ref().map(ToString::to_string),
+                        |this, report| this.child(self.render_host_details(report)),
+                    )
+                    .when(self.pending_broadcast.is_some(), |this| {
+                        this.child(self.render_broadcast_prompt(cx))
+                    })
+                    .when(Logger::debug_enabled(), |this| {
+                        this.child(self.render_debug_overlay(cx))
+                    }),
             )
     }
 }
@@ -547,6 +1252,11 @@ fn main() -> Result<()> {
         Logger::info("Launching Trident window...");
         return launch_ssh_launcher();
     }
+
+    // `trident search [--explain|--dry-run] <query>` prints ranked hosts without opening a window
+    if args.len() > 1 && args[1] == "search" {
+        return run_search_command(&args[2..]);
+    }
     
     Logger::info("Starting Trident SSH Launcher...");
     
@@ -554,6 +1264,67 @@ fn main() -> Result<()> {
     run_menubar_app()
 }
 
+#[cfg(not(test))]
+fn run_search_command(args: &[String]) -> Result<()> {
+    let explain = args.iter().any(|arg| arg == "--explain");
+    let dry_run = args.iter().any(|arg| arg == "--dry-run");
+    let query = args
+        .iter()
+        .filter(|arg| *arg != "--explain" && *arg != "--dry-run")
+        .cloned()
+        .collect::<Vec<_>>()
+        .join(" ");
+    // `host > command` searches with the host part, like the launcher
+    let (query, remote_command) = app::split_command_query(&query);
+
+    // Use the existing config without generating one, so the command has no side effects
+    let config_path = Config::default_config_path()?;
+    let mut config = if config_path.exists() {
+        Config::load_from_file(&config_path)?
+    } else {
+        Config::default()
+    };
+    config.expand_path()?;
+
+    let mut state = AppState::new();
+    state.history = LaunchHistory::load(&LaunchHistory::default_path()?)?;
+    state.update(app::Message::LoadConfig(Box::new(config)))?;
+    if let Some(error) = &state.error_message {
+        anyhow::bail!("Failed to load hosts: {}", error);
+    }
+
+    let engine = state.search_engine();
+    let results = engine.explain(
+        query,
+        &fuzzy::MatchOptions::from_ui_config(&state.config.ui),
+        state.config.ui.max_results,
+    );
+
+    if dry_run {
+        // Describe what Enter would launch for the top result
+        let Some(top) = results.first() else {
+            anyhow::bail!("No host matches '{}'", query);
+        };
+        state.filtered_hosts = vec![top.host.clone()];
+        state.update(app::Message::SelectHost(0))?;
+        state.update(app::Message::DryRunSelectedHost(LaunchOptions {
+            remote_command: remote_command.map(str::to_string),
+            ..Default::default()
+        }))?;
+        if let Some(dry_run) = &state.dry_run {
+            print!("{}", dry_run);
+        }
+    } else if explain {
+        print!("{}", fuzzy::format_explanation(&results));
+    } else {
+        for ranked in &results {
+            println!("{}", ranked.host.name);
+        }
+    }
+
+    Ok(())
+}
+
 fn run_menubar_app() -> Result<()> {
     Application::new().run(|cx: &mut App| {
         // Configure as background agent app (hide dock icon)
@@ -594,6 +1365,44 @@ fn run_menubar_app() -> Result<()> {
         
         Logger::info("Native menubar created! Look for the ψ (trident) icon in your menubar");
         
+        // Forward-only tunnels live here rather than in the launcher, which quits after each launch
+        match TunnelStore::default_path() {
+            Ok(store_path) => {
+                let supervisor = Arc::new(Mutex::new(TunnelSupervisor::new(Backoff::default())));
+                tunnel::spawn_supervisor_thread(store_path.clone(), supervisor.clone());
+
+                let list_supervisor = supervisor.clone();
+                let stop_supervisor = supervisor.clone();
+                menubar.set_tunnel_callbacks(
+                    move || {
+                        let now = Instant::now();
+                        list_supervisor
+                            .lock()
+                            .map(|supervisor| {
+                                supervisor
+                                    .statuses()
+                                    .into_iter()
+                                    .map(|status| (status.id.clone(), status.describe(now)))
+                                    .collect()
+                            })
+                            .unwrap_or_default()
+                    },
+                    move |id| {
+                        if let Err(e) = tunnel::stop_tunnel(&store_path, &stop_supervisor, id) {
+                            Logger::error(&format!("Failed to stop tunnel {}: {:#}", id, e));
+                        }
+                    },
+                    // Stopped on quit but kept in the store, so the next start restores them
+                    move || {
+                        if let Ok(mut supervisor) = supervisor.lock() {
+                            supervisor.shutdown();
+                        }
+                    },
+                );
+            }
+            Err(e) => Logger::error(&format!("Background tunnels are unavailable: {:#}", e)),
+        }
+        
         // Keep the menubar alive by forgetting it
         std::mem::forget(menubar);
         
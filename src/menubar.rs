@@ -1,13 +1,13 @@
 // ABOUTME: Native macOS menubar integration using objc2 and NSStatusItem
-// ABOUTME: Provides proper system menubar icon with automatic dark mode support
+// ABOUTME: Provides proper system menubar icon with automatic dark mode support and a Tunnels submenu
 
 #[cfg(target_os = "macos")]
-use objc2::runtime::AnyObject;
+use objc2::runtime::{AnyObject, ProtocolObject};
 #[cfg(target_os = "macos")]
 use objc2::{declare_class, msg_send, msg_send_id, mutability, ClassType, DeclaredClass};
 #[cfg(target_os = "macos")]
 use objc2_app_kit::{
-    NSApplication, NSImage, NSMenu, NSMenuItem, NSStatusBar, NSStatusItem,
+    NSApplication, NSImage, NSMenu, NSMenuDelegate, NSMenuItem, NSStatusBar, NSStatusItem,
     NSVariableStatusItemLength,
 };
 #[cfg(target_os = "macos")]
@@ -26,6 +26,20 @@ pub struct TridentMenuBar {
 #[cfg(target_os = "macos")]
 static GLOBAL_CALLBACK: std::sync::Mutex<Option<Arc<dyn Fn() + Send + Sync>>> = std::sync::Mutex::new(None);
 
+/// Lists supervised tunnels as `(id, menu text)`, stops one by id, and stops all on quit
+pub struct TunnelCallbacks {
+    pub list: Box<dyn Fn() -> Vec<(String, String)> + Send + Sync>,
+    pub stop: Box<dyn Fn(&str) + Send + Sync>,
+    pub shutdown: Box<dyn Fn() + Send + Sync>,
+}
+
+#[cfg(target_os = "macos")]
+static GLOBAL_TUNNELS: std::sync::Mutex<Option<TunnelCallbacks>> = std::sync::Mutex::new(None);
+
+// Tunnel ids behind the items of the Tunnels submenu, indexed by each item's tag
+#[cfg(target_os = "macos")]
+static TUNNEL_MENU_IDS: std::sync::Mutex<Vec<String>> = std::sync::Mutex::new(Vec::new());
+
 #[cfg(target_os = "macos")]
 declare_class!(
     struct MenuBarDelegate;
@@ -70,9 +84,29 @@ declare_class!(
             }
         }
 
+        #[method(stopTunnel:)]
+        fn stop_tunnel(&self, sender: Option<&AnyObject>) {
+            let Some(menu_item) = sender else {
+                return;
+            };
+            let tag: isize = unsafe { msg_send![menu_item, tag] };
+            let id = TUNNEL_MENU_IDS.lock().ok().and_then(|ids| ids.get(tag as usize).cloned());
+            if let (Some(id), Ok(tunnels)) = (id, GLOBAL_TUNNELS.lock()) {
+                crate::Logger::info(&format!("Stopping tunnel {} from the menubar", id));
+                if let Some(ref tunnels) = *tunnels {
+                    (tunnels.stop)(&id);
+                }
+            }
+        }
+
         #[method(quitTrident:)]
         fn quit_trident(&self, _sender: Option<&AnyObject>) {
             println!("[DEBUG] Menu item 'Quit Trident' clicked");
+            if let Ok(tunnels) = GLOBAL_TUNNELS.lock() {
+                if let Some(ref tunnels) = *tunnels {
+                    (tunnels.shutdown)();
+                }
+            }
             unsafe {
                 let app = NSApplication::sharedApplication(MainThreadMarker::new_unchecked());
                 app.terminate(None);
@@ -80,6 +114,46 @@ declare_class!(
         }
     }
 
+    unsafe impl NSMenuDelegate for MenuBarDelegate {
+        // Rebuild the Tunnels submenu each time it opens, so it shows current states
+        #[method(menuNeedsUpdate:)]
+        fn menu_needs_update(&self, menu: &NSMenu) {
+            let tunnels = GLOBAL_TUNNELS
+                .lock()
+                .ok()
+                .and_then(|tunnels| tunnels.as_ref().map(|tunnels| (tunnels.list)()))
+                .unwrap_or_default();
+            let mut ids = match TUNNEL_MENU_IDS.lock() {
+                Ok(ids) => ids,
+                Err(_) => return,
+            };
+            ids.clear();
+
+            unsafe {
+                let mtm = MainThreadMarker::new_unchecked();
+                menu.removeAllItems();
+
+                if tunnels.is_empty() {
+                    let empty_item = NSMenuItem::new(mtm);
+                    empty_item.setTitle(&NSString::from_str("No tunnels running"));
+                    empty_item.setEnabled(false);
+                    menu.addItem(&empty_item);
+                }
+
+                for (index, (id, description)) in tunnels.into_iter().enumerate() {
+                    let tunnel_item = NSMenuItem::new(mtm);
+                    tunnel_item.setTitle(&NSString::from_str(&format!("Stop {}", description)));
+                    tunnel_item.setTarget(Some(self));
+                    tunnel_item.setAction(Some(objc2::sel!(stopTunnel:)));
+                    tunnel_item.setTag(index as isize);
+                    tunnel_item.setEnabled(true);
+                    menu.addItem(&tunnel_item);
+                    ids.push(id);
+                }
+            }
+        }
+    }
+
 );
 
 #[cfg(target_os = "macos")]
@@ -190,6 +264,29 @@ impl TridentMenuBar {
         }
     }
 
+    /// Show supervised tunnels in the Tunnels submenu, each with a Stop item
+    pub fn set_tunnel_callbacks<L, S, Q>(&mut self, list: L, stop: S, shutdown: Q)
+    where
+        L: Fn() -> Vec<(String, String)> + Send + Sync + 'static,
+        S: Fn(&str) + Send + Sync + 'static,
+        Q: Fn() + Send + Sync + 'static,
+    {
+        #[cfg(target_os = "macos")]
+        {
+            if let Ok(mut global_tunnels) = GLOBAL_TUNNELS.lock() {
+                *global_tunnels = Some(TunnelCallbacks {
+                    list: Box::new(list),
+                    stop: Box::new(stop),
+                    shutdown: Box::new(shutdown),
+                });
+            }
+        }
+        #[cfg(not(target_os = "macos"))]
+        {
+            let _ = (list, stop, shutdown);
+        }
+    }
+
     #[cfg(target_os = "macos")]
     pub fn create_status_item(&mut self) -> Result<(), Box<dyn std::error::Error>> {
         unsafe {
@@ -224,6 +321,16 @@ impl TridentMenuBar {
             open_item.setEnabled(true);
             menu.addItem(&open_item);
             
+            // Create "Tunnels" submenu, filled in by the delegate each time it opens
+            let tunnels_item = NSMenuItem::new(mtm);
+            tunnels_item.setTitle(&NSString::from_str("Tunnels"));
+            tunnels_item.setEnabled(true);
+            let tunnels_menu = NSMenu::new(mtm);
+            tunnels_menu.setAutoenablesItems(false);
+            tunnels_menu.setDelegate(Some(ProtocolObject::from_ref(&*delegate)));
+            tunnels_item.setSubmenu(Some(&tunnels_menu));
+            menu.addItem(&tunnels_item);
+            
             // Add separator
             let separator1 = NSMenuItem::separatorItem(mtm);
             menu.addItem(&separator1);
@@ -0,0 +1,274 @@
+// ABOUTME: ssh-agent awareness: lists loaded keys with `ssh-add -L` and matches them to IdentityFile
+// ABOUTME: Flags hosts whose key isn't loaded and builds the `ssh-add <file>` command that loads it
+
+use crate::config::AgentConfig;
+use crate::ssh::parser::HostEntry;
+use crate::ssh::process::run_with_timeout;
+use anyhow::{Context, Result};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+
+/// How long `ssh-add -L` gets before the agent counts as unreachable
+///
+/// A stale socket whose agent stopped answering otherwise blocks the listing forever.
+const SSH_ADD_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Public keys loaded in the agent, by key type and base64 blob
+///
+/// The blob is what a key's fingerprint is computed from, so matching blobs
+/// matches fingerprints.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AgentKeys {
+    keys: HashSet<(String, String)>,
+}
+
+impl AgentKeys {
+    /// Parse `ssh-add -L` output: one `type blob comment` line per key
+    pub fn parse(output: &str) -> Self {
+        let keys = output.lines().filter_map(public_key_id).collect();
+        Self { keys }
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Whether the key in an OpenSSH public key line is loaded
+    pub fn contains(&self, public_key: &str) -> bool {
+        public_key_id(public_key).is_some_and(|id| self.keys.contains(&id))
+    }
+}
+
+/// The type and blob of a public key line, skipping options such as `cert-authority`
+fn public_key_id(line: &str) -> Option<(String, String)> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    words.windows(2).find_map(|pair| {
+        let is_key_type = pair[0].starts_with("ssh-")
+            || pair[0].starts_with("ecdsa-")
+            || pair[0].starts_with("sk-");
+        is_key_type.then(|| (pair[0].to_string(), pair[1].to_string()))
+    })
+}
+
+/// The agent socket: `[agent] socket`, or `SSH_AUTH_SOCK` from the environment
+fn agent_socket(config: &AgentConfig) -> Option<String> {
+    config
+        .socket
+        .clone()
+        .or_else(|| std::env::var("SSH_AUTH_SOCK").ok())
+}
+
+/// List the agent's keys with `ssh-add -L`
+///
+/// An agent without keys is an empty list; an agent that can't be reached or doesn't
+/// answer in time is an error.
+pub fn loaded_keys(config: &AgentConfig) -> Result<AgentKeys> {
+    loaded_keys_within(config, SSH_ADD_TIMEOUT)
+}
+
+fn loaded_keys_within(config: &AgentConfig, timeout: Duration) -> Result<AgentKeys> {
+    let mut command = Command::new(&config.ssh_add_binary);
+    command.arg("-L");
+    if let Some(socket) = &config.socket {
+        command.env("SSH_AUTH_SOCK", socket);
+    }
+    let output = run_with_timeout(&mut command, timeout)
+        .with_context(|| format!("Failed to run '{} -L'", config.ssh_add_binary))?;
+
+    // ssh-add exits with 1 when the agent has no identities and 2 when it can't connect
+    match output.status.code() {
+        Some(0) => Ok(AgentKeys::parse(&String::from_utf8_lossy(&output.stdout))),
+        Some(1) => Ok(AgentKeys::default()),
+        _ => {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!(
+                "No ssh agent at {}: {}",
+                agent_socket(config)
+                    .as_deref()
+                    .unwrap_or("SSH_AUTH_SOCK (unset)"),
+                stderr.trim()
+            );
+        }
+    }
+}
+
+/// A host's `IdentityFile`s whose public key is known but not loaded in the agent
+///
+/// Empty when the host names no identity file, one of its keys is loaded, or none of
+/// its keys has a readable `.pub` file to compare with.
+pub fn missing_keys(host: &HostEntry, keys: &AgentKeys) -> Vec<PathBuf> {
+    let mut missing = Vec::new();
+    for file in &host.identity_files {
+        let path = expand_identity_path(file);
+        let Some(public_key) = read_public_key(&path) else {
+            continue;
+        };
+        if keys.contains(&public_key) {
+            return Vec::new();
+        }
+        missing.push(path);
+    }
+    missing
+}
+
+/// The argv that loads a key into the agent, run in a terminal so ssh-add can ask for the passphrase
+pub fn add_key_argv(config: &AgentConfig, key: &Path) -> Vec<String> {
+    let mut argv = Vec::new();
+    if let Some(socket) = &config.socket {
+        argv.push("env".to_string());
+        argv.push(format!("SSH_AUTH_SOCK={}", socket));
+    }
+    argv.push(config.ssh_add_binary.clone());
+    argv.push(key.to_string_lossy().to_string());
+    argv
+}
+
+fn read_public_key(private_key: &Path) -> Option<String> {
+    let mut public = private_key.as_os_str().to_owned();
+    public.push(".pub");
+    fs::read_to_string(public).ok()
+}
+
+/// Expand `~` and `%d` (the home directory) in an `IdentityFile` path
+fn expand_identity_path(file: &str) -> PathBuf {
+    let Some(home) = dirs::home_dir() else {
+        return PathBuf::from(file);
+    };
+    if let Some(rest) = file.strip_prefix("~/") {
+        home.join(rest)
+    } else {
+        PathBuf::from(file.replace("%d", &home.to_string_lossy()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const WORK_KEY: &str = "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIWork work@laptop";
+    const HOME_KEY: &str = "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIHome me@home";
+
+    /// Write a key pair's public half and return the private key path
+    fn key_file(dir: &TempDir, name: &str, public_key: &str) -> String {
+        let path = dir.path().join(name);
+        fs::write(path.with_extension("pub"), format!("{}\n", public_key)).unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn test_parse_ssh_add_output() {
+        let keys = AgentKeys::parse(&format!(
+            "{}\necdsa-sha2-nistp256 AAAAE2VjZHNh laptop\n\n",
+            WORK_KEY
+        ));
+
+        assert_eq!(keys.len(), 2);
+        assert!(keys.contains("ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIWork other-comment"));
+        assert!(!keys.contains(HOME_KEY));
+    }
+
+    #[test]
+    fn test_missing_keys_for_host() {
+        let dir = TempDir::new().unwrap();
+        let keys = AgentKeys::parse(WORK_KEY);
+        let mut host = HostEntry::new("web".to_string(), "ssh web".to_string());
+
+        // No IdentityFile: ssh picks default keys, so there is nothing to check
+        assert!(missing_keys(&host, &keys).is_empty());
+
+        host.identity_files = vec![key_file(&dir, "id_home", HOME_KEY)];
+        assert_eq!(missing_keys(&host, &keys), vec![dir.path().join("id_home")]);
+
+        // Any loaded key is enough
+        host.identity_files
+            .push(key_file(&dir, "id_work", WORK_KEY));
+        assert!(missing_keys(&host, &keys).is_empty());
+
+        // Without a .pub file the key can't be compared
+        host.identity_files = vec![dir.path().join("id_unknown").display().to_string()];
+        assert!(missing_keys(&host, &keys).is_empty());
+    }
+
+    #[test]
+    fn test_add_key_argv() {
+        let mut config = AgentConfig::default();
+        assert_eq!(
+            add_key_argv(&config, Path::new("/home/me/.ssh/id_work")),
+            vec!["ssh-add", "/home/me/.ssh/id_work"]
+        );
+
+        config.socket = Some("/tmp/agent.sock".to_string());
+        assert_eq!(
+            add_key_argv(&config, Path::new("/home/me/.ssh/id_work")),
+            vec![
+                "env",
+                "SSH_AUTH_SOCK=/tmp/agent.sock",
+                "ssh-add",
+                "/home/me/.ssh/id_work"
+            ]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_loaded_keys_from_stub_ssh_add() {
+        use crate::test_support::write_script;
+
+        let dir = TempDir::new().unwrap();
+        // Lists a key only when pointed at the configured socket
+        let stub = write_script(
+            dir.path(),
+            "ssh-add",
+            &format!(
+                "[ \"$1\" = -L ] || exit 3\ncase \"$SSH_AUTH_SOCK\" in\n  /tmp/full.sock) echo '{}' ;;\n  /tmp/empty.sock) echo 'The agent has no identities.'; exit 1 ;;\n  *) echo 'Could not open a connection to your authentication agent.' >&2; exit 2 ;;\nesac",
+                WORK_KEY
+            ),
+        );
+
+        let mut config = AgentConfig {
+            ssh_add_binary: stub.to_string_lossy().to_string(),
+            socket: Some("/tmp/full.sock".to_string()),
+        };
+        let keys = loaded_keys(&config).unwrap();
+        assert!(keys.contains(WORK_KEY));
+
+        config.socket = Some("/tmp/empty.sock".to_string());
+        assert!(loaded_keys(&config).unwrap().is_empty());
+
+        config.socket = Some("/tmp/missing.sock".to_string());
+        let error = loaded_keys(&config).unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .starts_with("No ssh agent at /tmp/missing.sock")
+        );
+        assert!(error.to_string().contains("Could not open a connection"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_unresponsive_agent_times_out() {
+        use crate::test_support::write_script;
+
+        let dir = TempDir::new().unwrap();
+        // Like ssh-add on a stale socket whose agent never answers
+        let stub = write_script(dir.path(), "ssh-add", "exec sleep 10");
+        let config = AgentConfig {
+            ssh_add_binary: stub.to_string_lossy().to_string(),
+            socket: Some("/tmp/stale.sock".to_string()),
+        };
+
+        let started = std::time::Instant::now();
+        let error = loaded_keys_within(&config, Duration::from_millis(200)).unwrap_err();
+        assert!(format!("{:#}", error).contains("timed out"));
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+}
@@ -0,0 +1,99 @@
+// ABOUTME: Launching several marked hosts at once, as separate terminals or one tiled tmux window
+// ABOUTME: Builds the local tmux command that tiles a pane per host with synchronized input
+
+use crate::ssh::launcher::shell_join;
+
+/// A local tmux command opening one tiled pane per connection, with `synchronize-panes` on
+///
+/// Each pane runs its connection argv as a shell command. Re-tiling after every split keeps
+/// panes from getting too small to split again. Returns `None` without connections.
+pub fn tiled_tmux_argv(tmux: &str, connections: &[Vec<String>]) -> Option<Vec<String>> {
+    let (first, rest) = connections.split_first()?;
+
+    let mut argv = vec![
+        tmux.to_string(),
+        "new-session".to_string(),
+        shell_join(first),
+    ];
+    for connection in rest {
+        argv.extend(
+            [";", "split-window"]
+                .iter()
+                .map(|arg| arg.to_string())
+                .chain([shell_join(connection)]),
+        );
+        argv.extend(
+            [";", "select-layout", "tiled"]
+                .iter()
+                .map(|arg| arg.to_string()),
+        );
+    }
+    argv.extend(
+        [";", "set-window-option", "synchronize-panes", "on"]
+            .iter()
+            .map(|arg| arg.to_string()),
+    );
+    Some(argv)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ssh(host: &str) -> Vec<String> {
+        vec!["/usr/bin/ssh".to_string(), host.to_string()]
+    }
+
+    #[test]
+    fn test_tiled_tmux_argv() {
+        let argv = tiled_tmux_argv("tmux", &[ssh("web1"), ssh("web2"), ssh("web 3")]).unwrap();
+
+        assert_eq!(
+            argv,
+            vec![
+                "tmux",
+                "new-session",
+                "/usr/bin/ssh web1",
+                ";",
+                "split-window",
+                "/usr/bin/ssh web2",
+                ";",
+                "select-layout",
+                "tiled",
+                ";",
+                "split-window",
+                "/usr/bin/ssh 'web 3'",
+                ";",
+                "select-layout",
+                "tiled",
+                ";",
+                "set-window-option",
+                "synchronize-panes",
+                "on"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tiled_tmux_argv_single_host() {
+        let argv = tiled_tmux_argv("/opt/bin/tmux", &[ssh("db")]).unwrap();
+
+        assert_eq!(
+            argv,
+            vec![
+                "/opt/bin/tmux",
+                "new-session",
+                "/usr/bin/ssh db",
+                ";",
+                "set-window-option",
+                "synchronize-panes",
+                "on"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tiled_tmux_argv_without_hosts() {
+        assert_eq!(tiled_tmux_argv("tmux", &[]), None);
+    }
+}
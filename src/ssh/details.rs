@@ -0,0 +1,98 @@
+// ABOUTME: Host details report: where a host connects and the host keys known_hosts stores for it
+// ABOUTME: Includes the result of the last "Verify host key" check when there is one
+
+use crate::ssh::host_keys::{HostKey, KeyCheck};
+use crate::ssh::parser::HostEntry;
+use crate::ssh::transport::DEFAULT_SSH_PORT;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HostDetails {
+    pub host: String,
+    pub address: String,
+    pub port: u16,
+    pub user: Option<String>,
+    pub proxy: Option<String>,
+    pub description: Option<String>,
+    /// Keys stored in known_hosts
+    pub keys: Vec<HostKey>,
+    /// Result of the last ssh-keyscan comparison, if the host was verified
+    pub check: Option<KeyCheck>,
+}
+
+impl HostDetails {
+    pub fn new(host: &HostEntry, keys: &[HostKey], check: Option<&KeyCheck>) -> Self {
+        Self {
+            host: host.name.clone(),
+            address: host.hostname.clone().unwrap_or_else(|| host.name.clone()),
+            port: host.port.unwrap_or(DEFAULT_SSH_PORT),
+            user: host.user.clone(),
+            proxy: host.proxy.clone(),
+            description: host.description.clone(),
+            keys: keys.to_vec(),
+            check: check.cloned(),
+        }
+    }
+}
+
+/// One `key: value` line per field, with a line per stored host key
+impl fmt::Display for HostDetails {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "host: {}", self.host)?;
+        if let Some(description) = &self.description {
+            writeln!(f, "description: {}", description)?;
+        }
+        writeln!(f, "address: {}:{}", self.address, self.port)?;
+        if let Some(user) = &self.user {
+            writeln!(f, "user: {}", user)?;
+        }
+        if let Some(proxy) = &self.proxy {
+            writeln!(f, "via: {}", proxy)?;
+        }
+        if self.keys.is_empty() {
+            writeln!(f, "host keys: none in known_hosts")?;
+        } else {
+            writeln!(f, "host keys:")?;
+            for key in &self.keys {
+                writeln!(f, "  {}", key)?;
+            }
+        }
+        match &self.check {
+            Some(check) => writeln!(f, "verified: {}", check),
+            None => writeln!(f, "verified: not yet (Cmd+K, Verify host key)"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_details_lines() {
+        let key = HostKey::parse(
+            "ssh-ed25519",
+            "AAAAC3NzaC1lZDI1NTE5AAAAIPQVbVw/rj+RTMBVLm7CactYAToJ7Wo1rv11TcTKzixY",
+        )
+        .unwrap();
+        let mut host = HostEntry::new("web".to_string(), "ssh web".to_string());
+        host.hostname = Some("web.example.com".to_string());
+        host.user = Some("deploy".to_string());
+
+        let details = HostDetails::new(&host, std::slice::from_ref(&key), None);
+        assert_eq!(
+            details.to_string(),
+            "host: web\n\
+            address: web.example.com:22\n\
+            user: deploy\n\
+            host keys:\n  ED25519 SHA256:vthKWWjqXX8vAhdvFYJAOWZR8GGQKPz6WIoeZeaNb+4\n\
+            verified: not yet (Cmd+K, Verify host key)\n"
+        );
+
+        let check = KeyCheck::compare(std::slice::from_ref(&key), std::slice::from_ref(&key));
+        let details = HostDetails::new(&host, &[], Some(&check));
+        let report = details.to_string();
+        assert!(report.contains("host keys: none in known_hosts\n"));
+        assert!(report.contains("verified: Host key matches known_hosts (ED25519 SHA256:"));
+    }
+}
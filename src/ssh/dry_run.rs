@@ -0,0 +1,118 @@
+// ABOUTME: Dry-run report of the exact process a launch would spawn, without spawning it
+// ABOUTME: Lists the program, argv, environment and working directory after substitution and quoting
+
+use crate::ssh::launcher::{LaunchCommand, shell_join};
+use std::fmt;
+use std::path::PathBuf;
+
+/// What a launch would execute
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DryRun {
+    pub host: String,
+    pub program: String,
+    /// The full argv, starting with the program
+    pub argv: Vec<String>,
+    /// Variables set for the process on top of Trident's own environment
+    pub env: Vec<(String, String)>,
+    pub working_dir: PathBuf,
+}
+
+impl DryRun {
+    /// Describe `command`, which runs in `working_dir`
+    pub fn new(host: &str, command: &LaunchCommand, working_dir: PathBuf) -> Self {
+        Self {
+            host: host.to_string(),
+            program: command.program.clone(),
+            argv: std::iter::once(command.program.clone())
+                .chain(command.args.iter().cloned())
+                .collect(),
+            env: command.env.clone(),
+            working_dir,
+        }
+    }
+
+    /// The argv as one line that can be pasted into a shell
+    pub fn command_line(&self) -> String {
+        shell_join(&self.argv)
+    }
+}
+
+/// One `key: value` line per field; argv elements are quoted so whitespace stays visible
+impl fmt::Display for DryRun {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "host: {}", self.host)?;
+        writeln!(f, "program: {}", self.program)?;
+        writeln!(f, "argv:")?;
+        for (index, arg) in self.argv.iter().enumerate() {
+            writeln!(f, "  [{}] {:?}", index, arg)?;
+        }
+        if self.env.is_empty() {
+            writeln!(f, "environment: inherited")?;
+        } else {
+            writeln!(f, "environment: inherited, plus")?;
+            for (name, value) in &self.env {
+                writeln!(f, "  {}={:?}", name, value)?;
+            }
+        }
+        writeln!(f, "working directory: {}", self.working_dir.display())?;
+        writeln!(f, "command line: {}", self.command_line())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Transport;
+    use crate::ssh::target::LaunchTarget;
+
+    fn command() -> LaunchCommand {
+        let ssh_argv = vec!["/usr/bin/ssh".to_string(), "web 1".to_string()];
+        LaunchCommand {
+            profile: None,
+            program: "/usr/bin/xterm".to_string(),
+            args: vec![
+                "-e".to_string(),
+                "sh".to_string(),
+                "-c".to_string(),
+                shell_join(&ssh_argv),
+            ],
+            target: LaunchTarget::Window,
+            transport: Transport::Ssh,
+            ssh_argv,
+            env: Vec::new(),
+            working_dir: None,
+        }
+    }
+
+    #[test]
+    fn test_dry_run_report() {
+        let dry_run = DryRun::new("web 1", &command(), PathBuf::from("/home/me"));
+
+        assert_eq!(
+            dry_run.to_string(),
+            concat!(
+                "host: web 1\n",
+                "program: /usr/bin/xterm\n",
+                "argv:\n",
+                "  [0] \"/usr/bin/xterm\"\n",
+                "  [1] \"-e\"\n",
+                "  [2] \"sh\"\n",
+                "  [3] \"-c\"\n",
+                "  [4] \"/usr/bin/ssh 'web 1'\"\n",
+                "environment: inherited\n",
+                "working directory: /home/me\n",
+                "command line: /usr/bin/xterm -e sh -c '/usr/bin/ssh '\\''web 1'\\'''\n",
+            )
+        );
+    }
+
+    #[test]
+    fn test_dry_run_lists_added_env() {
+        let mut command = command();
+        command.env = vec![("TERM".to_string(), "xterm-256color".to_string())];
+        let dry_run = DryRun::new("web", &command, PathBuf::from("/"));
+
+        let report = dry_run.to_string();
+        assert!(report.contains("environment: inherited, plus\n  TERM=\"xterm-256color\"\n"));
+    }
+}
@@ -0,0 +1,178 @@
+// ABOUTME: Environment for launched terminals: the login shell's variables and [terminal.env]
+// ABOUTME: Captures `$SHELL -l -c env` once per process, since launchd gives apps a bare environment
+
+use crate::Logger;
+use crate::config::TerminalConfig;
+use crate::ssh::process::run_with_timeout;
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::process::Command;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+/// How long a login shell gets to print its environment
+const LOGIN_SHELL_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Variables describing the capturing shell itself rather than the user's setup
+const SHELL_STATE_VARS: &[&str] = &["_", "PWD", "OLDPWD", "SHLVL"];
+
+/// The variables to set on a terminal, on top of the inherited environment
+///
+/// With `inherit_login_shell_env`, the login shell's variables come first; `[terminal.env]`
+/// values override them.
+pub fn terminal_env(terminal: &TerminalConfig) -> Vec<(String, String)> {
+    let mut env = BTreeMap::new();
+    if terminal.inherit_login_shell_env
+        && let Some(login_env) = login_shell_env()
+    {
+        env.extend(login_env.clone());
+    }
+    env.extend(terminal.env.clone());
+    env.into_iter().collect()
+}
+
+/// Capture the login shell's environment ahead of the first launch
+///
+/// Capturing takes as long as the user's shell profile, so the app calls this from a
+/// background task at startup rather than on the first keypress.
+pub fn preload_login_env(terminal: &TerminalConfig) {
+    if terminal.inherit_login_shell_env {
+        login_shell_env();
+    }
+}
+
+/// The login shell's environment, captured on first use; `None` if that failed
+fn login_shell_env() -> Option<&'static BTreeMap<String, String>> {
+    static LOGIN_ENV: OnceLock<Option<BTreeMap<String, String>>> = OnceLock::new();
+    LOGIN_ENV
+        .get_or_init(|| {
+            let shell = std::env::var("SHELL").unwrap_or_else(|_| "/bin/sh".to_string());
+            match capture_login_env(&shell, LOGIN_SHELL_TIMEOUT) {
+                Ok(env) => {
+                    Logger::debug(&format!(
+                        "Captured {} variables from login shell {}",
+                        env.len(),
+                        shell
+                    ));
+                    Some(env)
+                }
+                Err(e) => {
+                    Logger::warn(&format!(
+                        "Failed to capture the login shell environment, using Trident's own: {:#}",
+                        e
+                    ));
+                    None
+                }
+            }
+        })
+        .as_ref()
+}
+
+/// Run `shell -l -c env` and parse its output
+pub fn capture_login_env(shell: &str, timeout: Duration) -> Result<BTreeMap<String, String>> {
+    let output = run_with_timeout(Command::new(shell).args(["-l", "-c", "env"]), timeout)
+        .with_context(|| format!("Failed to run '{} -l -c env'", shell))?;
+    if !output.status.success() {
+        anyhow::bail!("'{} -l -c env' exited with {}", shell, output.status);
+    }
+    Ok(parse_env_output(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Parse `env` output, where a line without `NAME=` continues a multi-line value
+///
+/// Lines before the first variable, such as a greeting printed by a profile, are skipped.
+pub fn parse_env_output(output: &str) -> BTreeMap<String, String> {
+    let mut env = BTreeMap::new();
+    let mut last: Option<String> = None;
+
+    for line in output.lines() {
+        let variable = line.split_once('=').filter(|(name, _)| is_env_name(name));
+        match (variable, &last) {
+            (Some((name, value)), _) => {
+                env.insert(name.to_string(), value.to_string());
+                last = Some(name.to_string());
+            }
+            (None, Some(name)) => {
+                if let Some(value) = env.get_mut(name) {
+                    value.push('\n');
+                    value.push_str(line);
+                }
+            }
+            (None, None) => {}
+        }
+    }
+
+    for name in SHELL_STATE_VARS {
+        env.remove(*name);
+    }
+    env
+}
+
+fn is_env_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_env_output() {
+        let output = "Welcome back!\nPATH=/opt/homebrew/bin:/usr/bin\nSSH_AUTH_SOCK=/tmp/agent.sock\nMOTD=line one\nline two\nEMPTY=\nSHLVL=2\nPWD=/home/me\n";
+        let env = parse_env_output(output);
+
+        assert_eq!(
+            env.into_iter().collect::<Vec<_>>(),
+            vec![
+                ("EMPTY".to_string(), String::new()),
+                ("MOTD".to_string(), "line one\nline two".to_string()),
+                ("PATH".to_string(), "/opt/homebrew/bin:/usr/bin".to_string()),
+                ("SSH_AUTH_SOCK".to_string(), "/tmp/agent.sock".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_config_env_overrides_login_env() {
+        let mut terminal = crate::config::Config::default().terminal;
+        terminal.env = BTreeMap::from([
+            ("LANG".to_string(), "en_US.UTF-8".to_string()),
+            ("TERM".to_string(), "xterm-256color".to_string()),
+        ]);
+
+        assert_eq!(
+            terminal_env(&terminal),
+            vec![
+                ("LANG".to_string(), "en_US.UTF-8".to_string()),
+                ("TERM".to_string(), "xterm-256color".to_string()),
+            ]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_capture_login_env_from_shell() {
+        use crate::test_support::write_script;
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let shell = write_script(
+            temp_dir.path(),
+            "login-shell",
+            "[ \"$1\" = -l ] || exit 1\necho 'Last login: today'\nprintf 'SSH_AUTH_SOCK=/run/agent\\nLANG=C.UTF-8\\n'",
+        );
+        let shell = shell.to_string_lossy().to_string();
+
+        let env = capture_login_env(&shell, Duration::from_secs(5)).unwrap();
+        assert_eq!(env.get("SSH_AUTH_SOCK").unwrap(), "/run/agent");
+        assert_eq!(env.get("LANG").unwrap(), "C.UTF-8");
+        assert_eq!(env.len(), 2);
+
+        let slow = write_script(temp_dir.path(), "slow-shell", "sleep 5");
+        let error =
+            capture_login_env(&slow.to_string_lossy(), Duration::from_millis(100)).unwrap_err();
+        assert!(format!("{:#}", error).contains("timed out"));
+    }
+}
@@ -0,0 +1,208 @@
+// ABOUTME: File transfer actions for a host: sftp in the terminal, a file manager URI and an scp command
+// ABOUTME: Fills each from the host's resolved name, user and port
+
+use crate::config::{Config, FilesConfig, find_executable};
+use crate::ssh::launcher::shell_quote;
+use crate::ssh::parser::HostEntry;
+use crate::ssh::transport::{DEFAULT_SSH_PORT, local_user};
+use anyhow::{Context, Result};
+use std::process::{Command, Stdio};
+
+/// The argv that opens sftp on a host
+///
+/// Like ssh, sftp connects to the alias so ssh config settings such as `ProxyJump` still apply.
+pub fn sftp_argv(config: &Config, host: &HostEntry) -> Vec<String> {
+    vec![config.files.sftp_binary.clone(), host.name.clone()]
+}
+
+/// sftp runs inside the terminal, where a missing binary is easy to miss, so check first
+pub fn check_sftp(config: &Config) -> Result<()> {
+    if find_executable(&config.files.sftp_binary).is_none() {
+        anyhow::bail!(
+            "sftp uses '{}', which was not found. Install it or set sftp_binary in [files].",
+            config.files.sftp_binary
+        );
+    }
+    Ok(())
+}
+
+/// The file manager URI for a host, e.g. `sftp://deploy@10.0.0.5:22/`
+///
+/// `{host}` is the resolved `HostName` (or the alias), `{user}` falls back to the local user
+/// and `{port}` to 22. The user is percent-encoded and IPv6 addresses get brackets.
+pub fn file_manager_uri(files: &FilesConfig, host: &HostEntry) -> String {
+    let hostname = host.hostname.as_deref().unwrap_or(&host.name);
+    let hostname = if hostname.contains(':') {
+        format!("[{}]", hostname)
+    } else {
+        hostname.to_string()
+    };
+    let user = percent_encode(&host.user.clone().unwrap_or_else(local_user));
+    let port = host.port.unwrap_or(DEFAULT_SSH_PORT).to_string();
+
+    files
+        .file_manager_uri
+        .replace("{user}", &user)
+        .replace("{host}", &hostname)
+        .replace("{port}", &port)
+}
+
+/// The scp command copied to the clipboard, e.g. `scp -P 22 FILE deploy@10.0.0.5:`
+///
+/// Placeholders are the same as for the file manager URI, each quoted for the shell.
+pub fn scp_command(files: &FilesConfig, host: &HostEntry) -> String {
+    let hostname = host.hostname.as_deref().unwrap_or(&host.name);
+    let user = host.user.clone().unwrap_or_else(local_user);
+    let port = host.port.unwrap_or(DEFAULT_SSH_PORT).to_string();
+
+    files
+        .scp_template
+        .replace("{user}", &shell_quote(&user))
+        .replace("{host}", &shell_quote(hostname))
+        .replace("{port}", &port)
+}
+
+/// Open a host's file manager URI with the configured `open_command`
+pub fn open_in_file_manager(files: &FilesConfig, host: &HostEntry) -> Result<String> {
+    let uri = file_manager_uri(files, host);
+    let output = Command::new(&files.open_command)
+        .arg(&uri)
+        .stdin(Stdio::null())
+        .output()
+        .with_context(|| format!("Failed to run '{}'", files.open_command))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!(
+            "'{} {}' exited with {}: {}",
+            files.open_command,
+            uri,
+            output.status,
+            stderr.trim()
+        );
+    }
+    Ok(uri)
+}
+
+/// Percent-encode everything but unreserved URI characters
+fn percent_encode(text: &str) -> String {
+    text.bytes()
+        .map(|byte| {
+            if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+                (byte as char).to_string()
+            } else {
+                format!("%{:02X}", byte)
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_host() -> HostEntry {
+        let mut host = HostEntry::new("db".to_string(), "ssh db".to_string());
+        host.hostname = Some("10.2.0.15".to_string());
+        host.user = Some("postgres".to_string());
+        host.port = Some(2222);
+        host
+    }
+
+    #[test]
+    fn test_sftp_connects_to_alias() {
+        let mut config = Config::default();
+        config.files.sftp_binary = "/opt/bin/sftp".to_string();
+
+        assert_eq!(
+            sftp_argv(&config, &test_host()),
+            vec!["/opt/bin/sftp", "db"]
+        );
+    }
+
+    #[test]
+    fn test_missing_sftp_binary_is_reported() {
+        let mut config = Config::default();
+        config.files.sftp_binary = "/nonexistent/sftp".to_string();
+
+        let error = check_sftp(&config).unwrap_err();
+        assert!(error.to_string().contains("set sftp_binary in [files]"));
+    }
+
+    #[test]
+    fn test_file_manager_uri_from_host_fields() {
+        let files = FilesConfig::default();
+        assert_eq!(
+            file_manager_uri(&files, &test_host()),
+            "sftp://postgres@10.2.0.15:2222/"
+        );
+
+        let mut host = HostEntry::new("v6".to_string(), "ssh v6".to_string());
+        host.hostname = Some("fd00::1".to_string());
+        host.user = Some("ops@corp".to_string());
+        assert_eq!(
+            file_manager_uri(&files, &host),
+            "sftp://ops%40corp@[fd00::1]:22/"
+        );
+    }
+
+    #[test]
+    fn test_custom_file_manager_uri() {
+        let files = FilesConfig {
+            file_manager_uri: "ssh://{user}@{host}:{port}/home/{user}".to_string(),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            file_manager_uri(&files, &test_host()),
+            "ssh://postgres@10.2.0.15:2222/home/postgres"
+        );
+    }
+
+    #[test]
+    fn test_scp_command_quotes_fields() {
+        let files = FilesConfig::default();
+        assert_eq!(
+            scp_command(&files, &test_host()),
+            "scp -P 2222 FILE postgres@10.2.0.15:"
+        );
+
+        let mut host = test_host();
+        host.user = Some("it's me".to_string());
+        assert_eq!(
+            scp_command(&files, &host),
+            "scp -P 2222 FILE 'it'\\''s me'@10.2.0.15:"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_open_in_file_manager_runs_open_command() {
+        use crate::test_support::write_script;
+        use std::fs;
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let record = temp_dir.path().join("opened");
+        let opener = write_script(
+            temp_dir.path(),
+            "open",
+            &format!("printf '%s' \"$1\" > '{}'", record.display()),
+        );
+
+        let files = FilesConfig {
+            open_command: opener.display().to_string(),
+            ..Default::default()
+        };
+        let uri = open_in_file_manager(&files, &test_host()).unwrap();
+
+        assert_eq!(uri, "sftp://postgres@10.2.0.15:2222/");
+        assert_eq!(fs::read_to_string(&record).unwrap(), uri);
+
+        let failing = FilesConfig {
+            open_command: "false".to_string(),
+            ..Default::default()
+        };
+        assert!(open_in_file_manager(&failing, &test_host()).is_err());
+    }
+}
@@ -0,0 +1,179 @@
+// ABOUTME: Port-forward presets compiled into ssh -L, -R and -D arguments
+// ABOUTME: Checks forwards fit the transport and that their local ports are free before launching
+
+use crate::config::{ForwardKind, ForwardPreset, Transport};
+use anyhow::Result;
+use std::net::TcpListener;
+
+/// The ssh options for one forward, e.g. `["-L", "5432:localhost:5432"]`
+pub fn forward_args(preset: &ForwardPreset) -> Vec<String> {
+    let target = preset.target.as_deref().unwrap_or_default();
+    let (flag, spec) = match preset.kind {
+        ForwardKind::Local => ("-L", format!("{}:{}", preset.port, target)),
+        ForwardKind::Remote => ("-R", format!("{}:{}", preset.port, target)),
+        ForwardKind::Dynamic => ("-D", preset.port.to_string()),
+    };
+    vec![flag.to_string(), spec]
+}
+
+/// Human-readable summary for menus, e.g. `-N -L 5432:localhost:5432`
+pub fn describe(preset: &ForwardPreset) -> String {
+    let args = forward_args(preset).join(" ");
+    if preset.forward_only {
+        format!("-N {}", args)
+    } else {
+        args
+    }
+}
+
+/// Add forwards to a transport's argv, which ends in the host
+///
+/// Only ssh and autossh pass ssh options through. Any forward-only preset adds `-N`,
+/// so the connection holds the forwards open without starting a shell.
+pub fn with_forwards(
+    transport: Transport,
+    mut connect_argv: Vec<String>,
+    forwards: &[(&str, &ForwardPreset)],
+) -> Result<Vec<String>> {
+    if !matches!(transport, Transport::Ssh | Transport::Autossh) {
+        anyhow::bail!(
+            "Port forwards need the ssh or autossh transport, not {}",
+            transport.name()
+        );
+    }
+
+    let mut options = Vec::new();
+    if forwards.iter().any(|(_, preset)| preset.forward_only) {
+        options.push("-N".to_string());
+    }
+    for (_, preset) in forwards {
+        options.extend(forward_args(preset));
+    }
+
+    let host_index = connect_argv.len().saturating_sub(1);
+    connect_argv.splice(host_index..host_index, options);
+    Ok(connect_argv)
+}
+
+/// Fail if another program already listens on a forward's local port
+///
+/// ssh only warns when a forward can't bind and carries on, which is easy to miss in a new
+/// terminal, so check before launching.
+pub fn check_ports_available(forwards: &[(&str, &ForwardPreset)]) -> Result<()> {
+    for (name, preset) in forwards {
+        if let Some(port) = preset.local_port()
+            && TcpListener::bind(("127.0.0.1", port)).is_err()
+        {
+            anyhow::bail!(
+                "Local port {} for forward '{}' is already in use",
+                port,
+                name
+            );
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn preset(kind: ForwardKind, port: u16, target: Option<&str>) -> ForwardPreset {
+        ForwardPreset {
+            kind,
+            port,
+            target: target.map(str::to_string),
+            forward_only: false,
+        }
+    }
+
+    fn ssh_argv() -> Vec<String> {
+        vec!["/usr/bin/ssh".to_string(), "db".to_string()]
+    }
+
+    #[test]
+    fn test_forward_args_per_kind() {
+        assert_eq!(
+            forward_args(&preset(ForwardKind::Local, 5432, Some("localhost:5432"))),
+            vec!["-L", "5432:localhost:5432"]
+        );
+        assert_eq!(
+            forward_args(&preset(ForwardKind::Remote, 8080, Some("localhost:3000"))),
+            vec!["-R", "8080:localhost:3000"]
+        );
+        assert_eq!(
+            forward_args(&preset(ForwardKind::Dynamic, 1080, None)),
+            vec!["-D", "1080"]
+        );
+    }
+
+    #[test]
+    fn test_with_forwards_inserts_before_host() {
+        let postgres = preset(ForwardKind::Local, 5432, Some("localhost:5432"));
+        let socks = preset(ForwardKind::Dynamic, 1080, None);
+
+        let argv = with_forwards(
+            Transport::Ssh,
+            ssh_argv(),
+            &[("postgres", &postgres), ("socks", &socks)],
+        )
+        .unwrap();
+
+        assert_eq!(
+            argv,
+            vec![
+                "/usr/bin/ssh",
+                "-L",
+                "5432:localhost:5432",
+                "-D",
+                "1080",
+                "db"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_forward_only_adds_no_shell_flag() {
+        let mut postgres = preset(ForwardKind::Local, 5432, Some("localhost:5432"));
+        postgres.forward_only = true;
+
+        let argv = with_forwards(Transport::Ssh, ssh_argv(), &[("postgres", &postgres)]).unwrap();
+
+        assert_eq!(
+            argv,
+            vec!["/usr/bin/ssh", "-N", "-L", "5432:localhost:5432", "db"]
+        );
+        assert_eq!(describe(&postgres), "-N -L 5432:localhost:5432");
+    }
+
+    #[test]
+    fn test_forwards_need_ssh_transport() {
+        let socks = preset(ForwardKind::Dynamic, 1080, None);
+        let mosh = vec!["mosh".to_string(), "db".to_string()];
+
+        assert!(with_forwards(Transport::Mosh, mosh, &[("socks", &socks)]).is_err());
+
+        let autossh = vec!["autossh", "-M", "0", "db"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+        assert_eq!(
+            with_forwards(Transport::Autossh, autossh, &[("socks", &socks)]).unwrap(),
+            vec!["autossh", "-M", "0", "-D", "1080", "db"]
+        );
+    }
+
+    #[test]
+    fn test_port_in_use_is_reported() {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let busy = preset(ForwardKind::Local, port, Some("localhost:5432"));
+        let remote = preset(ForwardKind::Remote, port, Some("localhost:5432"));
+
+        let error = check_ports_available(&[("busy", &busy)]).unwrap_err();
+        assert!(error.to_string().contains("already in use"));
+
+        // Remote forwards listen on the host, not here
+        assert!(check_ports_available(&[("remote", &remote)]).is_ok());
+    }
+}
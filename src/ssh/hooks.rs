@@ -0,0 +1,252 @@
+// ABOUTME: Pre- and post-launch hook commands configured globally and per tag
+// ABOUTME: Runs each hook through the shell with host variables and a timeout
+
+use crate::Logger;
+use crate::config::Config;
+use crate::ssh::parser::HostEntry;
+use crate::ssh::process::run_with_timeout;
+use crate::ssh::transport::{DEFAULT_SSH_PORT, local_user};
+use anyhow::{Context, Result};
+use std::process::Command;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookStage {
+    PreLaunch,
+    PostLaunch,
+}
+
+impl HookStage {
+    pub fn name(self) -> &'static str {
+        match self {
+            HookStage::PreLaunch => "pre_launch",
+            HookStage::PostLaunch => "post_launch",
+        }
+    }
+}
+
+/// Run a stage's hooks for a host: the global hook first, then each tag's, stopping at the first failure
+pub fn run_hooks(config: &Config, stage: HookStage, host: &HostEntry) -> Result<()> {
+    for hooks in config.hooks_for(&host.name) {
+        let command = match stage {
+            HookStage::PreLaunch => hooks.pre_launch.as_deref(),
+            HookStage::PostLaunch => hooks.post_launch.as_deref(),
+        };
+        if let Some(command) = command {
+            run_hook(command, host, Duration::from_secs(hooks.timeout_secs))
+                .with_context(|| format!("The {} hook for '{}' failed", stage.name(), host.name))?;
+        }
+    }
+    Ok(())
+}
+
+/// Run a stage's hooks for several hosts side by side, one thread per host
+///
+/// Returns each host's result in the order of `hosts`.
+pub fn run_hooks_for_all(
+    config: &Config,
+    stage: HookStage,
+    hosts: &[HostEntry],
+) -> Vec<Result<()>> {
+    std::thread::scope(|scope| {
+        let runs: Vec<_> = hosts
+            .iter()
+            .map(|host| scope.spawn(move || run_hooks(config, stage, host)))
+            .collect();
+        runs.into_iter()
+            .map(|run| {
+                run.join()
+                    .unwrap_or_else(|_| Err(anyhow::anyhow!("Hook thread panicked")))
+            })
+            .collect()
+    })
+}
+
+/// Run one hook command with `sh -c`, failing on a non-zero exit or after `timeout`
+pub fn run_hook(command: &str, host: &HostEntry, timeout: Duration) -> Result<()> {
+    Logger::debug(&format!("Running hook for {}: {}", host.name, command));
+
+    let user = host.user.clone().unwrap_or_else(local_user);
+    let port = host.port.unwrap_or(DEFAULT_SSH_PORT).to_string();
+    let mut process = Command::new("/bin/sh");
+    process
+        .arg("-c")
+        .arg(command)
+        .env("TRIDENT_HOST", &host.name)
+        .env("TRIDENT_USER", user)
+        .env("TRIDENT_PORT", port);
+    let output = run_with_timeout(&mut process, timeout)
+        .map_err(|e| anyhow::anyhow!("'{}' failed: {:#}", command, e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let stderr = stderr.trim();
+        if stderr.is_empty() {
+            anyhow::bail!("'{}' exited with {}", command, output.status);
+        }
+        anyhow::bail!("'{}' exited with {}: {}", command, output.status, stderr);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::write_script;
+    use std::fs;
+    use std::path::Path;
+    use std::time::Instant;
+    use tempfile::TempDir;
+
+    fn command(path: &Path) -> String {
+        format!("'{}'", path.display())
+    }
+
+    fn test_host() -> HostEntry {
+        let mut host = HostEntry::new("db".to_string(), "ssh db".to_string());
+        host.user = Some("postgres".to_string());
+        host.port = Some(2222);
+        host
+    }
+
+    #[test]
+    fn test_hook_receives_host_variables() {
+        let temp_dir = TempDir::new().unwrap();
+        let output = temp_dir.path().join("env.txt");
+        let hook = write_script(
+            temp_dir.path(),
+            "hook.sh",
+            &format!(
+                "echo \"$TRIDENT_HOST $TRIDENT_USER $TRIDENT_PORT\" > '{}'",
+                output.display()
+            ),
+        );
+
+        run_hook(&command(&hook), &test_host(), Duration::from_secs(10)).unwrap();
+
+        assert_eq!(fs::read_to_string(output).unwrap(), "db postgres 2222\n");
+    }
+
+    #[test]
+    fn test_hook_defaults_port() {
+        let temp_dir = TempDir::new().unwrap();
+        let output = temp_dir.path().join("port.txt");
+        let hook = write_script(
+            temp_dir.path(),
+            "hook.sh",
+            &format!("echo \"$TRIDENT_PORT\" > '{}'", output.display()),
+        );
+        let host = HostEntry::new("web".to_string(), "ssh web".to_string());
+
+        run_hook(&command(&hook), &host, Duration::from_secs(10)).unwrap();
+
+        assert_eq!(fs::read_to_string(output).unwrap(), "22\n");
+    }
+
+    #[test]
+    fn test_failing_hook_reports_exit_and_stderr() {
+        let temp_dir = TempDir::new().unwrap();
+        let hook = write_script(
+            temp_dir.path(),
+            "hook.sh",
+            "echo 'no identities' >&2\nexit 3",
+        );
+
+        let error = run_hook(&command(&hook), &test_host(), Duration::from_secs(10))
+            .unwrap_err()
+            .to_string();
+
+        assert!(error.contains("exit status: 3"), "{}", error);
+        assert!(error.contains("no identities"), "{}", error);
+    }
+
+    #[test]
+    fn test_slow_hook_times_out() {
+        let temp_dir = TempDir::new().unwrap();
+        let hook = write_script(temp_dir.path(), "hook.sh", "exec sleep 5");
+
+        let started = Instant::now();
+        let error = run_hook(&command(&hook), &test_host(), Duration::from_millis(200))
+            .unwrap_err()
+            .to_string();
+
+        assert!(error.contains("timed out"), "{}", error);
+        assert!(started.elapsed() < Duration::from_secs(4));
+    }
+
+    #[test]
+    fn test_backgrounded_hook_does_not_hang() {
+        let temp_dir = TempDir::new().unwrap();
+        let hook = write_script(temp_dir.path(), "hook.sh", "sleep 10 &");
+
+        let started = Instant::now();
+        run_hook(&command(&hook), &test_host(), Duration::from_secs(30)).unwrap();
+
+        assert!(started.elapsed() < Duration::from_secs(4));
+    }
+
+    #[test]
+    fn test_hooks_for_several_hosts_run_side_by_side() {
+        let temp_dir = TempDir::new().unwrap();
+        let hook = write_script(
+            temp_dir.path(),
+            "hook.sh",
+            "sleep 1; [ \"$TRIDENT_HOST\" != bad ]",
+        );
+        let mut config = Config::default();
+        config.hooks.pre_launch = Some(command(&hook));
+        let hosts: Vec<HostEntry> = ["a", "bad", "c"]
+            .iter()
+            .map(|name| HostEntry::new(name.to_string(), format!("ssh {}", name)))
+            .collect();
+
+        let started = Instant::now();
+        let results = run_hooks_for_all(&config, HookStage::PreLaunch, &hosts);
+
+        assert!(started.elapsed() < Duration::from_secs(3));
+        assert!(results[0].is_ok());
+        assert!(results[1].is_err());
+        assert!(results[2].is_ok());
+    }
+
+    #[test]
+    fn test_run_hooks_runs_global_then_tag_hooks() {
+        let temp_dir = TempDir::new().unwrap();
+        let log = temp_dir.path().join("log.txt");
+        let global = write_script(
+            temp_dir.path(),
+            "global.sh",
+            &format!("echo global >> '{}'", log.display()),
+        );
+        let tagged = write_script(
+            temp_dir.path(),
+            "tagged.sh",
+            &format!("echo tagged >> '{}'", log.display()),
+        );
+
+        let mut config = Config::default();
+        config.hooks.pre_launch = Some(command(&global));
+        config.tags.insert(
+            "teleport".to_string(),
+            crate::config::TagSettings {
+                hooks: Some(crate::config::HooksConfig {
+                    pre_launch: Some(command(&tagged)),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+        );
+        config.hosts.insert(
+            "db".to_string(),
+            crate::config::HostSettings {
+                tags: vec!["teleport".to_string()],
+                ..Default::default()
+            },
+        );
+
+        run_hooks(&config, HookStage::PreLaunch, &test_host()).unwrap();
+        run_hooks(&config, HookStage::PostLaunch, &test_host()).unwrap();
+
+        assert_eq!(fs::read_to_string(log).unwrap(), "global\ntagged\n");
+    }
+}
@@ -0,0 +1,419 @@
+// ABOUTME: Host key fingerprints from known_hosts and live verification with ssh-keyscan
+// ABOUTME: Computes OpenSSH-style SHA256 fingerprints and flags servers whose key has changed
+
+use crate::config::HostKeysConfig;
+use crate::ssh::parser::HostEntry;
+use crate::ssh::process::run_with_timeout;
+use crate::ssh::transport::DEFAULT_SSH_PORT;
+use anyhow::{Context, Result};
+use base64::Engine;
+use base64::engine::general_purpose::{STANDARD, STANDARD_NO_PAD};
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::process::Command;
+use std::time::Duration;
+
+/// Time allowed on top of `ssh-keyscan -T`, which doesn't cover resolving the host name
+const KEYSCAN_GRACE: Duration = Duration::from_secs(2);
+
+/// A public host key as written in known_hosts: `ssh-ed25519 AAAAC3...`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HostKey {
+    pub key_type: String,
+    /// Base64 key blob
+    pub key: String,
+}
+
+impl HostKey {
+    /// Parse the key type and blob from a known_hosts or ssh-keyscan line, after the host field
+    pub fn parse(key_type: &str, key: &str) -> Option<Self> {
+        STANDARD.decode(key).ok().filter(|blob| !blob.is_empty())?;
+        Some(Self {
+            key_type: key_type.to_string(),
+            key: key.to_string(),
+        })
+    }
+
+    /// `SHA256:...`, as printed by `ssh-keygen -l` and ssh's host key prompts
+    pub fn fingerprint(&self) -> String {
+        let blob = STANDARD.decode(&self.key).unwrap_or_default();
+        format!("SHA256:{}", STANDARD_NO_PAD.encode(Sha256::digest(&blob)))
+    }
+
+    /// Short key type name, e.g. `ED25519` for `ssh-ed25519`
+    pub fn type_label(&self) -> String {
+        let label = match self.key_type.as_str() {
+            "ssh-ed25519" => "ED25519",
+            "ssh-rsa" => "RSA",
+            "ssh-dss" => "DSA",
+            "sk-ssh-ed25519@openssh.com" => "ED25519-SK",
+            "sk-ecdsa-sha2-nistp256@openssh.com" => "ECDSA-SK",
+            other if other.starts_with("ecdsa-sha2-") => "ECDSA",
+            other => other,
+        };
+        label.to_string()
+    }
+}
+
+impl fmt::Display for HostKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.type_label(), self.fingerprint())
+    }
+}
+
+/// A key from known_hosts and the port its line names
+///
+/// `[host]:2222` lines hold the keys of the server on port 2222, which can differ from
+/// the host's port 22 keys; plain `host` lines are port 22.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KnownKey {
+    pub port: u16,
+    pub key: HostKey,
+}
+
+/// How the keys a server offers compare with the ones stored in known_hosts
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyCheck {
+    /// Every key type both sides have matches
+    Matches { keys: Vec<HostKey> },
+    /// The server offers a key type stored in known_hosts, but none of its keys of that
+    /// type are stored
+    Changed { stored: HostKey, scanned: HostKey },
+    /// known_hosts has no key for the host, so there is nothing to compare
+    NotStored { scanned: Vec<HostKey> },
+    /// The server offered none of the stored key types
+    NoCommonType,
+}
+
+impl KeyCheck {
+    pub fn compare(stored: &[HostKey], scanned: &[HostKey]) -> Self {
+        if stored.is_empty() {
+            return KeyCheck::NotStored {
+                scanned: scanned.to_vec(),
+            };
+        }
+
+        let mut matching = Vec::new();
+        // known_hosts may hold several keys of a type, e.g. during a key rotation, and
+        // any one of them matching is enough
+        let mut first_of_type: Vec<&HostKey> = Vec::new();
+        for key in stored {
+            if !first_of_type
+                .iter()
+                .any(|seen| seen.key_type == key.key_type)
+            {
+                first_of_type.push(key);
+            }
+        }
+        for stored_key in first_of_type {
+            let offered: Vec<&HostKey> = scanned
+                .iter()
+                .filter(|key| key.key_type == stored_key.key_type)
+                .collect();
+            let Some(first_offered) = offered.first() else {
+                continue;
+            };
+            match offered.iter().copied().find(|&key| stored.contains(key)) {
+                Some(key) => matching.push(key.clone()),
+                None => {
+                    return KeyCheck::Changed {
+                        stored: stored_key.clone(),
+                        scanned: (*first_offered).clone(),
+                    };
+                }
+            }
+        }
+
+        if matching.is_empty() {
+            KeyCheck::NoCommonType
+        } else {
+            KeyCheck::Matches { keys: matching }
+        }
+    }
+
+    pub fn is_changed(&self) -> bool {
+        matches!(self, KeyCheck::Changed { .. })
+    }
+}
+
+impl fmt::Display for KeyCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyCheck::Matches { keys } => {
+                let keys: Vec<String> = keys.iter().map(HostKey::to_string).collect();
+                write!(f, "Host key matches known_hosts ({})", keys.join(", "))
+            }
+            KeyCheck::Changed { stored, scanned } => write!(
+                f,
+                "HOST KEY CHANGED: known_hosts has {} but the server offers {}. \
+                Someone may be impersonating the host; don't connect until you know why.",
+                stored, scanned
+            ),
+            KeyCheck::NotStored { scanned } => {
+                let keys: Vec<String> = scanned.iter().map(HostKey::to_string).collect();
+                write!(
+                    f,
+                    "No key in known_hosts to compare with; the server offers {}",
+                    keys.join(", ")
+                )
+            }
+            KeyCheck::NoCommonType => {
+                write!(f, "The server offered none of the key types in known_hosts")
+            }
+        }
+    }
+}
+
+/// Keys stored for the port a host connects to: from its own known_hosts entry, or the
+/// entry for its `HostName`
+pub fn stored_keys(host: &HostEntry, hosts: &[HostEntry]) -> Vec<HostKey> {
+    let port = host.port.unwrap_or(DEFAULT_SSH_PORT);
+    let for_port = |entry: &HostEntry| -> Vec<HostKey> {
+        entry
+            .host_keys
+            .iter()
+            .filter(|known| known.port == port)
+            .map(|known| known.key.clone())
+            .collect()
+    };
+
+    let own = for_port(host);
+    if !own.is_empty() {
+        return own;
+    }
+    host.hostname
+        .as_deref()
+        .and_then(|hostname| hosts.iter().find(|other| other.name == hostname))
+        .map(for_port)
+        .unwrap_or_default()
+}
+
+pub fn keyscan_argv(config: &HostKeysConfig, host: &HostEntry) -> Vec<String> {
+    vec![
+        config.keyscan_binary.clone(),
+        "-T".to_string(),
+        config.timeout_secs.to_string(),
+        "-p".to_string(),
+        host.port.unwrap_or(DEFAULT_SSH_PORT).to_string(),
+        host.hostname.clone().unwrap_or_else(|| host.name.clone()),
+    ]
+}
+
+/// Ask the server for its host keys with `ssh-keyscan`
+pub fn scan(config: &HostKeysConfig, host: &HostEntry) -> Result<Vec<HostKey>> {
+    if let Some(proxy) = &host.proxy {
+        anyhow::bail!(
+            "{} is reached through a proxy ({}), which ssh-keyscan can't follow",
+            host.name,
+            proxy
+        );
+    }
+
+    let argv = keyscan_argv(config, host);
+    let timeout = Duration::from_secs(config.timeout_secs) + KEYSCAN_GRACE;
+    let output = run_with_timeout(Command::new(&argv[0]).args(&argv[1..]), timeout)
+        .with_context(|| format!("Failed to run '{}'", argv.join(" ")))?;
+
+    let keys = parse_keyscan_output(&String::from_utf8_lossy(&output.stdout));
+    if keys.is_empty() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let reason = stderr
+            .lines()
+            .filter(|line| !line.starts_with('#'))
+            .collect::<Vec<_>>()
+            .join(" ");
+        anyhow::bail!(
+            "ssh-keyscan got no host keys from {}: {}",
+            argv[argv.len() - 1],
+            if reason.trim().is_empty() {
+                "no answer"
+            } else {
+                reason.trim()
+            }
+        );
+    }
+    Ok(keys)
+}
+
+/// Parse `host type key` lines, skipping the `# host:port SSH-2.0-...` comments
+pub fn parse_keyscan_output(output: &str) -> Vec<HostKey> {
+    output
+        .lines()
+        .filter(|line| !line.trim_start().starts_with('#'))
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace().skip(1);
+            HostKey::parse(fields.next()?, fields.next()?)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ED25519: &str =
+        "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIPQVbVw/rj+RTMBVLm7CactYAToJ7Wo1rv11TcTKzixY";
+    const ECDSA: &str = "ecdsa-sha2-nistp256 AAAAE2VjZHNhLXNoYTItbmlzdHAyNTYAAAAIbmlzdHAyNTYAAABBBBWIEqPqCXAOGWLcjjL6PHsLEr6YRSquRgB10SGC7cZbyvqdU19zD2cBNU9nRenfFrLW4SZVe2JOPKsVzU8OVw8=";
+    const OTHER_ED25519: &str =
+        "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIHome000000000000000000000000000000000000000";
+
+    fn key(line: &str) -> HostKey {
+        let (key_type, blob) = line.split_once(' ').unwrap();
+        HostKey::parse(key_type, blob).unwrap()
+    }
+
+    fn known_key(port: u16, line: &str) -> KnownKey {
+        KnownKey {
+            port,
+            key: key(line),
+        }
+    }
+
+    #[test]
+    fn test_fingerprints_match_ssh_keygen() {
+        assert_eq!(
+            key(ED25519).fingerprint(),
+            "SHA256:vthKWWjqXX8vAhdvFYJAOWZR8GGQKPz6WIoeZeaNb+4"
+        );
+        assert_eq!(
+            key(ECDSA).to_string(),
+            "ECDSA SHA256:83xynZTdGivCQJSVnfXNbrcblgqoHUDDtarkRs+wQc8"
+        );
+        assert_eq!(HostKey::parse("ssh-ed25519", "not base64!"), None);
+    }
+
+    #[test]
+    fn test_compare_keys() {
+        let stored = vec![key(ED25519), key(ECDSA)];
+
+        assert_eq!(
+            KeyCheck::compare(&stored, &[key(ED25519)]),
+            KeyCheck::Matches {
+                keys: vec![key(ED25519)]
+            }
+        );
+
+        let changed = KeyCheck::compare(&stored, &[key(ECDSA), key(OTHER_ED25519)]);
+        assert!(changed.is_changed());
+        assert!(changed.to_string().starts_with("HOST KEY CHANGED"));
+
+        assert_eq!(
+            KeyCheck::compare(&[key(ECDSA)], &[key(ED25519)]),
+            KeyCheck::NoCommonType
+        );
+        assert!(matches!(
+            KeyCheck::compare(&[], &[key(ED25519)]),
+            KeyCheck::NotStored { .. }
+        ));
+
+        // Any stored key of a type matching is enough, e.g. while a key is rotated
+        assert_eq!(
+            KeyCheck::compare(&[key(OTHER_ED25519), key(ED25519)], &[key(ED25519)]),
+            KeyCheck::Matches {
+                keys: vec![key(ED25519)]
+            }
+        );
+    }
+
+    #[test]
+    fn test_keys_are_compared_for_the_scanned_port() {
+        // The same name runs different servers on ports 22 and 2222
+        let mut web = HostEntry::new("web.example.com".to_string(), String::new());
+        web.host_keys = vec![known_key(22, ED25519), known_key(2222, OTHER_ED25519)];
+        let mut alt = web.clone();
+        alt.port = Some(2222);
+        let hosts = vec![web.clone()];
+
+        assert_eq!(stored_keys(&web, &hosts), vec![key(ED25519)]);
+        assert_eq!(stored_keys(&alt, &hosts), vec![key(OTHER_ED25519)]);
+        assert!(!KeyCheck::compare(&stored_keys(&web, &hosts), &[key(ED25519)]).is_changed());
+        assert!(!KeyCheck::compare(&stored_keys(&alt, &hosts), &[key(OTHER_ED25519)]).is_changed());
+        assert!(KeyCheck::compare(&stored_keys(&alt, &hosts), &[key(ED25519)]).is_changed());
+
+        // A port without stored keys has nothing to compare
+        alt.port = Some(2200);
+        assert!(matches!(
+            KeyCheck::compare(&stored_keys(&alt, &hosts), &[key(ED25519)]),
+            KeyCheck::NotStored { .. }
+        ));
+    }
+
+    #[test]
+    fn test_stored_keys_fall_back_to_hostname() {
+        let mut known = HostEntry::new("web.example.com".to_string(), String::new());
+        known.host_keys = vec![known_key(22, ED25519)];
+        let mut alias = HostEntry::new("web".to_string(), "ssh web".to_string());
+        alias.hostname = Some("web.example.com".to_string());
+        let hosts = vec![known.clone(), alias.clone()];
+
+        assert_eq!(stored_keys(&alias, &hosts), vec![key(ED25519)]);
+        assert_eq!(stored_keys(&known, &hosts), vec![key(ED25519)]);
+
+        alias.hostname = Some("10.0.0.5".to_string());
+        assert!(stored_keys(&alias, &hosts).is_empty());
+    }
+
+    #[test]
+    fn test_parse_keyscan_output() {
+        let output = format!(
+            "# web.example.com:22 SSH-2.0-OpenSSH_9.6\nweb.example.com {}\n[web.example.com]:2222 {}\n",
+            ED25519, ECDSA
+        );
+        assert_eq!(
+            parse_keyscan_output(&output),
+            vec![key(ED25519), key(ECDSA)]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_scan_with_stub_keyscan() {
+        use crate::test_support::write_script;
+        use tempfile::TempDir;
+
+        let dir = TempDir::new().unwrap();
+        // Answers for port 22 only, like a server that isn't listening elsewhere
+        let stub = write_script(
+            dir.path(),
+            "ssh-keyscan",
+            &format!(
+                "[ \"$1 $3\" = \"-T -p\" ] || exit 3\nif [ \"$4\" = 22 ]; then echo \"# $5:22 SSH-2.0-OpenSSH_9.6\" >&2; echo \"$5 {}\"; else echo \"connect to $5 port $4: Connection refused\" >&2; fi",
+                ED25519
+            ),
+        );
+        let config = HostKeysConfig {
+            keyscan_binary: stub.to_string_lossy().to_string(),
+            timeout_secs: 2,
+        };
+
+        let mut host = HostEntry::new("web".to_string(), "ssh web".to_string());
+        host.hostname = Some("web.example.com".to_string());
+        assert_eq!(scan(&config, &host).unwrap(), vec![key(ED25519)]);
+
+        host.port = Some(2222);
+        let error = scan(&config, &host).unwrap_err().to_string();
+        assert_eq!(
+            error,
+            "ssh-keyscan got no host keys from web.example.com: connect to web.example.com port 2222: Connection refused"
+        );
+
+        // A server that never answers is given up on
+        let hung = write_script(dir.path(), "hung-keyscan", "sleep 30");
+        let config = HostKeysConfig {
+            keyscan_binary: hung.to_string_lossy().to_string(),
+            timeout_secs: 0,
+        };
+        let started = std::time::Instant::now();
+        let error = format!("{:#}", scan(&config, &host).unwrap_err());
+        assert!(error.contains("timed out"), "{}", error);
+        assert!(started.elapsed() < Duration::from_secs(10));
+
+        host.proxy = Some("bastion".to_string());
+        assert!(
+            scan(&config, &host)
+                .unwrap_err()
+                .to_string()
+                .contains("through a proxy (bastion)")
+        );
+    }
+}
@@ -2,196 +2,1450 @@
 // ABOUTME: Provides safe command substitution and process spawning for various terminal applications
 
 use crate::Logger;
-use crate::config::TerminalConfig;
+use crate::config::{Config, Transport, check_forward_conflicts, find_executable};
+use crate::ssh::agent::add_key_argv;
+use crate::ssh::broadcast::tiled_tmux_argv;
+use crate::ssh::dry_run::DryRun;
+use crate::ssh::environment::terminal_env;
+use crate::ssh::files::{check_sftp, sftp_argv};
+use crate::ssh::forward::{check_ports_available, with_forwards};
+use crate::ssh::hooks::{HookStage, run_hooks, run_hooks_for_all};
 use crate::ssh::parser::HostEntry;
+use crate::ssh::session::{session_argv, user_command_argv, with_remote_command};
+use crate::ssh::target::{LaunchTarget, TerminalKind, target_template};
+use crate::ssh::template::{Placeholders, expand_arg};
+use crate::ssh::transport::connect_argv;
+use crate::tunnel::TunnelSpec;
 use anyhow::{Context, Result};
-use std::process::Command;
+use std::fs::{self, File, OpenOptions};
+use std::io::{ErrorKind, Read, Seek, SeekFrom};
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+/// How long a freshly spawned terminal is watched for an immediate failure
+const STARTUP_WAIT: Duration = Duration::from_millis(500);
+
+/// The exact process a launch spawns
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LaunchCommand {
+    /// Terminal profile the command came from, if not the plain `[terminal]` settings
+    pub profile: Option<String>,
+    pub program: String,
+    pub args: Vec<String>,
+    /// Where the connection opens; a new window when the terminal can't do the requested target
+    pub target: LaunchTarget,
+    pub transport: Transport,
+    /// The connection command substituted into the terminal args
+    pub ssh_argv: Vec<String>,
+    /// Variables set on top of the inherited environment
+    pub env: Vec<(String, String)>,
+    /// Where the terminal starts; the launcher's own directory when unset
+    pub working_dir: Option<PathBuf>,
+}
+
+/// Per-launch choices that override configuration
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LaunchOptions {
+    /// Connect with this transport instead of the host's configured one
+    pub transport: Option<Transport>,
+    /// Open in this terminal profile instead of the host's configured one
+    pub profile: Option<String>,
+    pub target: LaunchTarget,
+    /// Names of `[forwards]` presets to open with the connection
+    pub forwards: Vec<String>,
+    /// Open sftp on the host instead of a shell
+    pub sftp: bool,
+    /// Run this command on the host instead of a login shell or session
+    pub remote_command: Option<String>,
+    /// Load this key into the ssh agent with `ssh-add` instead of connecting
+    pub add_key: Option<PathBuf>,
+}
+
+/// Result of a successful launch
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LaunchOutcome {
+    pub host: String,
+    pub command: LaunchCommand,
+    /// Process id of the spawned terminal program
+    pub pid: u32,
+}
 
 pub struct TerminalLauncher {
-    config: TerminalConfig,
+    config: Config,
 }
 
 impl TerminalLauncher {
-    pub fn new(config: TerminalConfig) -> Self {
-        Self { config }
+    pub fn new(config: &Config) -> Self {
+        Self {
+            config: config.clone(),
+        }
     }
 
-    pub fn launch(&self, host: &HostEntry) -> Result<()> {
-        Logger::debug(&format!("Launching SSH connection to host: {}", host.name));
+    /// Build the terminal command for a host without spawning it
+    pub fn command_for(&self, host: &HostEntry, options: &LaunchOptions) -> Result<LaunchCommand> {
+        let (profile, terminal) = self
+            .config
+            .terminal_profile_for(&host.name, options.profile.as_deref())?;
+        let (transport, ssh_argv) = if let Some(key) = &options.add_key {
+            // ssh-add runs locally, so there is no connection to forward over
+            if !options.forwards.is_empty() {
+                anyhow::bail!("Port forwards can't be opened while adding a key");
+            }
+            (Transport::Ssh, add_key_argv(&self.config.agent, key))
+        } else if options.sftp {
+            // sftp always runs over ssh and has no shell for forwards or a session
+            if !options.forwards.is_empty() {
+                anyhow::bail!("Port forwards can't be opened with sftp");
+            }
+            (Transport::Ssh, sftp_argv(&self.config, host))
+        } else {
+            let transport = options
+                .transport
+                .unwrap_or_else(|| self.config.transport_for(&host.name));
+            (transport, self.shell_argv(transport, host, options)?)
+        };
+
+        let mut program = terminal.program.clone();
+        let placeholders = self.placeholders_for(host, options, &ssh_argv);
+        let mut args = build_terminal_args(&terminal.program, &terminal.args, &placeholders)?;
+        let mut target = options.target;
+
+        if target != LaunchTarget::Window {
+            // The profile's own tab/split args win over the terminal's built-in mechanism
+            let configured = match target {
+                LaunchTarget::Tab => terminal.tab_args.clone(),
+                LaunchTarget::Split => terminal.split_args.clone(),
+                LaunchTarget::Window => None,
+            };
+            let template = configured
+                .map(|args| (terminal.program.clone(), args))
+                .or_else(|| {
+                    TerminalKind::detect(&terminal.program, &terminal.args)
+                        .and_then(|kind| target_template(kind, target, &terminal.program))
+                });
+            match template {
+                Some((target_program, template)) => {
+                    args = build_terminal_args(&target_program, &template, &placeholders)?;
+                    program = target_program;
+                }
+                None => {
+                    Logger::warn(&format!(
+                        "Terminal '{}' can't open a new {}; opening a new window instead",
+                        terminal.program, target
+                    ));
+                    target = LaunchTarget::Window;
+                }
+            }
+        }
+
+        Ok(LaunchCommand {
+            profile,
+            program,
+            args,
+            target,
+            transport,
+            ssh_argv,
+            env: terminal_env(&self.config.terminal),
+            working_dir: self.working_dir(),
+        })
+    }
+
+    fn working_dir(&self) -> Option<PathBuf> {
+        self.config
+            .terminal
+            .working_directory
+            .as_ref()
+            .map(PathBuf::from)
+    }
+
+    /// Values for the placeholders in a host's terminal args
+    fn placeholders_for(
+        &self,
+        host: &HostEntry,
+        options: &LaunchOptions,
+        ssh_argv: &[String],
+    ) -> Placeholders {
+        let title = if options.add_key.is_some() {
+            format!("{} (ssh-add)", host.name)
+        } else if options.sftp {
+            format!("{} (sftp)", host.name)
+        } else if let Some(command) = &options.remote_command {
+            format!("{}: {}", host.name, command)
+        } else {
+            host.name.clone()
+        };
+        let tag = self
+            .config
+            .hosts
+            .get(&host.name)
+            .and_then(|settings| settings.tags.first())
+            .cloned();
+
+        Placeholders {
+            host: host.hostname.clone().unwrap_or_else(|| host.name.clone()),
+            alias: host.name.clone(),
+            user: host.user.clone(),
+            port: host.port,
+            title,
+            tag,
+            ssh_argv: ssh_argv.to_vec(),
+            ..self.terminal_placeholders()
+        }
+    }
+
+    /// Environment and directory placeholders shared by every launch
+    fn terminal_placeholders(&self) -> Placeholders {
+        Placeholders {
+            env: self.config.terminal.env.clone().into_iter().collect(),
+            working_dir: self.config.terminal.working_directory.clone(),
+            ..Default::default()
+        }
+    }
+
+    /// Describe what launching a host would execute, without running hooks or spawning
+    pub fn dry_run(&self, host: &HostEntry, options: &LaunchOptions) -> Result<DryRun> {
+        let command = self.command_for(host, options)?;
+        // Without a working_directory, terminals start in ours
+        let working_dir = match &command.working_dir {
+            Some(dir) => dir.clone(),
+            None => std::env::current_dir().context("Failed to determine the working directory")?,
+        };
+        Ok(DryRun::new(&host.name, &command, working_dir))
+    }
+
+    /// The connection argv for a shell, with any forwards and the configured session
+    /// or the requested remote command
+    fn shell_argv(
+        &self,
+        transport: Transport,
+        host: &HostEntry,
+        options: &LaunchOptions,
+    ) -> Result<Vec<String>> {
+        let mut ssh_argv = connect_argv(&self.config, transport, host)?;
+
+        let forwards = self.config.forward_presets(&options.forwards)?;
+        let forward_only = forwards.iter().any(|(_, preset)| preset.forward_only);
+        if !forwards.is_empty() {
+            check_forward_conflicts(&forwards)?;
+            ssh_argv = with_forwards(transport, ssh_argv, &forwards)?;
+        }
+
+        if let Some(command) = &options.remote_command {
+            if forward_only {
+                anyhow::bail!("A forward-only connection can't run a remote command");
+            }
+            let Some(wrapped) =
+                with_remote_command(transport, ssh_argv, &user_command_argv(command))
+            else {
+                anyhow::bail!(
+                    "Remote commands are not supported with the {} transport",
+                    transport.name()
+                );
+            };
+            return Ok(wrapped);
+        }
+
+        // A forward-only connection runs no remote command, so there is no session to attach
+        if let Some(remote) = self
+            .config
+            .session_for(&host.name)
+            .filter(|_| !forward_only)
+            .and_then(|session| session_argv(session, host))
+        {
+            match with_remote_command(transport, ssh_argv.clone(), &remote) {
+                Some(wrapped) => ssh_argv = wrapped,
+                None => Logger::warn(&format!(
+                    "Sessions are not supported with the {} transport; opening a plain shell on {}",
+                    transport.name(),
+                    host.name
+                )),
+            }
+        }
+        Ok(ssh_argv)
+    }
 
-        // Escape the SSH command for safe shell execution
-        let escaped_command = escape_shell_command(&host.connection_string);
-        Logger::debug(&format!("Escaped SSH command: {}", escaped_command));
+    /// Build a background tunnel holding the given forwards open, with no terminal or shell
+    ///
+    /// The tunnel always runs `-N`, exits when a forward can't bind so the supervisor
+    /// restarts it, and never prompts since there is no terminal to answer in.
+    pub fn tunnel_for(&self, host: &HostEntry, options: &LaunchOptions) -> Result<TunnelSpec> {
+        let forwards = self.config.forward_presets(&options.forwards)?;
+        if forwards.is_empty() {
+            anyhow::bail!("A tunnel needs at least one forward preset");
+        }
+        check_forward_conflicts(&forwards)?;
+
+        let transport = options
+            .transport
+            .unwrap_or_else(|| self.config.transport_for(&host.name));
+        let mut argv = with_forwards(
+            transport,
+            connect_argv(&self.config, transport, host)?,
+            &forwards,
+        )?;
+        if !argv.iter().any(|arg| arg == "-N") {
+            argv.insert(1, "-N".to_string());
+        }
+        let host_index = argv.len() - 1;
+        argv.splice(
+            host_index..host_index,
+            ["-o", "ExitOnForwardFailure=yes", "-o", "BatchMode=yes"].map(String::from),
+        );
+
+        Ok(TunnelSpec {
+            host: host.name.clone(),
+            forwards: options.forwards.clone(),
+            argv,
+        })
+    }
 
-        // Substitute {ssh_command} placeholder in terminal arguments
-        let args: Vec<String> = self
+    /// Build one terminal command showing every host in a tiled local tmux window
+    ///
+    /// The terminal profile comes from the first host, or from `options.profile`.
+    pub fn tiled_command_for(
+        &self,
+        hosts: &[HostEntry],
+        options: &LaunchOptions,
+    ) -> Result<LaunchCommand> {
+        let Some(first) = hosts.first() else {
+            anyhow::bail!("No hosts to launch");
+        };
+        let (profile, terminal) = self
             .config
-            .args
+            .terminal_profile_for(&first.name, options.profile.as_deref())?;
+
+        let mut connections = Vec::new();
+        for host in hosts {
+            let command = self.command_for(host, options)?;
+            check_transport(&command)?;
+            connections.push(command.ssh_argv);
+        }
+        let tmux = &self.config.broadcast.tmux_binary;
+        if find_executable(tmux).is_none() {
+            anyhow::bail!(
+                "The tiled layout uses '{}', which was not found. Install tmux or set tmux_binary in [broadcast].",
+                tmux
+            );
+        }
+        let tmux_argv = tiled_tmux_argv(tmux, &connections).context("No hosts to launch")?;
+        // The window shows every host, so only the title and connection describe it
+        let placeholders = Placeholders {
+            title: hosts
+                .iter()
+                .map(|host| host.name.as_str())
+                .collect::<Vec<_>>()
+                .join(", "),
+            ssh_argv: tmux_argv.clone(),
+            ..self.terminal_placeholders()
+        };
+
+        Ok(LaunchCommand {
+            profile,
+            args: build_terminal_args(&terminal.program, &terminal.args, &placeholders)?,
+            program: terminal.program,
+            target: LaunchTarget::Window,
+            transport: options
+                .transport
+                .unwrap_or_else(|| self.config.transport_for(&first.name)),
+            ssh_argv: tmux_argv,
+            env: terminal_env(&self.config.terminal),
+            working_dir: self.working_dir(),
+        })
+    }
+
+    pub fn launch(&self, host: &HostEntry, options: &LaunchOptions) -> Result<LaunchOutcome> {
+        let command = self.checked_command(host, options)?;
+        run_hooks(&self.config, HookStage::PreLaunch, host)?;
+        let outcome = StartedTerminal::spawn(&host.name, command)?.confirm()?;
+        self.run_post_launch_hooks(std::slice::from_ref(host));
+        Ok(outcome)
+    }
+
+    /// Launch each host in its own terminal, continuing past failures
+    ///
+    /// Hooks for different hosts run side by side, and every terminal is started before
+    /// any is checked for an early exit, so the startup wait is paid once, not per host.
+    pub fn launch_each(
+        &self,
+        hosts: &[HostEntry],
+        options: &LaunchOptions,
+    ) -> Vec<Result<LaunchOutcome>> {
+        let commands: Vec<Result<LaunchCommand>> = hosts
             .iter()
-            .map(|arg| arg.replace("{ssh_command}", &escaped_command))
+            .map(|host| self.checked_command(host, options))
             .collect();
+        let ready: Vec<HostEntry> = hosts
+            .iter()
+            .zip(&commands)
+            .filter(|(_, command)| command.is_ok())
+            .map(|(host, _)| host.clone())
+            .collect();
+        let mut hooks = run_hooks_for_all(&self.config, HookStage::PreLaunch, &ready).into_iter();
+
+        let started: Vec<Result<StartedTerminal>> = hosts
+            .iter()
+            .zip(commands)
+            .map(|(host, command)| {
+                let command = command?;
+                hooks.next().unwrap_or(Ok(()))?;
+                StartedTerminal::spawn(&host.name, command)
+            })
+            .collect();
+        let results: Vec<Result<LaunchOutcome>> = started
+            .into_iter()
+            .map(|terminal| terminal.and_then(StartedTerminal::confirm))
+            .collect();
+
+        let launched: Vec<HostEntry> = hosts
+            .iter()
+            .zip(&results)
+            .filter(|(_, result)| result.is_ok())
+            .map(|(host, _)| host.clone())
+            .collect();
+        self.run_post_launch_hooks(&launched);
+        results
+    }
 
+    /// The terminal command for a host, after checking what it needs is available
+    fn checked_command(&self, host: &HostEntry, options: &LaunchOptions) -> Result<LaunchCommand> {
+        Logger::debug(&format!("Launching SSH connection to host: {}", host.name));
+
+        let command = self.command_for(host, options)?;
+        check_transport(&command)?;
+        if options.sftp {
+            check_sftp(&self.config)?;
+        }
+        check_ports_available(&self.config.forward_presets(&options.forwards)?)?;
+        Ok(command)
+    }
+
+    /// Open a terminal running `ssh-add` for one of a host's keys
+    ///
+    /// Nothing connects to the host, so its launch hooks and port checks don't apply.
+    pub fn add_key(&self, host: &HostEntry, key: &Path) -> Result<LaunchOutcome> {
+        let options = LaunchOptions {
+            add_key: Some(key.to_path_buf()),
+            ..Default::default()
+        };
+        let command = self.command_for(host, &options)?;
+        StartedTerminal::spawn(&host.name, command)?.confirm()
+    }
+
+    /// Launch all hosts in one terminal, tiled in a local tmux window with synchronized input
+    pub fn launch_tiled(
+        &self,
+        hosts: &[HostEntry],
+        options: &LaunchOptions,
+    ) -> Result<LaunchOutcome> {
+        let names: Vec<&str> = hosts.iter().map(|host| host.name.as_str()).collect();
+        let label = names.join(", ");
+        Logger::debug(&format!("Launching tiled tmux window for hosts: {}", label));
+
+        let command = self.tiled_command_for(hosts, options)?;
+        for result in run_hooks_for_all(&self.config, HookStage::PreLaunch, hosts) {
+            result?;
+        }
+        let outcome = StartedTerminal::spawn(&label, command)?.confirm()?;
+        self.run_post_launch_hooks(hosts);
+        Ok(outcome)
+    }
+
+    /// The terminal is already open, so a failing post-launch hook is logged rather than returned
+    fn run_post_launch_hooks(&self, hosts: &[HostEntry]) {
+        for result in run_hooks_for_all(&self.config, HookStage::PostLaunch, hosts) {
+            if let Err(e) = result {
+                Logger::error(&format!("{:#}", e));
+            }
+        }
+    }
+}
+
+/// The ssh binary is checked with the config; other transports fail inside the
+/// terminal where the error is easy to miss, so check them before spawning
+fn check_transport(command: &LaunchCommand) -> Result<()> {
+    if command.transport != Transport::Ssh && find_executable(&command.ssh_argv[0]).is_none() {
+        anyhow::bail!(
+            "Transport '{}' uses '{}', which was not found. Install it or set its path in [transport].",
+            command.transport.name(),
+            command.ssh_argv[0]
+        );
+    }
+    Ok(())
+}
+
+/// A spawned terminal that hasn't yet been checked for an early exit
+struct StartedTerminal {
+    /// One host or a comma-separated group
+    host: String,
+    command: LaunchCommand,
+    child: Child,
+    stderr: File,
+    started_at: Instant,
+}
+
+impl StartedTerminal {
+    fn spawn(host: &str, command: LaunchCommand) -> Result<Self> {
+        Logger::debug(&format!("SSH argv: {:?}", command.ssh_argv));
         Logger::debug(&format!(
             "Launching terminal: {} with args: {:?}",
-            self.config.program, args
+            command.program, command.args
         ));
 
-        // Spawn the terminal process
-        match Command::new(&self.config.program).args(&args).spawn() {
-            Ok(_) => {
-                Logger::info(&format!(
-                    "Successfully launched terminal for host: {}",
-                    host.name
-                ));
-                Ok(())
-            }
+        let stderr = stderr_capture_file()?;
+        let mut process = Command::new(&command.program);
+        process
+            .args(&command.args)
+            .envs(command.env.iter().map(|(name, value)| (name, value)))
+            .stdin(Stdio::null())
+            .stderr(stderr.try_clone()?);
+        if let Some(dir) = &command.working_dir {
+            process.current_dir(dir);
+        }
+
+        match process.spawn() {
+            Ok(child) => Ok(Self {
+                host: host.to_string(),
+                command,
+                child,
+                stderr,
+                started_at: Instant::now(),
+            }),
             Err(e) => {
-                Logger::error(&format!(
-                    "Failed to launch terminal for host '{}': {}",
-                    host.name, e
-                ));
-                Logger::error(&format!("  Terminal program: {}", self.config.program));
-                Logger::error(&format!("  Terminal args: {:?}", args));
+                log_launch_failure(host, &command, &e.to_string());
                 Logger::error(
                     "  Check that the terminal program exists and the configuration is correct",
                 );
                 Err(e).with_context(|| {
                     format!(
                         "Failed to launch terminal: {} with args: {:?}",
-                        self.config.program, args
+                        command.program, command.args
                     )
                 })
             }
         }
     }
+
+    /// Wait out the rest of [`STARTUP_WAIT`] for the terminal to fail
+    ///
+    /// A terminal that exits non-zero in that time (bad args, an AppleScript error) fails
+    /// the launch with its exit status and stderr. Terminals that hand off to a running
+    /// instance and exit 0, or are still running, count as launched.
+    fn confirm(mut self) -> Result<LaunchOutcome> {
+        let deadline = self.started_at + STARTUP_WAIT;
+        match wait_for_early_exit(&mut self.child, deadline) {
+            Some(status) if !status.success() => {
+                let mut stderr = String::new();
+                let _ = self.stderr.seek(SeekFrom::Start(0));
+                let _ = self.stderr.read_to_string(&mut stderr);
+                let stderr = stderr.trim();
+                log_launch_failure(&self.host, &self.command, &status.to_string());
+                if stderr.is_empty() {
+                    anyhow::bail!("Terminal exited with {}", status);
+                }
+                Logger::error(&format!("  Terminal stderr: {}", stderr));
+                anyhow::bail!("Terminal exited with {}: {}", status, stderr)
+            }
+            _ => {
+                Logger::info(&format!(
+                    "Successfully launched terminal for host: {}",
+                    self.host
+                ));
+                Ok(LaunchOutcome {
+                    pid: self.child.id(),
+                    host: self.host,
+                    command: self.command,
+                })
+            }
+        }
+    }
+}
+
+fn log_launch_failure(host: &str, command: &LaunchCommand, reason: &str) {
+    Logger::error(&format!(
+        "Failed to launch terminal for host '{}': {}",
+        host, reason
+    ));
+    Logger::error(&format!("  Terminal program: {}", command.program));
+    Logger::error(&format!("  Terminal args: {:?}", command.args));
+    let argv: Vec<String> = std::iter::once(command.program.clone())
+        .chain(command.args.iter().cloned())
+        .collect();
+    Logger::error(&format!("  Full argv: {}", shell_join(&argv)));
 }
 
-fn escape_shell_command(command: &str) -> String {
-    // Escape special shell characters to prevent command injection
-    command
-        .replace("\\", "\\\\")
-        .replace("\"", "\\\"")
-        .replace("'", "\\'")
-        .replace(";", "\\;")
-        .replace("&", "\\&")
-        .replace("|", "\\|")
-        .replace("$", "\\$")
-        .replace("`", "\\`")
-        .replace("(", "\\(")
-        .replace(")", "\\)")
-        .replace("<", "\\<")
-        .replace(">", "\\>")
-        .replace("\n", "\\n")
-        .replace("\t", "\\t")
+/// The child's exit status if it exits before `deadline`, `None` if it is still running
+fn wait_for_early_exit(child: &mut Child, deadline: Instant) -> Option<ExitStatus> {
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) => return Some(status),
+            Ok(None) if Instant::now() < deadline => {
+                std::thread::sleep(Duration::from_millis(20));
+            }
+            _ => return None,
+        }
+    }
+}
+
+/// A private, already unlinked file for a terminal's stderr
+///
+/// stderr goes to a file rather than a pipe: once we stop reading, a pipe would break a
+/// long-running terminal's writes. The file is created fresh with mode 0600, so nothing
+/// planted at the name in a shared temp dir (such as a symlink) is followed or reused.
+fn stderr_capture_file() -> Result<File> {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    const ATTEMPTS: usize = 100;
+
+    for _ in 0..ATTEMPTS {
+        let path = std::env::temp_dir().join(format!(
+            "trident-terminal-{}-{}.stderr",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        ));
+        let mut options = OpenOptions::new();
+        options.read(true).write(true).create_new(true);
+        #[cfg(unix)]
+        options.mode(0o600);
+        match options.open(&path) {
+            Ok(file) => {
+                // The terminal keeps writing to the unlinked file without trouble
+                let _ = fs::remove_file(&path);
+                return Ok(file);
+            }
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to create {}", path.display()));
+            }
+        }
+    }
+    anyhow::bail!(
+        "Failed to create a file for terminal stderr in {}",
+        std::env::temp_dir().display()
+    )
+}
+
+/// How placeholders are quoted for the argument they are substituted into
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuoteContext {
+    /// A plain argv element, passed to the terminal without a shell
+    Argument,
+    /// A command line run by a POSIX shell, e.g. the argument after `sh -c`
+    Shell,
+    /// Inside a double-quoted AppleScript string, e.g. `do script "..."` via osascript
+    AppleScript,
+}
+
+impl QuoteContext {
+    /// Pick the quoting context for a terminal argument template
+    pub fn for_arg(program: &str, arg: &str) -> Self {
+        let is_osascript = Path::new(program)
+            .file_name()
+            .is_some_and(|name| name == "osascript");
+        if is_osascript || arg.contains("tell app") {
+            QuoteContext::AppleScript
+        } else if arg.contains("{ssh_command}") {
+            QuoteContext::Shell
+        } else {
+            QuoteContext::Argument
+        }
+    }
+
+    /// Escape a single placeholder value, such as a title, for this context
+    pub fn escape(self, value: &str) -> String {
+        match self {
+            QuoteContext::Argument => value.to_string(),
+            QuoteContext::Shell => shell_quote(value),
+            QuoteContext::AppleScript => applescript_escape(&shell_quote(value)),
+        }
+    }
+
+    /// Render an argv as a single string that this context turns back into the same argv
+    pub fn render(self, argv: &[String]) -> String {
+        let command = shell_join(argv);
+        match self {
+            QuoteContext::Argument | QuoteContext::Shell => command,
+            // AppleScript hands the string to a shell, so quote for the shell first
+            QuoteContext::AppleScript => applescript_escape(&command),
+        }
+    }
+}
+
+/// Expand terminal argument templates for one launch
+///
+/// An argument that is exactly `{ssh_argv}` is replaced by the argv elements themselves,
+/// with no quoting. `{ssh_command}` is replaced by the argv rendered as one string,
+/// quoted for the context the argument is used in. See `template` for the other placeholders.
+pub fn build_terminal_args(
+    program: &str,
+    template: &[String],
+    placeholders: &Placeholders,
+) -> Result<Vec<String>> {
+    let mut args = Vec::with_capacity(template.len() + placeholders.ssh_argv.len());
+    for (index, arg) in template.iter().enumerate() {
+        let expanded = expand_arg(program, arg, placeholders)
+            .map_err(|e| anyhow::anyhow!("Terminal argument {} ({:?}): {}", index + 1, arg, e))?;
+        args.extend(expanded);
+    }
+    Ok(args)
+}
+
+/// Quote a single word so a POSIX shell reads it back unchanged
+pub fn shell_quote(word: &str) -> String {
+    let is_safe = |c: char| c.is_ascii_alphanumeric() || "@%_+=:,./-".contains(c);
+    if !word.is_empty() && word.chars().all(is_safe) {
+        return word.to_string();
+    }
+    format!("'{}'", word.replace('\'', "'\\''"))
+}
+
+/// Join an argv into a POSIX shell command line
+pub fn shell_join(argv: &[String]) -> String {
+    argv.iter()
+        .map(|word| shell_quote(word))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Escape text for use inside a double-quoted AppleScript string literal
+pub fn applescript_escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Split a command line into words the way a POSIX shell would, without expansions
+///
+/// Handles single quotes, double quotes and backslash escapes, so a connection string
+/// like `ssh host -t 'sudo -i'` yields `["ssh", "host", "-t", "sudo -i"]`.
+pub fn split_command_line(command: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut in_word = false;
+    let mut chars = command.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                in_word = true;
+                for c in chars.by_ref() {
+                    if c == '\'' {
+                        break;
+                    }
+                    current.push(c);
+                }
+            }
+            '"' => {
+                in_word = true;
+                while let Some(c) = chars.next() {
+                    match c {
+                        '"' => break,
+                        '\\' => match chars.next() {
+                            Some(next @ ('"' | '\\' | '$' | '`')) => current.push(next),
+                            Some('\n') => {}
+                            Some(next) => {
+                                current.push('\\');
+                                current.push(next);
+                            }
+                            None => current.push('\\'),
+                        },
+                        _ => current.push(c),
+                    }
+                }
+            }
+            '\\' => {
+                in_word = true;
+                match chars.next() {
+                    Some('\n') => {}
+                    Some(next) => current.push(next),
+                    None => current.push('\\'),
+                }
+            }
+            c if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut current));
+                    in_word = false;
+                }
+            }
+            _ => {
+                in_word = true;
+                current.push(c);
+            }
+        }
+    }
+
+    if in_word {
+        words.push(current);
+    }
+    words
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[cfg(unix)]
+    #[test]
+    fn test_stderr_capture_file_is_private() {
+        use std::io::Write;
+        use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+        let mut first = stderr_capture_file().unwrap();
+        let second = stderr_capture_file().unwrap();
+
+        assert_eq!(
+            first.metadata().unwrap().permissions().mode() & 0o777,
+            0o600
+        );
+        // Unlinked on creation, so each launch has its own file and nothing is left behind
+        assert_eq!(first.metadata().unwrap().nlink(), 0);
+        assert_eq!(second.metadata().unwrap().nlink(), 0);
+        first.write_all(b"boom").unwrap();
+        assert_eq!(second.metadata().unwrap().len(), 0);
+    }
+
+    /// Expand terminal args with only the connection placeholders filled in
+    fn terminal_args(program: &str, template: &[String], ssh_argv: &[String]) -> Vec<String> {
+        let placeholders = Placeholders {
+            ssh_argv: ssh_argv.to_vec(),
+            ..Default::default()
+        };
+        build_terminal_args(program, template, &placeholders).unwrap()
+    }
+
+    fn argv(words: &[&str]) -> Vec<String> {
+        words.iter().map(|word| word.to_string()).collect()
+    }
+
+    /// Argvs that the old backslash escaping mangled or that are easy to get wrong
+    fn tricky_argvs() -> Vec<Vec<String>> {
+        vec![
+            argv(&["ssh", "user@server.com"]),
+            argv(&["ssh", "server", "-t", "sudo -i"]),
+            argv(&["ssh", "server", "-t", "echo 'hacked'; rm -rf /"]),
+            argv(&["ssh", "server", "echo $HOME && `whoami` | cat > /tmp/x"]),
+            argv(&["ssh", "server", "-t", "say \"hi\" (twice)"]),
+            argv(&["ssh", "server", "back\\slash", "tab\there", "new\nline"]),
+            argv(&["ssh", "-o", "ProxyCommand=ssh -W %h:%p jump", "server"]),
+            argv(&["ssh", "", "it's"]),
+        ]
+    }
+
+    /// Reverse `applescript_escape` for the contents of a string literal
+    fn applescript_unescape(text: &str) -> String {
+        let mut result = String::new();
+        let mut chars = text.chars();
+        while let Some(c) = chars.next() {
+            if c == '\\' {
+                result.extend(chars.next());
+            } else {
+                result.push(c);
+            }
+        }
+        result
+    }
+
     #[test]
-    fn test_escape_shell_command_basic() {
-        let command = "ssh user@server.com";
-        let escaped = escape_shell_command(command);
-        assert_eq!(escaped, "ssh user@server.com");
+    fn test_shell_quote() {
+        assert_eq!(shell_quote("user@server.com"), "user@server.com");
+        assert_eq!(shell_quote("sudo -i"), "'sudo -i'");
+        assert_eq!(shell_quote("it's"), "'it'\\''s'");
+        assert_eq!(shell_quote(""), "''");
     }
 
     #[test]
-    fn test_escape_shell_command_with_special_chars() {
-        let command = "ssh user@server.com; echo 'hacked'";
-        let escaped = escape_shell_command(command);
-        assert_eq!(escaped, "ssh user@server.com\\; echo \\'hacked\\'");
+    fn test_split_command_line() {
+        assert_eq!(
+            split_command_line("ssh server -t 'sudo -i'"),
+            argv(&["ssh", "server", "-t", "sudo -i"])
+        );
+        assert_eq!(
+            split_command_line("ssh  \"a \\\"b\\\"\" c\\ d"),
+            argv(&["ssh", "a \"b\"", "c d"])
+        );
+        assert_eq!(split_command_line("ssh ''"), argv(&["ssh", ""]));
     }
 
     #[test]
-    fn test_escape_shell_command_with_quotes() {
-        let command = "ssh user@server.com -t \"sudo su\"";
-        let escaped = escape_shell_command(command);
-        assert_eq!(escaped, "ssh user@server.com -t \\\"sudo su\\\"");
+    fn test_ssh_argv_passes_arguments_directly() {
+        let template = argv(&["-e", "{ssh_argv}"]);
+        let ssh_argv = argv(&["ssh", "server", "-t", "sudo -i"]);
+
+        let args = terminal_args("/usr/bin/kitty", &template, &ssh_argv);
+
+        assert_eq!(args, argv(&["-e", "ssh", "server", "-t", "sudo -i"]));
     }
 
     #[test]
-    fn test_escape_shell_command_with_dollar_and_backticks() {
-        let command = "ssh user@server.com -t 'echo $HOME && `whoami`'";
-        let escaped = escape_shell_command(command);
+    fn test_shell_context_round_trip() {
+        let template = argv(&["-e", "sh", "-c", "{ssh_command}"]);
+
+        for ssh_argv in tricky_argvs() {
+            let args = terminal_args("/usr/bin/alacritty", &template, &ssh_argv);
+            assert_eq!(args.len(), 4);
+            assert_eq!(split_command_line(&args[3]), ssh_argv, "{}", args[3]);
+        }
+    }
+
+    #[test]
+    fn test_applescript_context_round_trip() {
+        let template = argv(&["-e", "tell app \"Terminal\" to do script \"{ssh_command}\""]);
+
+        for ssh_argv in tricky_argvs() {
+            let args = terminal_args("/usr/bin/osascript", &template, &ssh_argv);
+            let literal = args[1]
+                .strip_prefix("tell app \"Terminal\" to do script \"")
+                .and_then(|rest| rest.strip_suffix('"'))
+                .unwrap();
+            // An unescaped quote would end the AppleScript string early
+            assert!(
+                !literal
+                    .replace("\\\\", "")
+                    .replace("\\\"", "")
+                    .contains('"')
+            );
+
+            let command = applescript_unescape(literal);
+            assert_eq!(split_command_line(&command), ssh_argv, "{}", args[1]);
+        }
+    }
+
+    #[test]
+    fn test_quote_context_detection() {
+        assert_eq!(
+            QuoteContext::for_arg("/usr/bin/osascript", "{ssh_command}"),
+            QuoteContext::AppleScript
+        );
+        assert_eq!(
+            QuoteContext::for_arg(
+                "/Applications/iTerm.app/Contents/MacOS/iTerm2",
+                "tell application \"iTerm2\" to create window with default profile command \"{ssh_command}\""
+            ),
+            QuoteContext::AppleScript
+        );
         assert_eq!(
-            escaped,
-            "ssh user@server.com -t \\'echo \\$HOME \\&\\& \\`whoami\\`\\'"
+            QuoteContext::for_arg("/usr/bin/alacritty", "{ssh_command}"),
+            QuoteContext::Shell
+        );
+    }
+
+    #[test]
+    fn test_launcher_handles_multiple_placeholders() {
+        let template = argv(&[
+            "--title",
+            "SSH: {ssh_command}",
+            "--execute",
+            "{ssh_command}",
+        ]);
+        let host = HostEntry::new("server".to_string(), "ssh user@server".to_string());
+
+        let args = terminal_args(
+            "/usr/bin/terminal",
+            &template,
+            &split_command_line(&host.connection_string),
         );
+
+        assert_eq!(args[0], "--title");
+        assert_eq!(args[1], "SSH: ssh user@server");
+        assert_eq!(args[2], "--execute");
+        assert_eq!(args[3], "ssh user@server");
     }
 
     #[test]
-    fn test_launcher_substitutes_ssh_command() {
-        let config = TerminalConfig {
-            program: "/usr/bin/osascript".to_string(),
-            args: vec![
-                "-e".to_string(),
-                "tell app \"Terminal\" to do script \"{ssh_command}\"".to_string(),
-            ],
+    fn test_command_uses_configured_ssh_binary() {
+        let mut config = Config::default();
+        config.terminal.program = "/usr/bin/kitty".to_string();
+        config.terminal.args = argv(&["{ssh_argv}"]);
+        config.ssh.ssh_binary = "/opt/homebrew/bin/ssh".to_string();
+        let host = HostEntry::new("prod".to_string(), "ssh prod".to_string());
+
+        let command = TerminalLauncher::new(&config)
+            .command_for(&host, &LaunchOptions::default())
+            .unwrap();
+
+        assert_eq!(command.program, "/usr/bin/kitty");
+        assert_eq!(command.ssh_argv, argv(&["/opt/homebrew/bin/ssh", "prod"]));
+        assert_eq!(command.args, command.ssh_argv);
+    }
+
+    #[test]
+    fn test_host_placeholders_in_terminal_args() {
+        let mut config = Config::default();
+        config.terminal.program = "/usr/bin/kitty".to_string();
+        config.terminal.args = argv(&[
+            "--title",
+            "{title}",
+            "{?tag}--override=tab_color={tag}{/tag}",
+            "{?user}{user}@{/user}{host}{?port}:{port}{/port}",
+            "{ssh_argv}",
+        ]);
+        config.hosts.insert(
+            "db".to_string(),
+            crate::config::HostSettings {
+                tags: vec!["prod".to_string(), "postgres".to_string()],
+                ..Default::default()
+            },
+        );
+        let mut host = HostEntry::new("db".to_string(), "ssh db".to_string());
+        host.hostname = Some("10.2.0.15".to_string());
+        host.user = Some("postgres".to_string());
+        let launcher = TerminalLauncher::new(&config);
+
+        let options = LaunchOptions {
+            remote_command: Some("psql".to_string()),
+            ..Default::default()
         };
+        let command = launcher.command_for(&host, &options).unwrap();
+        assert_eq!(
+            command.args[..4],
+            [
+                "--title",
+                "db: psql",
+                "--override=tab_color=prod",
+                "postgres@10.2.0.15"
+            ]
+        );
 
-        let _launcher = TerminalLauncher::new(config.clone());
-        let host = HostEntry::new(
-            "test-server".to_string(),
-            "ssh user@test-server.com".to_string(),
+        // Without tags, user or hostname the sections drop out
+        let plain = HostEntry::new("web".to_string(), "ssh web".to_string());
+        let command = launcher
+            .command_for(&plain, &LaunchOptions::default())
+            .unwrap();
+        assert_eq!(
+            command.args,
+            ["--title", "web", "web", "/usr/bin/ssh", "web"]
         );
 
-        // We can't easily test the actual launch without mocking, but we can test the escaping
-        let escaped = escape_shell_command(&host.connection_string);
-        assert_eq!(escaped, "ssh user@test-server.com");
+        config.terminal.args = argv(&["--title={?user}{user}", "{ssh_argv}"]);
+        let error = TerminalLauncher::new(&config)
+            .command_for(&plain, &LaunchOptions::default())
+            .unwrap_err();
+        assert!(error.to_string().contains("Terminal argument 1"));
+        assert!(error.to_string().contains("at column 9"));
+    }
+
+    #[test]
+    fn test_shell_metacharacters_stay_literal() {
+        let dangerous = argv(&[
+            "ssh",
+            "user@server && rm -rf / | echo \"gotcha\" > /tmp/evil; $(whoami)",
+        ]);
+
+        let command = QuoteContext::Shell.render(&dangerous);
 
-        // Verify substitution would work
-        let substituted = config.args[1].replace("{ssh_command}", &escaped);
+        // The whole payload is a single quoted word, so none of it is interpreted
         assert_eq!(
-            substituted,
-            "tell app \"Terminal\" to do script \"ssh user@test-server.com\""
+            command,
+            "ssh 'user@server && rm -rf / | echo \"gotcha\" > /tmp/evil; $(whoami)'"
         );
     }
 
     #[test]
-    fn test_launcher_handles_multiple_placeholders() {
-        let config = TerminalConfig {
-            program: "/usr/bin/terminal".to_string(),
-            args: vec![
-                "--title".to_string(),
-                "SSH: {ssh_command}".to_string(),
-                "--execute".to_string(),
-                "{ssh_command}".to_string(),
-            ],
+    fn test_transport_override_order() {
+        let mut config = Config::default();
+        config.terminal.args = argv(&["{ssh_argv}"]);
+        config.transport.default = Transport::Et;
+        config.hosts.insert(
+            "flaky".to_string(),
+            crate::config::HostSettings {
+                transport: Some(Transport::Mosh),
+                ..Default::default()
+            },
+        );
+        let launcher = TerminalLauncher::new(&config);
+        let flaky = HostEntry::new("flaky".to_string(), "ssh flaky".to_string());
+        let other = HostEntry::new("other".to_string(), "ssh other".to_string());
+
+        let command = |host, transport| {
+            launcher
+                .command_for(
+                    host,
+                    &LaunchOptions {
+                        transport,
+                        ..Default::default()
+                    },
+                )
+                .unwrap()
         };
 
-        let _launcher = TerminalLauncher::new(config.clone());
-        let host = HostEntry::new("server".to_string(), "ssh user@server".to_string());
+        assert_eq!(command(&other, None).args, argv(&["et", "other"]));
+        assert_eq!(command(&flaky, None).args, argv(&["mosh", "flaky"]));
+        assert_eq!(
+            command(&flaky, Some(Transport::Ssh)).args,
+            argv(&["/usr/bin/ssh", "flaky"])
+        );
+    }
 
-        let escaped = escape_shell_command(&host.connection_string);
+    #[test]
+    fn test_launch_rejects_missing_transport_binary() {
+        let mut config = Config::default();
+        config.terminal.program = "/bin/echo".to_string();
+        config.transport.mosh_binary = "/nonexistent/mosh".to_string();
+        let host = HostEntry::new("web".to_string(), "ssh web".to_string());
 
-        // Test substitution in all args
-        let args: Vec<String> = config
-            .args
-            .iter()
-            .map(|arg| arg.replace("{ssh_command}", &escaped))
-            .collect();
+        let options = LaunchOptions {
+            transport: Some(Transport::Mosh),
+            ..Default::default()
+        };
+        let error = TerminalLauncher::new(&config)
+            .launch(&host, &options)
+            .unwrap_err();
 
-        assert_eq!(args[0], "--title");
-        assert_eq!(args[1], "SSH: ssh user@server");
-        assert_eq!(args[2], "--execute");
-        assert_eq!(args[3], "ssh user@server");
+        assert!(error.to_string().contains("/nonexistent/mosh"));
     }
 
     #[test]
-    fn test_escape_comprehensive_special_chars() {
-        let dangerous_command =
-            "ssh user@server && rm -rf / | echo \"gotcha\" > /tmp/evil; $(whoami)";
-        let escaped = escape_shell_command(dangerous_command);
+    fn test_session_wraps_connect_command() {
+        let mut config = Config::default();
+        config.terminal.args = argv(&["{ssh_argv}"]);
+        config.session.multiplexer = crate::config::Multiplexer::Tmux;
+        let host = HostEntry::new("db".to_string(), "ssh db".to_string());
+
+        let command = TerminalLauncher::new(&config)
+            .command_for(&host, &LaunchOptions::default())
+            .unwrap();
+
+        assert_eq!(
+            command.args,
+            argv(&["/usr/bin/ssh", "-t", "db", "tmux new-session -A -s db"])
+        );
+    }
 
-        // Verify all dangerous characters are escaped
-        assert!(escaped.contains("\\&\\&"));
-        assert!(escaped.contains("\\|"));
-        assert!(escaped.contains("\\;"));
-        assert!(escaped.contains("\\\""));
-        assert!(escaped.contains("\\$"));
-        assert!(escaped.contains("\\("));
-        assert!(escaped.contains("\\)"));
-        assert!(escaped.contains("\\>"));
+    #[test]
+    fn test_terminal_profile_selection() {
+        use crate::config::{HostSettings, TagSettings, TerminalProfile};
+
+        let mut config = Config::default();
+        config.terminal.program = "/usr/bin/xterm".to_string();
+        config.terminal.args = argv(&["-e", "{ssh_argv}"]);
+        for (name, program) in [
+            ("iterm-red", "/usr/bin/iterm"),
+            ("kitty-tab", "/usr/bin/kitty"),
+        ] {
+            config.terminal.profiles.insert(
+                name.to_string(),
+                TerminalProfile {
+                    program: program.to_string(),
+                    args: argv(&["{ssh_argv}"]),
+                    tab_args: None,
+                    split_args: None,
+                },
+            );
+        }
+        config.tags.insert(
+            "prod".to_string(),
+            TagSettings {
+                profile: Some("iterm-red".to_string()),
+                ..Default::default()
+            },
+        );
+        config.hosts.insert(
+            "db".to_string(),
+            HostSettings {
+                tags: vec!["prod".to_string()],
+                ..Default::default()
+            },
+        );
+        config.hosts.insert(
+            "dev".to_string(),
+            HostSettings {
+                profile: Some("kitty-tab".to_string()),
+                ..Default::default()
+            },
+        );
+        let launcher = TerminalLauncher::new(&config);
+
+        let command = |name: &str, profile: Option<&str>| {
+            let host = HostEntry::new(name.to_string(), format!("ssh {}", name));
+            let options = LaunchOptions {
+                profile: profile.map(str::to_string),
+                ..Default::default()
+            };
+            launcher.command_for(&host, &options)
+        };
+
+        let db = command("db", None).unwrap();
+        assert_eq!(db.profile.as_deref(), Some("iterm-red"));
+        assert_eq!(db.program, "/usr/bin/iterm");
+        assert_eq!(command("dev", None).unwrap().program, "/usr/bin/kitty");
+
+        let other = command("other", None).unwrap();
+        assert_eq!(other.profile, None);
+        assert_eq!(other.program, "/usr/bin/xterm");
+        assert_eq!(other.args, argv(&["-e", "/usr/bin/ssh", "other"]));
+
+        // A profile picked at launch wins over host and tag rules
+        let db = command("db", Some("kitty-tab")).unwrap();
+        assert_eq!(db.program, "/usr/bin/kitty");
+        assert!(command("db", Some("missing")).is_err());
+    }
+
+    #[test]
+    fn test_launch_target_uses_terminal_mechanism() {
+        let mut config = Config::default();
+        config.terminal.program = "/usr/bin/wezterm".to_string();
+        config.terminal.args = argv(&["start", "--", "{ssh_argv}"]);
+        let host = HostEntry::new("web".to_string(), "ssh web".to_string());
+        let launcher = TerminalLauncher::new(&config);
+
+        let command = |target| {
+            let options = LaunchOptions {
+                target,
+                ..Default::default()
+            };
+            launcher.command_for(&host, &options).unwrap()
+        };
+
+        let window = command(LaunchTarget::Window);
+        assert_eq!(window.args, argv(&["start", "--", "/usr/bin/ssh", "web"]));
+
+        let split = command(LaunchTarget::Split);
+        assert_eq!(split.target, LaunchTarget::Split);
+        assert_eq!(
+            split.args,
+            argv(&["cli", "split-pane", "--", "/usr/bin/ssh", "web"])
+        );
+    }
+
+    #[test]
+    fn test_launch_target_uses_profile_args() {
+        use crate::config::TerminalProfile;
+
+        let mut config = Config::default();
+        config.terminal.program = "/usr/bin/kitty".to_string();
+        config.terminal.args = argv(&["{ssh_argv}"]);
+        config.terminal.profiles.insert(
+            "iterm-red".to_string(),
+            TerminalProfile {
+                program: "osascript".to_string(),
+                args: argv(&["-e", "tell application \"iTerm2\" to create window with profile \"Production\" command \"{ssh_command}\""]),
+                tab_args: Some(argv(&["-e", "tell application \"iTerm2\" to tell current window to create tab with profile \"Production\" command \"{ssh_command}\""])),
+                split_args: None,
+            },
+        );
+        let host = HostEntry::new("web".to_string(), "ssh web".to_string());
+        let launcher = TerminalLauncher::new(&config);
+        let command = |target, profile: Option<&str>| {
+            let options = LaunchOptions {
+                target,
+                profile: profile.map(str::to_string),
+                ..Default::default()
+            };
+            launcher.command_for(&host, &options).unwrap()
+        };
+
+        // Built-in tabs still get the host's title
+        let tab = command(LaunchTarget::Tab, None);
+        assert_eq!(
+            tab.args,
+            argv(&[
+                "@",
+                "launch",
+                "--type=tab",
+                "--title",
+                "web",
+                "/usr/bin/ssh",
+                "web"
+            ])
+        );
+
+        let tab = command(LaunchTarget::Tab, Some("iterm-red"));
+        assert_eq!(tab.target, LaunchTarget::Tab);
+        assert_eq!(tab.program, "osascript");
+        assert_eq!(
+            tab.args[1],
+            "tell application \"iTerm2\" to tell current window to create tab with profile \"Production\" command \"/usr/bin/ssh web\""
+        );
+
+        // Without split_args, the profile's terminal decides; iTerm2 has a built-in split
+        let split = command(LaunchTarget::Split, Some("iterm-red"));
+        assert_eq!(split.target, LaunchTarget::Split);
+        assert!(split.args[1].contains("split vertically"));
+    }
+
+    #[test]
+    fn test_unsupported_target_falls_back_to_window() {
+        let mut config = Config::default();
+        config.terminal.program = "/usr/bin/alacritty".to_string();
+        config.terminal.args = argv(&["-e", "{ssh_argv}"]);
+        let host = HostEntry::new("web".to_string(), "ssh web".to_string());
+
+        let options = LaunchOptions {
+            target: LaunchTarget::Tab,
+            ..Default::default()
+        };
+        let command = TerminalLauncher::new(&config)
+            .command_for(&host, &options)
+            .unwrap();
+
+        assert_eq!(command.target, LaunchTarget::Window);
+        assert_eq!(command.program, "/usr/bin/alacritty");
+        assert_eq!(command.args, argv(&["-e", "/usr/bin/ssh", "web"]));
+    }
+
+    #[test]
+    fn test_forwards_compile_into_ssh_argv() {
+        let mut config = Config::default();
+        config.terminal.program = "/usr/bin/xterm".to_string();
+        config.terminal.args = argv(&["-e", "{ssh_argv}"]);
+        config.session.multiplexer = crate::config::Multiplexer::Tmux;
+        config.forwards.insert(
+            "postgres".to_string(),
+            crate::config::ForwardPreset {
+                kind: crate::config::ForwardKind::Local,
+                port: 5432,
+                target: Some("localhost:5432".to_string()),
+                forward_only: false,
+            },
+        );
+        let host = HostEntry::new("db".to_string(), "ssh db".to_string());
+        let launcher = TerminalLauncher::new(&config);
+        let options = LaunchOptions {
+            forwards: vec!["postgres".to_string()],
+            ..Default::default()
+        };
+
+        let command = launcher.command_for(&host, &options).unwrap();
+        assert_eq!(
+            command.ssh_argv,
+            argv(&[
+                "/usr/bin/ssh",
+                "-L",
+                "5432:localhost:5432",
+                "-t",
+                "db",
+                "tmux new-session -A -s db"
+            ])
+        );
+
+        // Forward-only skips the session and holds the forward open instead
+        config.forwards.get_mut("postgres").unwrap().forward_only = true;
+        let command = TerminalLauncher::new(&config)
+            .command_for(&host, &options)
+            .unwrap();
+        assert_eq!(
+            command.ssh_argv,
+            argv(&["/usr/bin/ssh", "-N", "-L", "5432:localhost:5432", "db"])
+        );
+    }
+
+    #[test]
+    fn test_unknown_forward_fails() {
+        let config = Config::default();
+        let host = HostEntry::new("db".to_string(), "ssh db".to_string());
+        let options = LaunchOptions {
+            forwards: vec!["missing".to_string()],
+            ..Default::default()
+        };
+
+        let error = TerminalLauncher::new(&config)
+            .command_for(&host, &options)
+            .unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("Unknown forward preset 'missing'")
+        );
+    }
+
+    #[test]
+    fn test_tunnel_holds_forwards_without_shell() {
+        let mut config = Config::default();
+        config.session.multiplexer = crate::config::Multiplexer::Tmux;
+        config.forwards.insert(
+            "socks".to_string(),
+            crate::config::ForwardPreset {
+                kind: crate::config::ForwardKind::Dynamic,
+                port: 1080,
+                target: None,
+                forward_only: false,
+            },
+        );
+        let host = HostEntry::new("db".to_string(), "ssh db".to_string());
+        let launcher = TerminalLauncher::new(&config);
+        let options = LaunchOptions {
+            forwards: vec!["socks".to_string()],
+            ..Default::default()
+        };
+
+        let tunnel = launcher.tunnel_for(&host, &options).unwrap();
+        assert_eq!(tunnel.id(), "db:socks");
+        assert_eq!(
+            tunnel.argv,
+            argv(&[
+                "/usr/bin/ssh",
+                "-N",
+                "-D",
+                "1080",
+                "-o",
+                "ExitOnForwardFailure=yes",
+                "-o",
+                "BatchMode=yes",
+                "db"
+            ])
+        );
+
+        let error = launcher
+            .tunnel_for(&host, &LaunchOptions::default())
+            .unwrap_err();
+        assert!(error.to_string().contains("at least one forward preset"));
+    }
+
+    #[test]
+    fn test_sftp_replaces_shell() {
+        let mut config = Config::default();
+        config.terminal.program = "/usr/bin/xterm".to_string();
+        config.terminal.args = argv(&["-e", "{ssh_argv}"]);
+        config.session.multiplexer = crate::config::Multiplexer::Tmux;
+        config.transport.default = Transport::Mosh;
+        let host = HostEntry::new("db".to_string(), "ssh db".to_string());
+        let launcher = TerminalLauncher::new(&config);
+        let options = LaunchOptions {
+            sftp: true,
+            ..Default::default()
+        };
+
+        let command = launcher.command_for(&host, &options).unwrap();
+        assert_eq!(command.transport, Transport::Ssh);
+        assert_eq!(command.args, argv(&["-e", "sftp", "db"]));
+
+        let with_forwards = LaunchOptions {
+            sftp: true,
+            forwards: vec!["postgres".to_string()],
+            ..Default::default()
+        };
+        assert!(launcher.command_for(&host, &with_forwards).is_err());
+    }
+
+    #[test]
+    fn test_remote_command_replaces_session() {
+        let mut config = Config::default();
+        config.terminal.program = "/usr/bin/xterm".to_string();
+        config.terminal.args = argv(&["-e", "{ssh_argv}"]);
+        config.session.multiplexer = crate::config::Multiplexer::Tmux;
+        let host = HostEntry::new("db".to_string(), "ssh db".to_string());
+        let options = LaunchOptions {
+            remote_command: Some("sudo -i".to_string()),
+            ..Default::default()
+        };
+
+        let command = TerminalLauncher::new(&config)
+            .command_for(&host, &options)
+            .unwrap();
+        assert_eq!(
+            command.ssh_argv,
+            argv(&["/usr/bin/ssh", "-t", "db", "sh -c 'sudo -i'"])
+        );
+
+        config.transport.default = Transport::Custom;
+        config.transport.custom = Some("tsh ssh {host}".to_string());
+        let error = TerminalLauncher::new(&config)
+            .command_for(&host, &options)
+            .unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("not supported with the custom transport")
+        );
     }
 }
@@ -1,8 +1,24 @@
 // ABOUTME: SSH file parsing and terminal launching module for SSH connections
 // ABOUTME: Provides simple parsing focused on extracting hostnames for fuzzy search and safe terminal launching
 
+pub mod agent;
+pub mod broadcast;
+pub mod details;
+pub mod dry_run;
+pub mod environment;
+pub mod files;
+pub mod forward;
+pub mod hooks;
+pub mod host_keys;
 pub mod launcher;
 pub mod parser;
+pub mod probe;
+pub mod process;
+pub mod session;
+pub mod target;
+pub mod template;
+pub mod transport;
 
-pub use launcher::TerminalLauncher;
-pub use parser::{HostEntry, parse_known_hosts, parse_ssh_config};
+pub use launcher::{LaunchCommand, LaunchOptions, LaunchOutcome, TerminalLauncher};
+pub use parser::{HostEntry, dedup_hosts, parse_known_hosts, parse_ssh_config};
+pub use target::LaunchTarget;
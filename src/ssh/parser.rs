@@ -1,6 +1,8 @@
 // ABOUTME: Simple SSH file parsers for extracting host entries from known_hosts and SSH config files
 // ABOUTME: Implements configuration-driven parsing with support for skipping complex features
 
+use crate::ssh::host_keys::{HostKey, KnownKey};
+use crate::ssh::transport::DEFAULT_SSH_PORT;
 use anyhow::{Context, Result};
 use std::fs;
 use std::path::Path;
@@ -9,6 +11,13 @@ use std::path::Path;
 pub struct HostEntry {
     pub name: String,              // What user types to match
     pub connection_string: String, // What gets passed to SSH
+    pub hostname: Option<String>,  // Resolved `HostName` from SSH config
+    pub user: Option<String>,
+    pub port: Option<u16>,
+    pub description: Option<String>, // `# comment` directly above the Host block
+    pub identity_files: Vec<String>, // `IdentityFile` entries, as written
+    pub proxy: Option<String>,       // `ProxyJump` or `ProxyCommand`, unless `none`
+    pub host_keys: Vec<KnownKey>,    // Keys stored for the host in known_hosts, by port
 }
 
 impl HostEntry {
@@ -16,10 +25,59 @@ impl HostEntry {
         Self {
             name,
             connection_string,
+            hostname: None,
+            user: None,
+            port: None,
+            description: None,
+            identity_files: Vec::new(),
+            proxy: None,
+            host_keys: Vec::new(),
+        }
+    }
+
+    /// Fill in any fields this entry is missing from another entry for the same host
+    pub fn merge_missing(&mut self, other: &HostEntry) {
+        if self.hostname.is_none() {
+            self.hostname = other.hostname.clone();
+        }
+        if self.user.is_none() {
+            self.user = other.user.clone();
+        }
+        if self.port.is_none() {
+            self.port = other.port;
+        }
+        if self.description.is_none() {
+            self.description = other.description.clone();
+        }
+        if self.identity_files.is_empty() {
+            self.identity_files = other.identity_files.clone();
+        }
+        if self.proxy.is_none() {
+            self.proxy = other.proxy.clone();
+        }
+        // known_hosts lists each key type and port on its own line, so keys from both
+        // entries count; each keeps the port it was stored for
+        for key in &other.host_keys {
+            if !self.host_keys.contains(key) {
+                self.host_keys.push(key.clone());
+            }
         }
     }
 }
 
+/// Sort hosts by name and collapse duplicates, keeping every field either source knew
+pub fn dedup_hosts(hosts: &mut Vec<HostEntry>) {
+    hosts.sort_by(|a, b| a.name.cmp(&b.name));
+    hosts.dedup_by(|duplicate, kept| {
+        if duplicate.name == kept.name {
+            kept.merge_missing(duplicate);
+            true
+        } else {
+            false
+        }
+    });
+}
+
 pub fn parse_known_hosts(path: &Path, skip_hashed: bool) -> Result<Vec<HostEntry>> {
     let content = fs::read_to_string(path)
         .with_context(|| format!("Failed to read known_hosts file: {}", path.display()))?;
@@ -43,8 +101,19 @@ fn parse_known_hosts_content(content: &str, skip_hashed: bool) -> Vec<HostEntry>
             continue;
         }
 
-        // Extract hostname(s) from the line
-        if let Some(hosts_part) = line.split_whitespace().next() {
+        // `@cert-authority` and `@revoked` lines name patterns, not hosts to connect to
+        if line.starts_with('@') {
+            continue;
+        }
+
+        // Extract hostname(s) from the line, then the key type and base64 key
+        let mut fields = line.split_whitespace();
+        let hosts_part = fields.next();
+        let host_key = fields
+            .next()
+            .zip(fields.next())
+            .and_then(|(key_type, key)| HostKey::parse(key_type, key));
+        if let Some(hosts_part) = hosts_part {
             // Handle comma-separated hosts
             for host in hosts_part.split(',') {
                 let host = host.trim();
@@ -54,30 +123,43 @@ fn parse_known_hosts_content(content: &str, skip_hashed: bool) -> Vec<HostEntry>
                     continue;
                 }
 
-                // Skip ports specified with brackets like [hostname]:port
-                let clean_host = if host.starts_with('[') && host.contains("]:") {
-                    if let Some(end) = host.find("]:") {
-                        &host[1..end]
-                    } else {
-                        host
-                    }
-                } else {
-                    host
-                };
+                // Strip ports specified with brackets like [hostname]:port
+                let (clean_host, port) =
+                    match host.strip_prefix('[').and_then(|h| h.split_once("]:")) {
+                        Some((name, port)) => (name, port.parse::<u16>().ok()),
+                        None => (host, None),
+                    };
 
                 if !clean_host.is_empty() && !clean_host.starts_with('|') {
-                    entries.push(HostEntry::new(
-                        clean_host.to_string(),
-                        format!("ssh {}", clean_host),
-                    ));
+                    let mut entry =
+                        HostEntry::new(clean_host.to_string(), format!("ssh {}", clean_host));
+                    entry.port = port;
+                    entry.host_keys.extend(host_key.clone().map(|key| KnownKey {
+                        port: port.unwrap_or(DEFAULT_SSH_PORT),
+                        key,
+                    }));
+                    entries.push(entry);
                 }
             }
         }
     }
 
-    // Remove duplicates
+    // Remove duplicates. A host listed on the default port is reached there, as `ssh host`
+    // is, so another port's line only adds its keys, tagged with that port
     entries.sort_by(|a, b| a.name.cmp(&b.name));
-    entries.dedup_by(|a, b| a.name == b.name);
+    entries.dedup_by(|duplicate, kept| {
+        if duplicate.name != kept.name {
+            return false;
+        }
+        let on_default_port = [kept.port, duplicate.port]
+            .iter()
+            .any(|port| port.is_none_or(|port| port == DEFAULT_SSH_PORT));
+        kept.merge_missing(duplicate);
+        if on_default_port {
+            kept.port = None;
+        }
+        true
+    });
 
     entries
 }
@@ -91,19 +173,30 @@ pub fn parse_ssh_config(path: &Path, simple_parsing: bool) -> Result<Vec<HostEnt
 
 fn parse_ssh_config_content(content: &str, _simple_parsing: bool) -> Vec<HostEntry> {
     let mut entries = Vec::new();
-    let mut current_host: Option<String> = None;
+    let mut current_host: Option<HostEntry> = None;
+    // Comment lines directly above the next Host line become its description
+    let mut pending_comments: Vec<&str> = Vec::new();
 
     for line in content.lines() {
         let line = line.trim();
 
         // Skip empty lines and comments
-        if line.is_empty() || line.starts_with('#') {
+        if line.is_empty() {
+            pending_comments.clear();
+            continue;
+        }
+        if let Some(comment) = line.strip_prefix('#') {
+            let comment = comment.trim();
+            if !comment.is_empty() {
+                pending_comments.push(comment);
+            }
             continue;
         }
 
         // Split by whitespace to get key-value pairs
         let parts: Vec<&str> = line.split_whitespace().collect();
         if parts.len() < 2 {
+            pending_comments.clear();
             continue;
         }
 
@@ -114,34 +207,70 @@ fn parse_ssh_config_content(content: &str, _simple_parsing: bool) -> Vec<HostEnt
             "host" => {
                 // Process the previous host if any
                 if let Some(host) = current_host.take() {
-                    if !host.contains('*') && !host.contains('?') {
-                        entries.push(HostEntry::new(host.clone(), format!("ssh {}", host)));
-                    }
+                    push_config_host(&mut entries, host);
                 }
 
                 // Start new host
-                current_host = Some(value);
+                let mut host = HostEntry::new(value.clone(), format!("ssh {}", value));
+                if !pending_comments.is_empty() {
+                    host.description = Some(pending_comments.join(" "));
+                }
+                current_host = Some(host);
+            }
+            "hostname" => {
+                if let Some(host) = current_host.as_mut() {
+                    host.hostname = Some(value);
+                }
+            }
+            "user" => {
+                if let Some(host) = current_host.as_mut() {
+                    host.user = Some(value);
+                }
+            }
+            "port" => {
+                if let Some(host) = current_host.as_mut() {
+                    host.port = value.parse().ok();
+                }
+            }
+            "identityfile" => {
+                if let Some(host) = current_host.as_mut() {
+                    host.identity_files
+                        .push(value.trim_matches('"').to_string());
+                }
+            }
+            "proxyjump" | "proxycommand" => {
+                if let Some(host) = current_host.as_mut()
+                    && host.proxy.is_none()
+                    && !value.eq_ignore_ascii_case("none")
+                {
+                    host.proxy = Some(value);
+                }
             }
             _ => {
                 // For simple parsing, we ignore all other directives
             }
         }
+        pending_comments.clear();
     }
 
     // Process the last host
     if let Some(host) = current_host {
-        if !host.contains('*') && !host.contains('?') {
-            entries.push(HostEntry::new(host.clone(), format!("ssh {}", host)));
-        }
+        push_config_host(&mut entries, host);
     }
 
     // Remove duplicates
-    entries.sort_by(|a, b| a.name.cmp(&b.name));
-    entries.dedup_by(|a, b| a.name == b.name);
+    dedup_hosts(&mut entries);
 
     entries
 }
 
+fn push_config_host(entries: &mut Vec<HostEntry>, host: HostEntry) {
+    // Wildcard patterns are defaults for other hosts, not connectable hosts
+    if !host.name.contains('*') && !host.name.contains('?') {
+        entries.push(host);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -246,4 +375,138 @@ example.com ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAI...";
         assert_eq!(entries.len(), 1);
         assert_eq!(entries[0].name, "example.com");
     }
+
+    #[test]
+    fn test_parse_known_hosts_keys() {
+        let content = "example.com,alias.example.com ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIPQVbVw/rj+RTMBVLm7CactYAToJ7Wo1rv11TcTKzixY
+example.com ecdsa-sha2-nistp256 AAAAE2VjZHNhLXNoYTItbmlzdHAyNTYAAAAIbmlzdHAyNTYAAABBBBWIEqPqCXAOGWLcjjL6PHsLEr6YRSquRgB10SGC7cZbyvqdU19zD2cBNU9nRenfFrLW4SZVe2JOPKsVzU8OVw8= comment
+@cert-authority *.example.com ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIPQVbVw/rj+RTMBVLm7CactYAToJ7Wo1rv11TcTKzixY
+broken.example.com ssh-rsa";
+
+        let entries = parse_known_hosts_content(content, false);
+
+        assert_eq!(entries.len(), 3);
+        let example = entries.iter().find(|e| e.name == "example.com").unwrap();
+        let types: Vec<&str> = example
+            .host_keys
+            .iter()
+            .map(|known| known.key.key_type.as_str())
+            .collect();
+        assert_eq!(types, vec!["ssh-ed25519", "ecdsa-sha2-nistp256"]);
+        assert_eq!(
+            example.host_keys[0].key.fingerprint(),
+            "SHA256:vthKWWjqXX8vAhdvFYJAOWZR8GGQKPz6WIoeZeaNb+4"
+        );
+
+        let alias = entries
+            .iter()
+            .find(|e| e.name == "alias.example.com")
+            .unwrap();
+        assert_eq!(alias.host_keys.len(), 1);
+
+        // A line without a usable key still lists the host
+        let broken = entries
+            .iter()
+            .find(|e| e.name == "broken.example.com")
+            .unwrap();
+        assert!(broken.host_keys.is_empty());
+    }
+
+    #[test]
+    fn test_known_hosts_keys_keep_their_port() {
+        let content = "example.com ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIPQVbVw/rj+RTMBVLm7CactYAToJ7Wo1rv11TcTKzixY
+[example.com]:2222 ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIHome000000000000000000000000000000000000000";
+
+        let entries = parse_known_hosts_content(content, false);
+
+        assert_eq!(entries.len(), 1);
+        let ports: Vec<u16> = entries[0]
+            .host_keys
+            .iter()
+            .map(|known| known.port)
+            .collect();
+        assert_eq!(ports, vec![22, 2222]);
+        assert_ne!(entries[0].host_keys[0].key, entries[0].host_keys[1].key);
+        // `ssh example.com` connects on 22, whichever line comes first
+        assert_eq!(entries[0].port, None);
+
+        let reversed: String = content.lines().rev().collect::<Vec<_>>().join("\n");
+        let entries = parse_known_hosts_content(&reversed, false);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].port, None);
+        assert_eq!(entries[0].host_keys.len(), 2);
+    }
+
+    #[test]
+    fn test_parse_ssh_config_fields() {
+        let content = "# Primary database
+# (read replica is db-ro)
+Host db
+    HostName 10.2.0.15
+    User postgres
+    Port 2222
+    IdentityFile ~/.ssh/id_db
+    IdentityFile \"~/.ssh/id ops\"
+
+Host bastion
+    HostName ec2-54-12-34-56.compute.amazonaws.com
+    ProxyJump none
+
+Host internal
+    ProxyJump bastion
+    ProxyCommand ssh -W %h:%p other
+";
+
+        let entries = parse_ssh_config_content(content, true);
+
+        let db = entries.iter().find(|e| e.name == "db").unwrap();
+        assert_eq!(db.hostname.as_deref(), Some("10.2.0.15"));
+        assert_eq!(db.user.as_deref(), Some("postgres"));
+        assert_eq!(db.port, Some(2222));
+        assert_eq!(db.identity_files, vec!["~/.ssh/id_db", "~/.ssh/id ops"]);
+        assert_eq!(
+            db.description.as_deref(),
+            Some("Primary database (read replica is db-ro)")
+        );
+
+        let bastion = entries.iter().find(|e| e.name == "bastion").unwrap();
+        assert_eq!(
+            bastion.hostname.as_deref(),
+            Some("ec2-54-12-34-56.compute.amazonaws.com")
+        );
+        assert_eq!(bastion.user, None);
+        assert!(bastion.identity_files.is_empty());
+        assert_eq!(bastion.proxy, None);
+        assert_eq!(db.proxy, None);
+
+        // The first proxy directive wins, as in ssh
+        let internal = entries.iter().find(|e| e.name == "internal").unwrap();
+        assert_eq!(internal.proxy.as_deref(), Some("bastion"));
+        // A blank line separates comments from the next Host block
+        assert_eq!(bastion.description, None);
+    }
+
+    #[test]
+    fn test_parse_known_hosts_records_port() {
+        let content = "[example.com]:2222 ssh-rsa AAAAB3NzaC1yc2EAAAABIwAAAQEA...";
+
+        let entries = parse_known_hosts_content(content, false);
+
+        assert_eq!(entries[0].port, Some(2222));
+    }
+
+    #[test]
+    fn test_dedup_hosts_merges_fields() {
+        let mut from_known_hosts = HostEntry::new("db".to_string(), "ssh db".to_string());
+        from_known_hosts.port = Some(22);
+        let mut from_config = HostEntry::new("db".to_string(), "ssh db".to_string());
+        from_config.hostname = Some("10.2.0.15".to_string());
+
+        let mut hosts = vec![from_known_hosts, from_config];
+        dedup_hosts(&mut hosts);
+
+        assert_eq!(hosts.len(), 1);
+        assert_eq!(hosts[0].port, Some(22));
+        assert_eq!(hosts[0].hostname.as_deref(), Some("10.2.0.15"));
+    }
 }
@@ -0,0 +1,510 @@
+// ABOUTME: Reachability probes: TCP connect to each host's address and port, then read the SSH banner
+// ABOUTME: Runs with bounded concurrency and caches results on disk so reopening the launcher is instant
+
+use crate::config::ProbeConfig;
+use crate::ssh::parser::HostEntry;
+use crate::ssh::transport::DEFAULT_SSH_PORT;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io::{self, Read};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+/// The most banner bytes read while looking for the `SSH-` line
+const MAX_BANNER_BYTES: usize = 1024;
+
+/// Where a host's probe connects
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProbeTarget {
+    pub host: String,
+    /// `HostName`, or the alias
+    pub address: String,
+    pub port: u16,
+    /// The connection goes through `ProxyJump` or `ProxyCommand`, which a direct probe can't follow
+    pub via_proxy: bool,
+}
+
+impl ProbeTarget {
+    pub fn for_host(host: &HostEntry) -> Self {
+        Self {
+            host: host.name.clone(),
+            address: host.hostname.clone().unwrap_or_else(|| host.name.clone()),
+            port: host.port.unwrap_or(DEFAULT_SSH_PORT),
+            via_proxy: host.proxy.is_some(),
+        }
+    }
+
+    /// Cache key: hosts sharing an address and port share a result
+    fn key(&self) -> String {
+        format!("{}:{}", self.address, self.port)
+    }
+}
+
+/// What a probe found
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum ProbeResult {
+    /// sshd answered with its banner, e.g. `SSH-2.0-OpenSSH_9.6`
+    Ssh {
+        latency_ms: u64,
+        banner: String,
+    },
+    /// The port accepted the connection but didn't send an SSH banner
+    Open {
+        latency_ms: u64,
+    },
+    Unreachable {
+        reason: String,
+    },
+    /// Behind a jump host, so not probed directly
+    NotProbed,
+}
+
+impl fmt::Display for ProbeResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProbeResult::Ssh { latency_ms, .. } => write!(f, "{} ms", latency_ms),
+            ProbeResult::Open { latency_ms } => write!(f, "{} ms, not ssh", latency_ms),
+            ProbeResult::Unreachable { .. } => write!(f, "unreachable"),
+            ProbeResult::NotProbed => write!(f, "not probed"),
+        }
+    }
+}
+
+/// Resolve a target and connect to it within `timeout`, then read its banner within `timeout`
+pub fn probe(target: &ProbeTarget, timeout: Duration) -> ProbeResult {
+    if target.via_proxy {
+        return ProbeResult::NotProbed;
+    }
+    let unreachable = |reason: String| ProbeResult::Unreachable { reason };
+
+    let deadline = Instant::now() + timeout;
+    let lookup = (target.address.clone(), target.port);
+    let resolved = resolve_within(
+        move || lookup.to_socket_addrs().map(Iterator::collect),
+        timeout,
+    );
+    let addresses = match resolved {
+        Ok(addresses) => addresses,
+        Err(e) => return unreachable(format!("can't resolve {}: {}", target.address, e)),
+    };
+    let Some(address) = addresses.first() else {
+        return unreachable(format!("{} has no addresses", target.address));
+    };
+    let remaining = deadline.saturating_duration_since(Instant::now());
+    if remaining.is_zero() {
+        return unreachable("connection timed out".to_string());
+    }
+
+    let started = Instant::now();
+    let mut stream = match TcpStream::connect_timeout(address, remaining) {
+        Ok(stream) => stream,
+        Err(e) => return unreachable(e.to_string()),
+    };
+    let latency_ms = started.elapsed().as_millis() as u64;
+
+    match read_banner(&mut stream, timeout) {
+        Some(banner) => ProbeResult::Ssh { latency_ms, banner },
+        None => ProbeResult::Open { latency_ms },
+    }
+}
+
+/// Run a name lookup on its own thread, giving up after `timeout`
+///
+/// The system resolver has no timeout of its own, so a stalled DNS server would otherwise
+/// hold the probe past its deadline. A lookup that's given up on finishes in the background.
+fn resolve_within(
+    lookup: impl FnOnce() -> io::Result<Vec<SocketAddr>> + Send + 'static,
+    timeout: Duration,
+) -> io::Result<Vec<SocketAddr>> {
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        let _ = sender.send(lookup());
+    });
+    receiver
+        .recv_timeout(timeout)
+        .unwrap_or_else(|_| Err(io::Error::new(io::ErrorKind::TimedOut, "lookup timed out")))
+}
+
+/// The server's `SSH-` identification line; servers may send other lines before it
+fn read_banner(stream: &mut TcpStream, timeout: Duration) -> Option<String> {
+    stream.set_read_timeout(Some(timeout)).ok()?;
+    let deadline = Instant::now() + timeout;
+    let mut received = Vec::new();
+    let mut buffer = [0u8; 256];
+
+    while received.len() < MAX_BANNER_BYTES && Instant::now() < deadline {
+        let read = stream.read(&mut buffer).ok()?;
+        if read == 0 {
+            break;
+        }
+        received.extend_from_slice(&buffer[..read]);
+
+        let text = String::from_utf8_lossy(&received);
+        let complete_lines = text.rsplit_once('\n').map_or("", |(lines, _)| lines);
+        if let Some(line) = complete_lines.lines().find(|line| line.starts_with("SSH-")) {
+            return Some(line.trim_end().to_string());
+        }
+    }
+    None
+}
+
+/// Probe every target, at most `concurrency` at a time; results are in target order
+pub fn probe_all(
+    targets: &[ProbeTarget],
+    concurrency: usize,
+    timeout: Duration,
+) -> Vec<ProbeResult> {
+    let results = Mutex::new(vec![ProbeResult::NotProbed; targets.len()]);
+    let next = AtomicUsize::new(0);
+
+    std::thread::scope(|scope| {
+        for _ in 0..concurrency.clamp(1, targets.len().max(1)) {
+            scope.spawn(|| {
+                loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(target) = targets.get(index) else {
+                        break;
+                    };
+                    let result = probe(target, timeout);
+                    results.lock().unwrap()[index] = result;
+                }
+            });
+        }
+    });
+
+    results.into_inner().unwrap()
+}
+
+/// Probe results from earlier launcher sessions, keyed by `address:port`
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct ProbeCache {
+    #[serde(default)]
+    pub entries: BTreeMap<String, CachedProbe>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct CachedProbe {
+    /// Seconds since the Unix epoch
+    pub checked_at: u64,
+    pub result: ProbeResult,
+}
+
+impl ProbeCache {
+    pub fn default_path() -> Result<PathBuf> {
+        let cache_dir = dirs::cache_dir().context("Failed to determine cache directory")?;
+        Ok(cache_dir.join("trident").join("probes.toml"))
+    }
+
+    /// Load the cache, treating a missing file as empty
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read probe cache: {}", path.display()))?;
+        toml::from_str(&content)
+            .with_context(|| format!("Failed to parse probe cache: {}", path.display()))
+    }
+
+    /// Write the cache to a temporary file and rename it into place
+    ///
+    /// Several probe batches save the same file, so a crash mid-write must not leave it
+    /// half written.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).with_context(|| {
+                format!("Failed to create cache directory: {}", parent.display())
+            })?;
+        }
+
+        let content = toml::to_string(self).context("Failed to serialize probe cache")?;
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let temp_path = path.with_extension(format!(
+            "toml.{}-{}.tmp",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::write(&temp_path, content)
+            .with_context(|| format!("Failed to write probe cache: {}", temp_path.display()))?;
+        fs::rename(&temp_path, path).with_context(|| {
+            let _ = fs::remove_file(&temp_path);
+            format!("Failed to replace probe cache: {}", path.display())
+        })
+    }
+
+    /// A result no older than `max_age_secs`
+    pub fn fresh(&self, target: &ProbeTarget, now: u64, max_age_secs: u64) -> Option<&ProbeResult> {
+        self.entries
+            .get(&target.key())
+            .filter(|cached| now.saturating_sub(cached.checked_at) <= max_age_secs)
+            .map(|cached| &cached.result)
+    }
+
+    /// Take entries from another copy of the cache, keeping the newer result per target
+    ///
+    /// Each batch of probes works on its own copy so a slow network doesn't hold the
+    /// cache; merging the copies back means no batch overwrites what another found.
+    pub fn merge(&mut self, other: ProbeCache) {
+        for (key, probe) in other.entries {
+            match self.entries.get(&key) {
+                Some(existing) if existing.checked_at > probe.checked_at => {}
+                _ => {
+                    self.entries.insert(key, probe);
+                }
+            }
+        }
+    }
+
+    pub fn insert(&mut self, target: &ProbeTarget, result: ProbeResult, now: u64) {
+        self.entries.insert(
+            target.key(),
+            CachedProbe {
+                checked_at: now,
+                result,
+            },
+        );
+    }
+}
+
+/// Results for hosts, from the cache where fresh and from new probes otherwise
+///
+/// New results are written back to the cache. Hosts behind a proxy are never probed.
+pub fn probe_hosts(
+    hosts: &[HostEntry],
+    config: &ProbeConfig,
+    cache: &mut ProbeCache,
+    now: u64,
+) -> BTreeMap<String, ProbeResult> {
+    let mut results = BTreeMap::new();
+    let mut pending = Vec::new();
+
+    for host in hosts {
+        let target = ProbeTarget::for_host(host);
+        if target.via_proxy {
+            results.insert(target.host, ProbeResult::NotProbed);
+        } else if let Some(cached) = cache.fresh(&target, now, config.cache_seconds) {
+            results.insert(target.host, cached.clone());
+        } else {
+            pending.push(target);
+        }
+    }
+
+    let timeout = Duration::from_millis(config.timeout_ms);
+    let probed = probe_all(&pending, config.concurrency, timeout);
+    for (target, result) in pending.into_iter().zip(probed) {
+        cache.insert(&target, result.clone(), now);
+        results.insert(target.host, result);
+    }
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::net::TcpListener;
+    use tempfile::TempDir;
+
+    const TIMEOUT: Duration = Duration::from_millis(500);
+
+    /// A local listener that greets each connection with `greeting`
+    fn listener(greeting: &'static [u8]) -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        std::thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let _ = stream.write_all(greeting);
+                // Keep the connection open until the prober gives up
+                std::thread::spawn(move || {
+                    std::thread::sleep(Duration::from_secs(2));
+                    drop(stream);
+                });
+            }
+        });
+        port
+    }
+
+    /// Port 0 is reserved, so connecting to it fails without anything racing to bind it
+    const CLOSED_PORT: u16 = 0;
+
+    fn target(port: u16) -> ProbeTarget {
+        ProbeTarget {
+            host: format!("local-{}", port),
+            address: "127.0.0.1".to_string(),
+            port,
+            via_proxy: false,
+        }
+    }
+
+    #[test]
+    fn test_probe_reads_ssh_banner() {
+        let port = listener(b"SSH-2.0-OpenSSH_9.6\r\n");
+
+        match probe(&target(port), TIMEOUT) {
+            ProbeResult::Ssh { banner, latency_ms } => {
+                assert_eq!(banner, "SSH-2.0-OpenSSH_9.6");
+                assert!(latency_ms < TIMEOUT.as_millis() as u64);
+            }
+            other => panic!("expected ssh, got {:?}", other),
+        }
+
+        // Lines before the identification string are allowed
+        let port = listener(b"Authorized use only\r\nSSH-2.0-dropbear\r\n");
+        assert!(matches!(
+            probe(&target(port), TIMEOUT),
+            ProbeResult::Ssh { banner, .. } if banner == "SSH-2.0-dropbear"
+        ));
+    }
+
+    #[test]
+    fn test_probe_without_banner_is_open() {
+        let port = listener(b"HTTP/1.1 400 Bad Request\r\n\r\n");
+        assert!(matches!(
+            probe(&target(port), TIMEOUT),
+            ProbeResult::Open { .. }
+        ));
+
+        let silent = listener(b"");
+        assert!(matches!(
+            probe(&target(silent), Duration::from_millis(200)),
+            ProbeResult::Open { .. }
+        ));
+    }
+
+    #[test]
+    fn test_probe_closed_port_is_unreachable() {
+        let result = probe(&target(CLOSED_PORT), TIMEOUT);
+        assert!(matches!(result, ProbeResult::Unreachable { .. }));
+        assert_eq!(result.to_string(), "unreachable");
+    }
+
+    #[test]
+    fn test_stalled_lookup_times_out() {
+        let started = Instant::now();
+        let error = resolve_within(
+            || {
+                std::thread::sleep(Duration::from_secs(5));
+                Ok(Vec::new())
+            },
+            Duration::from_millis(100),
+        )
+        .unwrap_err();
+
+        assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+        assert!(started.elapsed() < Duration::from_secs(2));
+
+        let address: SocketAddr = "127.0.0.1:22".parse().unwrap();
+        assert_eq!(
+            resolve_within(move || Ok(vec![address]), TIMEOUT).unwrap(),
+            vec![address]
+        );
+    }
+
+    #[test]
+    fn test_proxied_hosts_are_not_probed() {
+        let mut host = HostEntry::new("internal".to_string(), "ssh internal".to_string());
+        host.hostname = Some("127.0.0.1".to_string());
+        host.port = Some(CLOSED_PORT);
+        host.proxy = Some("bastion".to_string());
+
+        assert_eq!(
+            probe(&ProbeTarget::for_host(&host), TIMEOUT),
+            ProbeResult::NotProbed
+        );
+        assert_eq!(ProbeResult::NotProbed.to_string(), "not probed");
+    }
+
+    #[test]
+    fn test_probe_all_keeps_order_with_bounded_concurrency() {
+        let ssh = listener(b"SSH-2.0-OpenSSH_9.6\r\n");
+        let targets: Vec<ProbeTarget> = (0..6)
+            .map(|i| target(if i % 2 == 0 { ssh } else { CLOSED_PORT }))
+            .collect();
+
+        let results = probe_all(&targets, 2, TIMEOUT);
+
+        assert_eq!(results.len(), 6);
+        for (i, result) in results.iter().enumerate() {
+            if i % 2 == 0 {
+                assert!(matches!(result, ProbeResult::Ssh { .. }), "{:?}", result);
+            } else {
+                assert!(matches!(result, ProbeResult::Unreachable { .. }));
+            }
+        }
+        assert!(probe_all(&[], 4, TIMEOUT).is_empty());
+    }
+
+    #[test]
+    fn test_probe_hosts_uses_fresh_cache() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache_path = temp_dir.path().join("probes.toml");
+        let port = listener(b"SSH-2.0-OpenSSH_9.6\r\n");
+        let mut host = HostEntry::new("web".to_string(), "ssh web".to_string());
+        host.hostname = Some("127.0.0.1".to_string());
+        host.port = Some(port);
+        let config = ProbeConfig {
+            cache_seconds: 60,
+            ..Default::default()
+        };
+
+        let mut cache = ProbeCache::default();
+        let results = probe_hosts(std::slice::from_ref(&host), &config, &mut cache, 1000);
+        assert!(matches!(results["web"], ProbeResult::Ssh { .. }));
+        cache.save(&cache_path).unwrap();
+        // Saved through a temporary file that's renamed into place
+        assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 1);
+
+        // A cached result is used while fresh, even though the host now looks different
+        let mut cache = ProbeCache::load(&cache_path).unwrap();
+        let cached = ProbeResult::Unreachable {
+            reason: "cached".to_string(),
+        };
+        cache.insert(&ProbeTarget::for_host(&host), cached.clone(), 1000);
+        let results = probe_hosts(std::slice::from_ref(&host), &config, &mut cache, 1030);
+        assert_eq!(results["web"], cached);
+
+        // Once stale, the host is probed again and the cache updated
+        let results = probe_hosts(std::slice::from_ref(&host), &config, &mut cache, 1100);
+        assert!(matches!(results["web"], ProbeResult::Ssh { .. }));
+        assert_eq!(
+            cache.fresh(&ProbeTarget::for_host(&host), 1100, 60),
+            Some(&results["web"])
+        );
+    }
+
+    #[test]
+    fn test_merge_keeps_every_batch() {
+        let ssh = ProbeResult::Ssh {
+            latency_ms: 3,
+            banner: "SSH-2.0-OpenSSH_9.6".to_string(),
+        };
+        let down = ProbeResult::Unreachable {
+            reason: "refused".to_string(),
+        };
+        let mut shared = ProbeCache::default();
+        shared.insert(&target(1), ssh.clone(), 1000);
+
+        // Two batches probed from the same copy, finishing in either order
+        let mut first = shared.clone();
+        first.insert(&target(2), ssh.clone(), 1010);
+        let mut second = shared.clone();
+        second.insert(&target(3), down.clone(), 1020);
+        second.insert(&target(1), down.clone(), 1020);
+        shared.merge(second);
+        shared.merge(first);
+
+        assert_eq!(shared.entries.len(), 3);
+        assert_eq!(shared.fresh(&target(1), 1020, 60), Some(&down));
+        assert_eq!(shared.fresh(&target(2), 1020, 60), Some(&ssh));
+        assert_eq!(shared.fresh(&target(3), 1020, 60), Some(&down));
+    }
+}
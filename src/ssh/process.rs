@@ -0,0 +1,109 @@
+// ABOUTME: Runs a short-lived helper process with a deadline, capturing its output
+// ABOUTME: Shared by hooks and login shell capture so neither can hang the launcher
+
+use anyhow::{Context, Result};
+use std::io::Read;
+use std::process::{Command, Output, Stdio};
+use std::sync::mpsc::{self, Receiver};
+use std::time::{Duration, Instant};
+
+/// How long to wait for output after the process exits
+///
+/// A background job started by the process (`cmd &`) can hold the pipes open long after
+/// the process itself is gone; its output isn't waited for past this.
+const PIPE_GRACE: Duration = Duration::from_millis(200);
+
+/// Run `command` to completion with stdout and stderr captured, killing it after `timeout`
+///
+/// stdin is closed. Output still held open by a background child after the process exits
+/// is dropped rather than waited for.
+pub fn run_with_timeout(command: &mut Command, timeout: Duration) -> Result<Output> {
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    // Drain both pipes on other threads so a chatty process can't block on a full pipe
+    let stdout = read_in_background(child.stdout.take().context("stdout was not captured")?);
+    let stderr = read_in_background(child.stderr.take().context("stderr was not captured")?);
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            anyhow::bail!("timed out after {:?}", timeout);
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    };
+
+    let pipe_deadline = Instant::now() + PIPE_GRACE;
+    Ok(Output {
+        status,
+        stdout: collect(&stdout, pipe_deadline),
+        stderr: collect(&stderr, pipe_deadline),
+    })
+}
+
+fn read_in_background(mut pipe: impl Read + Send + 'static) -> Receiver<Vec<u8>> {
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        let mut output = Vec::new();
+        let _ = pipe.read_to_end(&mut output);
+        let _ = sender.send(output);
+    });
+    receiver
+}
+
+/// A pipe's output if it closes before `deadline`, otherwise nothing
+fn collect(output: &Receiver<Vec<u8>>, deadline: Instant) -> Vec<u8> {
+    let wait = deadline.saturating_duration_since(Instant::now());
+    output.recv_timeout(wait).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sh(script: &str) -> Command {
+        let mut command = Command::new("/bin/sh");
+        command.arg("-c").arg(script);
+        command
+    }
+
+    #[test]
+    fn test_captures_output_and_status() {
+        let output = run_with_timeout(
+            &mut sh("echo out; echo err >&2; exit 3"),
+            Duration::from_secs(10),
+        )
+        .unwrap();
+
+        assert_eq!(output.status.code(), Some(3));
+        assert_eq!(output.stdout, b"out\n");
+        assert_eq!(output.stderr, b"err\n");
+    }
+
+    #[test]
+    fn test_kills_process_after_timeout() {
+        let started = Instant::now();
+        let error = run_with_timeout(&mut sh("sleep 10"), Duration::from_millis(200)).unwrap_err();
+
+        assert!(error.to_string().contains("timed out"));
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_background_job_holding_pipes_does_not_hang() {
+        let started = Instant::now();
+        let output =
+            run_with_timeout(&mut sh("sleep 10 & echo started"), Duration::from_secs(30)).unwrap();
+
+        assert!(output.status.success());
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+}
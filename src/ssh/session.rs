@@ -0,0 +1,207 @@
+// ABOUTME: Remote tmux, screen and zellij sessions that survive dropped connections
+// ABOUTME: Builds the attach-or-create command and wraps it into a transport's argv
+
+use crate::config::{Multiplexer, SessionConfig, Transport};
+use crate::ssh::launcher::shell_join;
+use crate::ssh::parser::HostEntry;
+
+/// The remote argv that attaches to the session, creating it if needed
+pub fn session_argv(session: &SessionConfig, host: &HostEntry) -> Option<Vec<String>> {
+    let name = session_name(&session.name, host);
+    let argv: &[&str] = match session.multiplexer {
+        Multiplexer::None => return None,
+        Multiplexer::Tmux => &["tmux", "new-session", "-A", "-s"],
+        Multiplexer::Screen => &["screen", "-D", "-R", "-S"],
+        Multiplexer::Zellij => &["zellij", "attach", "--create"],
+    };
+
+    let mut argv: Vec<String> = argv.iter().map(|arg| arg.to_string()).collect();
+    argv.push(name);
+    Some(argv)
+}
+
+/// Fill in a session name template, keeping only characters every multiplexer accepts
+pub fn session_name(template: &str, host: &HostEntry) -> String {
+    let user = host.user.as_deref().unwrap_or_default();
+    template
+        .replace("{host}", &host.name)
+        .replace("{user}", user)
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// The remote argv for a command typed by the user, e.g. `journalctl -f -u app`
+///
+/// The command is handed to `sh -c` as a single argument, so pipes and quotes in it
+/// reach the remote shell unchanged whichever transport carries it.
+pub fn user_command_argv(command: &str) -> Vec<String> {
+    vec!["sh".to_string(), "-c".to_string(), command.to_string()]
+}
+
+/// Run a command on the remote host instead of a login shell, with a TTY
+///
+/// `connect_argv` is the transport's argv ending in the host. Returns `None` for the
+/// custom transport, whose command template has no known place for a remote command.
+pub fn with_remote_command(
+    transport: Transport,
+    mut connect_argv: Vec<String>,
+    remote_argv: &[String],
+) -> Option<Vec<String>> {
+    match transport {
+        Transport::Ssh | Transport::Autossh => {
+            // ssh joins its trailing arguments into one string for the remote shell
+            let host_index = connect_argv.len().checked_sub(1)?;
+            connect_argv.insert(host_index, "-t".to_string());
+            connect_argv.push(shell_join(remote_argv));
+        }
+        Transport::Mosh => {
+            // mosh-server runs the command directly, without a shell
+            connect_argv.push("--".to_string());
+            connect_argv.extend(remote_argv.iter().cloned());
+        }
+        Transport::Et => {
+            connect_argv.push("-c".to_string());
+            connect_argv.push(shell_join(remote_argv));
+        }
+        Transport::Custom => return None,
+    }
+    Some(connect_argv)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(multiplexer: Multiplexer) -> SessionConfig {
+        SessionConfig {
+            multiplexer,
+            name: "{host}".to_string(),
+        }
+    }
+
+    fn host(name: &str) -> HostEntry {
+        HostEntry::new(name.to_string(), format!("ssh {}", name))
+    }
+
+    fn ssh_argv(host: &str) -> Vec<String> {
+        vec!["/usr/bin/ssh".to_string(), host.to_string()]
+    }
+
+    #[test]
+    fn test_tmux_session_argv() {
+        let remote = session_argv(&session(Multiplexer::Tmux), &host("db")).unwrap();
+        let argv = with_remote_command(Transport::Ssh, ssh_argv("db"), &remote).unwrap();
+
+        assert_eq!(
+            argv,
+            vec!["/usr/bin/ssh", "-t", "db", "tmux new-session -A -s db"]
+        );
+    }
+
+    #[test]
+    fn test_screen_session_argv() {
+        let remote = session_argv(&session(Multiplexer::Screen), &host("db")).unwrap();
+        let argv = with_remote_command(Transport::Ssh, ssh_argv("db"), &remote).unwrap();
+
+        assert_eq!(argv, vec!["/usr/bin/ssh", "-t", "db", "screen -D -R -S db"]);
+    }
+
+    #[test]
+    fn test_zellij_session_argv() {
+        let remote = session_argv(&session(Multiplexer::Zellij), &host("db")).unwrap();
+        let argv = with_remote_command(Transport::Ssh, ssh_argv("db"), &remote).unwrap();
+
+        assert_eq!(
+            argv,
+            vec!["/usr/bin/ssh", "-t", "db", "zellij attach --create db"]
+        );
+    }
+
+    #[test]
+    fn test_no_multiplexer_means_no_session() {
+        assert_eq!(session_argv(&session(Multiplexer::None), &host("db")), None);
+    }
+
+    #[test]
+    fn test_session_name_template() {
+        let mut web = host("web.prod:1");
+        web.user = Some("deploy".to_string());
+
+        assert_eq!(session_name("{user}-{host}", &web), "deploy-web_prod_1");
+        assert_eq!(session_name("main", &web), "main");
+    }
+
+    #[test]
+    fn test_remote_command_per_transport() {
+        let remote = session_argv(&session(Multiplexer::Tmux), &host("db")).unwrap();
+
+        let autossh = vec!["autossh", "-M", "0", "db"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+        assert_eq!(
+            with_remote_command(Transport::Autossh, autossh, &remote).unwrap(),
+            vec![
+                "autossh",
+                "-M",
+                "0",
+                "-t",
+                "db",
+                "tmux new-session -A -s db"
+            ]
+        );
+
+        let mosh = vec!["mosh".to_string(), "db".to_string()];
+        assert_eq!(
+            with_remote_command(Transport::Mosh, mosh, &remote).unwrap(),
+            vec!["mosh", "db", "--", "tmux", "new-session", "-A", "-s", "db"]
+        );
+
+        let et = vec!["et".to_string(), "db".to_string()];
+        assert_eq!(
+            with_remote_command(Transport::Et, et, &remote).unwrap(),
+            vec!["et", "db", "-c", "tmux new-session -A -s db"]
+        );
+
+        let custom = vec!["tsh".to_string(), "db".to_string()];
+        assert_eq!(
+            with_remote_command(Transport::Custom, custom, &remote),
+            None
+        );
+    }
+
+    #[test]
+    fn test_user_command_stays_one_remote_command() {
+        let remote = user_command_argv("journalctl -f -u app | grep 'failed login'");
+
+        assert_eq!(
+            with_remote_command(Transport::Ssh, ssh_argv("db"), &remote).unwrap(),
+            vec![
+                "/usr/bin/ssh",
+                "-t",
+                "db",
+                "sh -c 'journalctl -f -u app | grep '\\''failed login'\\'''"
+            ]
+        );
+
+        let mosh = vec!["mosh".to_string(), "db".to_string()];
+        assert_eq!(
+            with_remote_command(Transport::Mosh, mosh, &remote).unwrap(),
+            vec![
+                "mosh",
+                "db",
+                "--",
+                "sh",
+                "-c",
+                "journalctl -f -u app | grep 'failed login'"
+            ]
+        );
+    }
+}
@@ -0,0 +1,269 @@
+// ABOUTME: Launch targets (new window, tab or split) and how each known terminal opens them
+// ABOUTME: Maps targets to kitty remote control, wezterm cli, iTerm2 AppleScript and tmux
+
+use std::fmt;
+use std::path::Path;
+
+/// Looked up on `$PATH` like the other terminal programs
+const OSASCRIPT: &str = "osascript";
+
+/// Where a launched connection opens
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LaunchTarget {
+    /// A new terminal window, using the configured terminal args
+    #[default]
+    Window,
+    Tab,
+    Split,
+}
+
+impl fmt::Display for LaunchTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            LaunchTarget::Window => "window",
+            LaunchTarget::Tab => "tab",
+            LaunchTarget::Split => "split",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Terminals that can open tabs or splits in an existing window
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TerminalKind {
+    Kitty,
+    WezTerm,
+    ITerm2,
+    Tmux,
+}
+
+impl TerminalKind {
+    /// Recognize a terminal from its configured program and args
+    pub fn detect(program: &str, args: &[String]) -> Option<Self> {
+        let name = Path::new(program)
+            .file_name()?
+            .to_string_lossy()
+            .to_lowercase();
+        match name.as_str() {
+            "kitty" => Some(TerminalKind::Kitty),
+            "wezterm" | "wezterm-gui" => Some(TerminalKind::WezTerm),
+            "iterm2" => Some(TerminalKind::ITerm2),
+            "tmux" => Some(TerminalKind::Tmux),
+            "osascript" if args.iter().any(|arg| arg.contains("iTerm")) => {
+                Some(TerminalKind::ITerm2)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Program and argument template that open a connection in a tab or split of the terminal
+///
+/// The template uses the same placeholders as the configured `args` and is expanded the
+/// same way, so titles and quoting match a new window. Returns `None` for
+/// [`LaunchTarget::Window`], which uses the configured args, and for targets the terminal
+/// has no mechanism for.
+pub fn target_template(
+    kind: TerminalKind,
+    target: LaunchTarget,
+    program: &str,
+) -> Option<(String, Vec<String>)> {
+    let template = |program: &str, args: &[&str]| {
+        (
+            program.to_string(),
+            args.iter().map(|arg| arg.to_string()).collect(),
+        )
+    };
+
+    let command = match (kind, target) {
+        (_, LaunchTarget::Window) => return None,
+        // Requires `allow_remote_control` in kitty.conf
+        (TerminalKind::Kitty, LaunchTarget::Tab) => template(
+            program,
+            &[
+                "@",
+                "launch",
+                "--type=tab",
+                "--title",
+                "{title}",
+                "{ssh_argv}",
+            ],
+        ),
+        (TerminalKind::Kitty, LaunchTarget::Split) => template(
+            program,
+            &[
+                "@",
+                "launch",
+                "--type=window",
+                "--location=vsplit",
+                "--title",
+                "{title}",
+                "{ssh_argv}",
+            ],
+        ),
+        (TerminalKind::WezTerm, LaunchTarget::Tab) => {
+            template(program, &["cli", "spawn", "--", "{ssh_argv}"])
+        }
+        (TerminalKind::WezTerm, LaunchTarget::Split) => {
+            template(program, &["cli", "split-pane", "--", "{ssh_argv}"])
+        }
+        (TerminalKind::ITerm2, LaunchTarget::Tab) => template(
+            OSASCRIPT,
+            &[
+                "-e",
+                "tell application \"iTerm2\" to tell current window to create tab with default profile command \"{ssh_command}\"",
+            ],
+        ),
+        (TerminalKind::ITerm2, LaunchTarget::Split) => template(
+            OSASCRIPT,
+            &[
+                "-e",
+                "tell application \"iTerm2\" to tell current session of current window to split vertically with default profile command \"{ssh_command}\"",
+            ],
+        ),
+        // tmux windows are its tabs; both take a shell command string
+        (TerminalKind::Tmux, LaunchTarget::Tab) => {
+            template(program, &["new-window", "-n", "{title}", "{ssh_command}"])
+        }
+        (TerminalKind::Tmux, LaunchTarget::Split) => {
+            template(program, &["split-window", "{ssh_command}"])
+        }
+    };
+    Some(command)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ssh::launcher::build_terminal_args;
+    use crate::ssh::template::Placeholders;
+
+    fn ssh_argv() -> Vec<String> {
+        vec!["/usr/bin/ssh".to_string(), "web 1".to_string()]
+    }
+
+    /// The target's template expanded for host "web 1"
+    fn command(kind: TerminalKind, target: LaunchTarget, program: &str) -> (String, Vec<String>) {
+        let (program, template) = target_template(kind, target, program).unwrap();
+        let placeholders = Placeholders {
+            title: "web 1".to_string(),
+            ssh_argv: ssh_argv(),
+            ..Default::default()
+        };
+        let args = build_terminal_args(&program, &template, &placeholders).unwrap();
+        (program, args)
+    }
+
+    #[test]
+    fn test_detect_terminal_kind() {
+        let no_args: Vec<String> = Vec::new();
+        assert_eq!(
+            TerminalKind::detect("/Applications/kitty.app/Contents/MacOS/kitty", &no_args),
+            Some(TerminalKind::Kitty)
+        );
+        assert_eq!(
+            TerminalKind::detect("wezterm", &no_args),
+            Some(TerminalKind::WezTerm)
+        );
+        assert_eq!(
+            TerminalKind::detect("/Applications/iTerm.app/Contents/MacOS/iTerm2", &no_args),
+            Some(TerminalKind::ITerm2)
+        );
+        assert_eq!(
+            TerminalKind::detect("/usr/bin/tmux", &no_args),
+            Some(TerminalKind::Tmux)
+        );
+        assert_eq!(
+            TerminalKind::detect(
+                "/usr/bin/osascript",
+                &["-e".to_string(), "tell application \"iTerm2\"".to_string()]
+            ),
+            Some(TerminalKind::ITerm2)
+        );
+        assert_eq!(TerminalKind::detect("/usr/bin/osascript", &no_args), None);
+        assert_eq!(TerminalKind::detect("ghostty", &no_args), None);
+    }
+
+    #[test]
+    fn test_kitty_targets() {
+        assert_eq!(
+            command(TerminalKind::Kitty, LaunchTarget::Tab, "kitty"),
+            (
+                "kitty".to_string(),
+                vec![
+                    "@",
+                    "launch",
+                    "--type=tab",
+                    "--title",
+                    "web 1",
+                    "/usr/bin/ssh",
+                    "web 1"
+                ]
+                .into_iter()
+                .map(String::from)
+                .collect()
+            )
+        );
+        assert_eq!(
+            command(TerminalKind::Kitty, LaunchTarget::Split, "kitty").1,
+            vec![
+                "@",
+                "launch",
+                "--type=window",
+                "--location=vsplit",
+                "--title",
+                "web 1",
+                "/usr/bin/ssh",
+                "web 1"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_wezterm_targets() {
+        assert_eq!(
+            command(TerminalKind::WezTerm, LaunchTarget::Tab, "wezterm").1,
+            vec!["cli", "spawn", "--", "/usr/bin/ssh", "web 1"]
+        );
+        assert_eq!(
+            command(TerminalKind::WezTerm, LaunchTarget::Split, "wezterm").1,
+            vec!["cli", "split-pane", "--", "/usr/bin/ssh", "web 1"]
+        );
+    }
+
+    #[test]
+    fn test_iterm2_targets() {
+        let (program, args) = command(TerminalKind::ITerm2, LaunchTarget::Tab, "iTerm2");
+        assert_eq!(program, "osascript");
+        assert_eq!(
+            args,
+            vec![
+                "-e",
+                "tell application \"iTerm2\" to tell current window to create tab with default profile command \"/usr/bin/ssh 'web 1'\""
+            ]
+        );
+
+        let (_, args) = command(TerminalKind::ITerm2, LaunchTarget::Split, "iTerm2");
+        assert!(args[1].contains("split vertically with default profile command"));
+    }
+
+    #[test]
+    fn test_tmux_targets() {
+        assert_eq!(
+            command(TerminalKind::Tmux, LaunchTarget::Tab, "tmux").1,
+            vec!["new-window", "-n", "web 1", "/usr/bin/ssh 'web 1'"]
+        );
+        assert_eq!(
+            command(TerminalKind::Tmux, LaunchTarget::Split, "tmux").1,
+            vec!["split-window", "/usr/bin/ssh 'web 1'"]
+        );
+    }
+
+    #[test]
+    fn test_window_uses_configured_args() {
+        assert_eq!(
+            target_template(TerminalKind::Kitty, LaunchTarget::Window, "kitty"),
+            None
+        );
+    }
+}
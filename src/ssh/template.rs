@@ -0,0 +1,501 @@
+// ABOUTME: Placeholder language for terminal args: {alias}, {user}, {ssh_command} and conditional sections
+// ABOUTME: Parses templates with error positions for validation and expands them per launch
+
+use crate::ssh::launcher::{QuoteContext, applescript_escape, shell_join, shell_quote};
+use std::fmt;
+
+/// Every placeholder terminal args may use
+pub const PLACEHOLDERS: &[&str] = &[
+    "host",
+    "alias",
+    "user",
+    "port",
+    "title",
+    "tag",
+    "ssh_command",
+    "ssh_argv",
+];
+
+/// A parse error, with the 1-based character column it was found at
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TemplateError {
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at column {}", self.message, self.column)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Text(String),
+    Placeholder {
+        name: String,
+        column: usize,
+    },
+    /// `{?name}body{/name}`, kept only when `name` has a non-empty value
+    Section {
+        name: String,
+        body: Vec<Segment>,
+    },
+}
+
+/// Values for one launch
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Placeholders {
+    /// `HostName` from ssh config, or the alias
+    pub host: String,
+    pub alias: String,
+    pub user: Option<String>,
+    pub port: Option<u16>,
+    pub title: String,
+    /// The host's first tag
+    pub tag: Option<String>,
+    pub ssh_argv: Vec<String>,
+    /// `[terminal.env]` and `working_directory`, written into an AppleScript `{ssh_command}`
+    ///
+    /// The terminal app, not osascript, starts that shell, so it never sees the variables
+    /// or directory set on the osascript process.
+    pub env: Vec<(String, String)>,
+    pub working_dir: Option<String>,
+}
+
+impl Placeholders {
+    /// The raw value of a placeholder; absent and empty values leave sections out
+    fn value(&self, name: &str) -> Option<String> {
+        let value = match name {
+            "host" => Some(self.host.clone()),
+            "alias" => Some(self.alias.clone()),
+            "user" => self.user.clone(),
+            "port" => self.port.map(|port| port.to_string()),
+            "title" => Some(self.title.clone()),
+            "tag" => self.tag.clone(),
+            _ => None,
+        };
+        value.filter(|value| !value.is_empty())
+    }
+
+    /// The connection as one command string quoted for `context`
+    fn ssh_command(&self, context: QuoteContext) -> String {
+        if context != QuoteContext::AppleScript
+            || (self.env.is_empty() && self.working_dir.is_none())
+        {
+            return context.render(&self.ssh_argv);
+        }
+        let mut argv = Vec::new();
+        if !self.env.is_empty() {
+            argv.push("env".to_string());
+            argv.extend(
+                self.env
+                    .iter()
+                    .map(|(name, value)| format!("{}={}", name, value)),
+            );
+        }
+        argv.extend(self.ssh_argv.iter().cloned());
+        let mut command = shell_join(&argv);
+        if let Some(dir) = &self.working_dir {
+            command = format!("cd {} && {}", shell_quote(dir), command);
+        }
+        applescript_escape(&command)
+    }
+}
+
+/// Separates argv elements while a plain argument is rendered
+///
+/// Arguments can't contain NUL, so no placeholder value or literal text clashes with it.
+const ARG_BREAK: char = '\0';
+
+/// Whether `name` could be meant as a placeholder, e.g. `ssh_comand` but not `0, 0`
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Parse a template, rejecting unknown placeholders and unbalanced sections
+///
+/// A brace that can't name a placeholder, such as an AppleScript list `{0, 0, 800, 600}`,
+/// or a shell variable's `${HOME}`, is kept as literal text.
+fn parse(template: &str) -> Result<Vec<Segment>, TemplateError> {
+    let chars: Vec<char> = template.chars().collect();
+    // Open sections: name, column and the segments before the section
+    let mut open: Vec<(String, usize, Vec<Segment>)> = Vec::new();
+    let mut segments = Vec::new();
+    let mut text = String::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let column = i + 1;
+        let tag = (chars[i] == '{' && (i == 0 || chars[i - 1] != '$'))
+            .then(|| chars[i + 1..].iter().position(|&c| c == '}'))
+            .flatten()
+            .map(|length| chars[i + 1..i + 1 + length].iter().collect::<String>());
+        let Some(tag) = tag else {
+            text.push(chars[i]);
+            i += 1;
+            continue;
+        };
+        let (kind, name) = match tag.chars().next() {
+            Some(kind @ ('?' | '/')) => (Some(kind), &tag[1..]),
+            _ => (None, tag.as_str()),
+        };
+        if !is_identifier(name) {
+            text.push('{');
+            i += 1;
+            continue;
+        }
+        if !PLACEHOLDERS.contains(&name) {
+            return Err(TemplateError {
+                column,
+                message: format!(
+                    "Unknown placeholder '{{{}}}' (known: {})",
+                    tag,
+                    PLACEHOLDERS.join(", ")
+                ),
+            });
+        }
+        i += tag.chars().count() + 2;
+
+        if !text.is_empty() {
+            segments.push(Segment::Text(std::mem::take(&mut text)));
+        }
+        match kind {
+            Some('?') => {
+                open.push((name.to_string(), column, std::mem::take(&mut segments)));
+            }
+            Some(_) => match open.pop() {
+                Some((open_name, _, outer)) if open_name == name => {
+                    let body = std::mem::replace(&mut segments, outer);
+                    segments.push(Segment::Section {
+                        name: open_name,
+                        body,
+                    });
+                }
+                Some((open_name, _, _)) => {
+                    return Err(TemplateError {
+                        column,
+                        message: format!("'{{/{}}}' closes '{{?{}}}'", name, open_name),
+                    });
+                }
+                None => {
+                    return Err(TemplateError {
+                        column,
+                        message: format!("'{{/{}}}' has no matching '{{?{}}}'", name, name),
+                    });
+                }
+            },
+            None => segments.push(Segment::Placeholder {
+                name: name.to_string(),
+                column,
+            }),
+        }
+    }
+
+    if let Some((name, column, _)) = open.pop() {
+        return Err(TemplateError {
+            column,
+            message: format!("'{{?{}}}' is never closed with '{{/{}}}'", name, name),
+        });
+    }
+    if !text.is_empty() {
+        segments.push(Segment::Text(text));
+    }
+    Ok(segments)
+}
+
+/// Check one terminal argument template
+///
+/// `{ssh_argv}` expands to several arguments, so it has to be an argument on its own.
+pub fn check_arg(arg: &str) -> Result<(), TemplateError> {
+    let segments = parse(arg)?;
+    if arg != "{ssh_argv}" {
+        check_no_ssh_argv(&segments)?;
+    }
+    Ok(())
+}
+
+fn check_no_ssh_argv(segments: &[Segment]) -> Result<(), TemplateError> {
+    for segment in segments {
+        match segment {
+            Segment::Placeholder { name, column } if name == "ssh_argv" => {
+                return Err(TemplateError {
+                    column: *column,
+                    message:
+                        "'{ssh_argv}' must be a whole argument; use '{ssh_command}' inside text"
+                            .to_string(),
+                });
+            }
+            Segment::Section { body, .. } => check_no_ssh_argv(body)?,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Expand one terminal argument template
+///
+/// An argument that is exactly `{ssh_argv}` becomes the argv elements themselves. Elsewhere,
+/// values are escaped for the argument's `QuoteContext`: unchanged in a plain argument,
+/// shell-quoted in a command line and also escaped for AppleScript string literals.
+/// In a plain argument, whitespace written inside a section separates arguments, so
+/// `{?user}-l {user}{/user}` becomes `-l` and `deploy`; values are never split.
+/// An argument whose placeholders and sections expand to nothing is left out, so a
+/// whole-argument section like `{?tag}--class={tag}{/tag}` doesn't leave an empty argument.
+pub fn expand_arg(
+    program: &str,
+    arg: &str,
+    placeholders: &Placeholders,
+) -> Result<Vec<String>, TemplateError> {
+    if arg == "{ssh_argv}" {
+        return Ok(placeholders.ssh_argv.clone());
+    }
+    let segments = parse(arg)?;
+    check_no_ssh_argv(&segments)?;
+    let context = QuoteContext::for_arg(program, arg);
+    let mut expanded = String::new();
+    render(&segments, context, placeholders, false, &mut expanded);
+    if arg.is_empty() {
+        return Ok(vec![expanded]);
+    }
+    Ok(expanded
+        .split(ARG_BREAK)
+        .filter(|arg| !arg.is_empty())
+        .map(String::from)
+        .collect())
+}
+
+fn render(
+    segments: &[Segment],
+    context: QuoteContext,
+    placeholders: &Placeholders,
+    in_section: bool,
+    out: &mut String,
+) {
+    for segment in segments {
+        match segment {
+            Segment::Text(text) if in_section && context == QuoteContext::Argument => {
+                out.extend(
+                    text.chars()
+                        .map(|c| if c.is_whitespace() { ARG_BREAK } else { c }),
+                );
+            }
+            Segment::Text(text) => out.push_str(text),
+            Segment::Placeholder { name, .. } if name == "ssh_command" => {
+                out.push_str(&placeholders.ssh_command(context));
+            }
+            Segment::Placeholder { name, .. } => {
+                if let Some(value) = placeholders.value(name) {
+                    out.push_str(&context.escape(&value));
+                }
+            }
+            Segment::Section { name, body } => {
+                let present = match name.as_str() {
+                    "ssh_command" | "ssh_argv" => !placeholders.ssh_argv.is_empty(),
+                    name => placeholders.value(name).is_some(),
+                };
+                if present {
+                    render(body, context, placeholders, true, out);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ssh::launcher::build_terminal_args;
+
+    fn placeholders() -> Placeholders {
+        Placeholders {
+            host: "10.0.0.5".to_string(),
+            alias: "web".to_string(),
+            user: Some("deploy".to_string()),
+            port: Some(2222),
+            title: "web: tail -f 'app.log'".to_string(),
+            tag: Some("prod".to_string()),
+            ssh_argv: vec!["ssh".to_string(), "web".to_string()],
+            ..Default::default()
+        }
+    }
+
+    fn expand(program: &str, arg: &str, placeholders: &Placeholders) -> String {
+        let mut expanded = expand_arg(program, arg, placeholders).unwrap();
+        assert_eq!(expanded.len(), 1);
+        expanded.remove(0)
+    }
+
+    #[test]
+    fn test_plain_argument_values_are_verbatim() {
+        assert_eq!(
+            expand("kitty", "--title={title} [{tag}]", &placeholders()),
+            "--title=web: tail -f 'app.log' [prod]"
+        );
+        assert_eq!(
+            expand("kitty", "{user}@{host}:{port} ({alias})", &placeholders()),
+            "deploy@10.0.0.5:2222 (web)"
+        );
+    }
+
+    #[test]
+    fn test_command_line_values_are_quoted() {
+        assert_eq!(
+            expand(
+                "alacritty",
+                "printf '%s' {title}; {ssh_command}",
+                &placeholders()
+            ),
+            "printf '%s' 'web: tail -f '\\''app.log'\\'''; ssh web"
+        );
+        assert_eq!(
+            expand(
+                "/usr/bin/osascript",
+                "tell app \"Terminal\" to do script \"echo {title}\"",
+                &placeholders()
+            ),
+            "tell app \"Terminal\" to do script \"echo 'web: tail -f '\\\\''app.log'\\\\'''\""
+        );
+    }
+
+    #[test]
+    fn test_sections_need_a_value() {
+        let template = "{?user}-l {user} {/user}{?port}-p {port} {/port}{alias}";
+        assert_eq!(
+            expand_arg("kitty", template, &placeholders()).unwrap(),
+            vec!["-l", "deploy", "-p", "2222", "web"]
+        );
+
+        let bare = Placeholders {
+            user: None,
+            port: None,
+            tag: Some(String::new()),
+            ..placeholders()
+        };
+        assert_eq!(expand("kitty", template, &bare), "web");
+        assert!(
+            expand_arg("kitty", "{?tag}tab-{tag}{/tag}", &bare)
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(expand_arg("kitty", "", &bare).unwrap(), vec![""]);
+    }
+
+    #[test]
+    fn test_section_words_are_separate_arguments() {
+        let args = vec![
+            "{?user}-l {user}{/user}".to_string(),
+            "{?tag}--title {title}{/tag}".to_string(),
+            "{alias}".to_string(),
+        ];
+        assert_eq!(
+            build_terminal_args("ssh", &args, &placeholders()).unwrap(),
+            vec!["-l", "deploy", "--title", "web: tail -f 'app.log'", "web"]
+        );
+
+        // Spaces outside a section stay inside the one argument
+        assert_eq!(
+            expand("kitty", "--title=ssh to {alias}", &placeholders()),
+            "--title=ssh to web"
+        );
+
+        // A command line keeps its spaces; the shell splits it
+        assert_eq!(
+            expand(
+                "alacritty",
+                "{?user}ssh -l {user} {/user}{ssh_command}",
+                &placeholders()
+            ),
+            "ssh -l deploy ssh web"
+        );
+    }
+
+    #[test]
+    fn test_applescript_command_carries_env_and_directory() {
+        let placeholders = Placeholders {
+            env: vec![("SSH_AUTH_SOCK".to_string(), "/run/my agent".to_string())],
+            working_dir: Some("/Users/me/src".to_string()),
+            ..placeholders()
+        };
+        assert_eq!(
+            expand(
+                "/usr/bin/osascript",
+                "tell app \"Terminal\" to do script \"{ssh_command}\"",
+                &placeholders
+            ),
+            "tell app \"Terminal\" to do script \"cd /Users/me/src && env 'SSH_AUTH_SOCK=/run/my agent' ssh web\""
+        );
+        // Terminals started directly inherit them from the process instead
+        assert_eq!(
+            expand("alacritty", "{ssh_command}", &placeholders),
+            "ssh web"
+        );
+    }
+
+    #[test]
+    fn test_unknown_braces_are_literal() {
+        assert_eq!(
+            expand(
+                "sh",
+                "echo ${HOME} {alias}, lone } and { stay",
+                &placeholders()
+            ),
+            "echo ${HOME} web, lone } and { stay"
+        );
+        assert_eq!(
+            expand(
+                "/usr/bin/osascript",
+                "tell app \"Terminal\" to set bounds of front window to {0, 0, 800, 600}",
+                &placeholders()
+            ),
+            "tell app \"Terminal\" to set bounds of front window to {0, 0, 800, 600}"
+        );
+        assert!(check_arg("{ } {1,2} {?} {/ x}").is_ok());
+        assert_eq!(expand("kitty", "{{alias}}", &placeholders()), "{web}");
+    }
+
+    #[test]
+    fn test_ssh_argv_is_whole_argument() {
+        assert_eq!(
+            expand_arg("kitty", "{ssh_argv}", &placeholders()).unwrap(),
+            vec!["ssh", "web"]
+        );
+
+        let error = check_arg("--cmd={ssh_argv}").unwrap_err();
+        assert_eq!(error.column, 7);
+        assert!(error.message.contains("must be a whole argument"));
+        assert!(check_arg("{ssh_argv }").is_ok());
+    }
+
+    #[test]
+    fn test_errors_report_column() {
+        let error = check_arg("--title={hots}").unwrap_err();
+        assert_eq!(error.column, 9);
+        assert!(
+            error
+                .to_string()
+                .starts_with("Unknown placeholder '{hots}'")
+        );
+        assert!(error.to_string().ends_with("at column 9"));
+        assert_eq!(check_arg("{ssh_comand}").unwrap_err().column, 1);
+        assert_eq!(check_arg("-l {?usr}x{/usr}").unwrap_err().column, 4);
+
+        let error = check_arg("--title={?user}").unwrap_err();
+        assert_eq!(error.column, 9);
+        assert!(error.to_string().starts_with("'{?user}' is never closed"));
+
+        assert_eq!(check_arg("{?user}-l {user}").unwrap_err().column, 1);
+        assert_eq!(check_arg("ab{/user}").unwrap_err().column, 3);
+        assert_eq!(
+            check_arg("{?user}{?port}{/user}{/port}")
+                .unwrap_err()
+                .column,
+            15
+        );
+        assert_eq!(check_arg("é {/alias}").unwrap_err().column, 3);
+    }
+}
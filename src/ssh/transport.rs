@@ -0,0 +1,165 @@
+// ABOUTME: Connection commands for each transport: ssh, mosh, Eternal Terminal, autossh or custom
+// ABOUTME: Builds the argv that runs inside the terminal to connect to a host
+
+use crate::config::{Config, Transport};
+use crate::ssh::launcher::split_command_line;
+use crate::ssh::parser::HostEntry;
+use anyhow::Result;
+
+pub const DEFAULT_SSH_PORT: u16 = 22;
+
+/// The argv that connects to a host with the given transport
+///
+/// Built-in transports connect to the host alias so ssh config settings still apply; ssh
+/// runs the host's connection string, with a leading `ssh` replaced by the configured
+/// binary. Custom templates get `{host}` (the resolved `HostName`, or the alias), `{user}`
+/// (falling back to the local user) and `{port}` (falling back to 22).
+pub fn connect_argv(
+    config: &Config,
+    transport: Transport,
+    host: &HostEntry,
+) -> Result<Vec<String>> {
+    let alias = host.name.clone();
+    let argv = match transport {
+        Transport::Ssh => ssh_connect_argv(config, host),
+        Transport::Mosh => vec![config.transport.mosh_binary.clone(), alias],
+        Transport::Et => vec![config.transport.et_binary.clone(), alias],
+        // autossh needs a monitoring port; 0 relies on ssh's ServerAlive checks instead
+        Transport::Autossh => vec![
+            config.transport.autossh_binary.clone(),
+            "-M".to_string(),
+            "0".to_string(),
+            alias,
+        ],
+        Transport::Custom => {
+            let Some(template) = config.transport.custom.as_deref() else {
+                anyhow::bail!("The custom transport has no command template in [transport]");
+            };
+            expand_template(template, host)
+        }
+    };
+    Ok(argv)
+}
+
+/// The host's connection string as argv, run with the configured ssh binary
+fn ssh_connect_argv(config: &Config, host: &HostEntry) -> Vec<String> {
+    let mut argv = split_command_line(&host.connection_string);
+    match argv.first().map(String::as_str) {
+        None => vec![config.ssh.ssh_binary.clone(), host.name.clone()],
+        Some("ssh") => {
+            argv[0] = config.ssh.ssh_binary.clone();
+            argv
+        }
+        Some(_) => argv,
+    }
+}
+
+fn expand_template(template: &str, host: &HostEntry) -> Vec<String> {
+    let hostname = host.hostname.as_deref().unwrap_or(&host.name);
+    let user = host.user.clone().unwrap_or_else(local_user);
+    let port = host.port.unwrap_or(DEFAULT_SSH_PORT).to_string();
+
+    // Split before substituting so values with spaces stay a single argument
+    split_command_line(template)
+        .into_iter()
+        .map(|word| {
+            word.replace("{host}", hostname)
+                .replace("{user}", &user)
+                .replace("{port}", &port)
+        })
+        .collect()
+}
+
+/// The local account name, used when a host has no `User`
+pub fn local_user() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_host() -> HostEntry {
+        let mut host = HostEntry::new("db".to_string(), "ssh db".to_string());
+        host.hostname = Some("10.2.0.15".to_string());
+        host.user = Some("postgres".to_string());
+        host.port = Some(2222);
+        host
+    }
+
+    #[test]
+    fn test_builtin_transports_connect_to_alias() {
+        let config = Config::default();
+        let host = test_host();
+
+        let argv = |transport| connect_argv(&config, transport, &host).unwrap();
+
+        assert_eq!(argv(Transport::Ssh), vec!["/usr/bin/ssh", "db"]);
+        assert_eq!(argv(Transport::Mosh), vec!["mosh", "db"]);
+        assert_eq!(argv(Transport::Et), vec!["et", "db"]);
+        assert_eq!(argv(Transport::Autossh), vec!["autossh", "-M", "0", "db"]);
+    }
+
+    #[test]
+    fn test_ssh_uses_connection_string() {
+        let mut config = Config::default();
+        config.ssh.ssh_binary = "/opt/homebrew/bin/ssh".to_string();
+        let mut host = test_host();
+        host.connection_string = "ssh -p 2222 -o 'ProxyJump bastion' postgres@db".to_string();
+
+        let argv = connect_argv(&config, Transport::Ssh, &host).unwrap();
+        assert_eq!(
+            argv,
+            vec![
+                "/opt/homebrew/bin/ssh",
+                "-p",
+                "2222",
+                "-o",
+                "ProxyJump bastion",
+                "postgres@db"
+            ]
+        );
+
+        // A connection string naming its own program runs as written
+        host.connection_string = "/usr/local/bin/ssh db".to_string();
+        let argv = connect_argv(&config, Transport::Ssh, &host).unwrap();
+        assert_eq!(argv, vec!["/usr/local/bin/ssh", "db"]);
+
+        host.connection_string = String::new();
+        let argv = connect_argv(&config, Transport::Ssh, &host).unwrap();
+        assert_eq!(argv, vec!["/opt/homebrew/bin/ssh", "db"]);
+    }
+
+    #[test]
+    fn test_custom_template_fills_placeholders() {
+        let mut config = Config::default();
+        config.transport.custom = Some("tsh ssh --port {port} '{user}@{host}'".to_string());
+
+        let argv = connect_argv(&config, Transport::Custom, &test_host()).unwrap();
+
+        assert_eq!(
+            argv,
+            vec!["tsh", "ssh", "--port", "2222", "postgres@10.2.0.15"]
+        );
+    }
+
+    #[test]
+    fn test_custom_template_defaults() {
+        let mut config = Config::default();
+        config.transport.custom = Some("connect {host} {port}".to_string());
+        let host = HostEntry::new("web".to_string(), "ssh web".to_string());
+
+        let argv = connect_argv(&config, Transport::Custom, &host).unwrap();
+
+        assert_eq!(argv, vec!["connect", "web", "22"]);
+    }
+
+    #[test]
+    fn test_custom_transport_without_template_fails() {
+        let config = Config::default();
+
+        assert!(connect_argv(&config, Transport::Custom, &test_host()).is_err());
+    }
+}
@@ -0,0 +1,14 @@
+// ABOUTME: Fixtures shared by tests across modules
+// ABOUTME: Writes the stub shell scripts that stand in for ssh, terminals and other helpers
+
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+/// Write an executable `/bin/sh` script running `body` to `dir/name` and return its path
+pub fn write_script(dir: &Path, name: &str, body: &str) -> PathBuf {
+    let path = dir.join(name);
+    fs::write(&path, format!("#!/bin/sh\n{}\n", body)).unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+    path
+}
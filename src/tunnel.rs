@@ -0,0 +1,644 @@
+// ABOUTME: Background tunnels supervised by the menubar process, outliving the launcher window
+// ABOUTME: Persists the wanted tunnels, spawns their ssh -N processes and restarts them with backoff
+
+use crate::Logger;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// How often the supervisor thread re-reads the store and checks its children
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A tunnel the user asked for, persisted so it is restored when the menubar app starts
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct TunnelSpec {
+    pub host: String,
+    /// Names of the `[forwards]` presets the tunnel holds open
+    pub forwards: Vec<String>,
+    /// The full connection argv, e.g. `["/usr/bin/ssh", "-N", "-L", "5432:localhost:5432", "db"]`
+    pub argv: Vec<String>,
+}
+
+impl TunnelSpec {
+    /// Identifies the tunnel; starting the same forwards on the same host again replaces it
+    pub fn id(&self) -> String {
+        format!("{}:{}", self.host, self.forwards.join("+"))
+    }
+
+    pub fn label(&self) -> String {
+        format!("{} ({})", self.host, self.forwards.join(", "))
+    }
+}
+
+/// The wanted tunnels, shared between the launcher (which adds them) and the menubar (which runs them)
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct TunnelStore {
+    #[serde(default)]
+    pub tunnels: Vec<TunnelSpec>,
+}
+
+impl TunnelStore {
+    pub fn default_path() -> Result<PathBuf> {
+        let config_dir = dirs::config_dir().context("Failed to determine config directory")?;
+        Ok(config_dir.join("trident").join("tunnels.toml"))
+    }
+
+    /// Load the store, treating a missing file as no tunnels
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read tunnels file: {}", path.display()))?;
+        toml::from_str(&content)
+            .with_context(|| format!("Failed to parse tunnels file: {}", path.display()))
+    }
+
+    /// Replace the file in one step, so a concurrent `load` sees the old or new store, never half
+    pub fn save(&self, path: &Path) -> Result<()> {
+        create_parent(path)?;
+        let content = toml::to_string(self).context("Failed to serialize tunnels")?;
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let temp_path = path.with_extension(format!(
+            "toml.{}-{}.tmp",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::write(&temp_path, content)
+            .with_context(|| format!("Failed to write tunnels file: {}", temp_path.display()))?;
+        fs::rename(&temp_path, path).with_context(|| {
+            let _ = fs::remove_file(&temp_path);
+            format!("Failed to replace tunnels file: {}", path.display())
+        })
+    }
+
+    /// Load, change and save the store in one go
+    ///
+    /// Both processes write the file, so changes are made against its current contents
+    /// rather than a copy loaded earlier, while holding a lock on `<path>.lock` so one
+    /// process's change can't overwrite the other's.
+    pub fn modify<T>(path: &Path, change: impl FnOnce(&mut Self) -> T) -> Result<T> {
+        create_parent(path)?;
+        let lock_path = path.with_extension("toml.lock");
+        let lock = fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&lock_path)
+            .with_context(|| format!("Failed to open {}", lock_path.display()))?;
+        lock.lock()
+            .with_context(|| format!("Failed to lock {}", lock_path.display()))?;
+
+        let mut store = Self::load(path)?;
+        let result = change(&mut store);
+        store.save(path)?;
+        // Closing the file releases the lock
+        drop(lock);
+        Ok(result)
+    }
+
+    /// Add a tunnel, replacing one with the same id
+    pub fn add(&mut self, spec: TunnelSpec) {
+        let id = spec.id();
+        match self.tunnels.iter_mut().find(|tunnel| tunnel.id() == id) {
+            Some(existing) => *existing = spec,
+            None => self.tunnels.push(spec),
+        }
+    }
+
+    /// Remove a tunnel by id, returning whether it was there
+    pub fn remove(&mut self, id: &str) -> bool {
+        let before = self.tunnels.len();
+        self.tunnels.retain(|tunnel| tunnel.id() != id);
+        self.tunnels.len() != before
+    }
+}
+
+fn create_parent(path: &Path) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create tunnels directory: {}", parent.display()))?;
+    }
+    Ok(())
+}
+
+/// Delay before restarting a tunnel that exited: doubling from `initial` up to `max`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Backoff {
+    pub initial: Duration,
+    pub max: Duration,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            initial: Duration::from_secs(1),
+            max: Duration::from_secs(60),
+        }
+    }
+}
+
+impl Backoff {
+    /// The delay after `failures` consecutive failures (at least one)
+    pub fn delay(&self, failures: u32) -> Duration {
+        let factor = 1u32
+            .checked_shl(failures.saturating_sub(1))
+            .unwrap_or(u32::MAX);
+        self.initial.saturating_mul(factor).min(self.max)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TunnelState {
+    Running {
+        pid: u32,
+    },
+    /// The process exited or failed to start; it is started again at `at`
+    Restarting {
+        at: Instant,
+        reason: String,
+    },
+}
+
+/// A supervised tunnel as shown in the status menu
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TunnelStatus {
+    pub id: String,
+    pub label: String,
+    pub state: TunnelState,
+}
+
+impl TunnelStatus {
+    /// Menu text, e.g. `db (postgres): running, pid 4242`
+    pub fn describe(&self, now: Instant) -> String {
+        match &self.state {
+            TunnelState::Running { pid } => format!("{}: running, pid {}", self.label, pid),
+            TunnelState::Restarting { at, reason } => format!(
+                "{}: restarting in {}s ({})",
+                self.label,
+                at.saturating_duration_since(now).as_secs(),
+                reason
+            ),
+        }
+    }
+}
+
+struct Tunnel {
+    spec: TunnelSpec,
+    child: Option<Child>,
+    started: Instant,
+    /// Consecutive failures, reset once the tunnel stays up for `backoff.max`
+    failures: u32,
+    state: TunnelState,
+}
+
+/// Owns the tunnel processes and keeps them matching the store
+pub struct TunnelSupervisor {
+    tunnels: BTreeMap<String, Tunnel>,
+    backoff: Backoff,
+    /// Set on quit, so the supervisor thread doesn't start tunnels again before the process exits
+    shut_down: bool,
+}
+
+impl TunnelSupervisor {
+    pub fn new(backoff: Backoff) -> Self {
+        Self {
+            tunnels: BTreeMap::new(),
+            backoff,
+            shut_down: false,
+        }
+    }
+
+    /// Start tunnels new to `specs`, restart changed ones and stop those no longer listed
+    pub fn sync(&mut self, specs: &[TunnelSpec], now: Instant) {
+        if self.shut_down {
+            return;
+        }
+        let wanted: BTreeMap<String, &TunnelSpec> =
+            specs.iter().map(|spec| (spec.id(), spec)).collect();
+
+        let stale: Vec<String> = self
+            .tunnels
+            .iter()
+            .filter(|(id, tunnel)| wanted.get(*id) != Some(&&tunnel.spec))
+            .map(|(id, _)| id.clone())
+            .collect();
+        for id in stale {
+            self.stop(&id);
+        }
+
+        for (id, spec) in wanted {
+            if !self.tunnels.contains_key(&id) {
+                let tunnel = self.start((*spec).clone(), 0, now);
+                self.tunnels.insert(id, tunnel);
+            }
+        }
+    }
+
+    /// Notice exited tunnels and restart those whose backoff has elapsed
+    pub fn poll(&mut self, now: Instant) {
+        if self.shut_down {
+            return;
+        }
+        let backoff = self.backoff;
+        for tunnel in self.tunnels.values_mut() {
+            if let Some(child) = tunnel.child.as_mut() {
+                let reason = match child.try_wait() {
+                    Ok(None) => continue,
+                    Ok(Some(status)) => status.to_string(),
+                    Err(e) => e.to_string(),
+                };
+                tunnel.child = None;
+                if now.saturating_duration_since(tunnel.started) >= backoff.max {
+                    tunnel.failures = 0;
+                }
+                tunnel.failures += 1;
+                let delay = backoff.delay(tunnel.failures);
+                Logger::warn(&format!(
+                    "Tunnel {} exited with {}; restarting in {}s",
+                    tunnel.spec.label(),
+                    reason,
+                    delay.as_secs()
+                ));
+                tunnel.state = TunnelState::Restarting {
+                    at: now + delay,
+                    reason,
+                };
+            }
+
+            if let TunnelState::Restarting { at, .. } = tunnel.state
+                && now >= at
+            {
+                let spec = tunnel.spec.clone();
+                *tunnel = Self::spawn(spec, tunnel.failures, backoff, now);
+            }
+        }
+    }
+
+    /// Stop a tunnel's process and stop supervising it, returning whether it was running
+    pub fn stop(&mut self, id: &str) -> bool {
+        let Some(mut tunnel) = self.tunnels.remove(id) else {
+            return false;
+        };
+        if let Some(mut child) = tunnel.child.take() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+        Logger::info(&format!("Stopped tunnel {}", tunnel.spec.label()));
+        true
+    }
+
+    pub fn stop_all(&mut self) {
+        let ids: Vec<String> = self.tunnels.keys().cloned().collect();
+        for id in ids {
+            self.stop(&id);
+        }
+    }
+
+    /// Stop every tunnel for good, leaving the store alone so they are restored next time
+    pub fn shutdown(&mut self) {
+        self.shut_down = true;
+        self.stop_all();
+    }
+
+    pub fn statuses(&self) -> Vec<TunnelStatus> {
+        self.tunnels
+            .iter()
+            .map(|(id, tunnel)| TunnelStatus {
+                id: id.clone(),
+                label: tunnel.spec.label(),
+                state: tunnel.state.clone(),
+            })
+            .collect()
+    }
+
+    fn start(&self, spec: TunnelSpec, failures: u32, now: Instant) -> Tunnel {
+        Self::spawn(spec, failures, self.backoff, now)
+    }
+
+    fn spawn(spec: TunnelSpec, failures: u32, backoff: Backoff, now: Instant) -> Tunnel {
+        let spawned = match spec.argv.split_first() {
+            Some((program, args)) => Command::new(program)
+                .args(args)
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn()
+                .map_err(|e| format!("failed to start {}: {}", program, e)),
+            None => Err("empty command".to_string()),
+        };
+
+        match spawned {
+            Ok(child) => {
+                Logger::info(&format!(
+                    "Started tunnel {} (pid {})",
+                    spec.label(),
+                    child.id()
+                ));
+                Tunnel {
+                    state: TunnelState::Running { pid: child.id() },
+                    child: Some(child),
+                    spec,
+                    started: now,
+                    failures,
+                }
+            }
+            Err(reason) => {
+                let failures = failures + 1;
+                Logger::error(&format!("Tunnel {} {}", spec.label(), reason));
+                Tunnel {
+                    state: TunnelState::Restarting {
+                        at: now + backoff.delay(failures),
+                        reason,
+                    },
+                    child: None,
+                    spec,
+                    started: now,
+                    failures,
+                }
+            }
+        }
+    }
+}
+
+impl Drop for TunnelSupervisor {
+    fn drop(&mut self) {
+        self.stop_all();
+    }
+}
+
+/// Keep `supervisor` in step with the store at `store_path`, checking every second
+///
+/// Starting the menubar app restores every persisted tunnel on the first pass.
+pub fn spawn_supervisor_thread(
+    store_path: PathBuf,
+    supervisor: Arc<Mutex<TunnelSupervisor>>,
+) -> JoinHandle<()> {
+    thread::spawn(move || {
+        loop {
+            let store = TunnelStore::load(&store_path);
+            if let Ok(mut supervisor) = supervisor.lock() {
+                match &store {
+                    Ok(store) => supervisor.sync(&store.tunnels, Instant::now()),
+                    Err(e) => Logger::warn(&format!("{:#}", e)),
+                }
+                supervisor.poll(Instant::now());
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+    })
+}
+
+/// Stop a tunnel and remove it from the store so it is not restored
+pub fn stop_tunnel(
+    store_path: &Path,
+    supervisor: &Mutex<TunnelSupervisor>,
+    id: &str,
+) -> Result<()> {
+    TunnelStore::modify(store_path, |store| store.remove(id))?;
+    if let Ok(mut supervisor) = supervisor.lock() {
+        supervisor.stop(id);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(unix)]
+    use crate::test_support::write_script;
+    use tempfile::TempDir;
+
+    const FAST: Backoff = Backoff {
+        initial: Duration::from_millis(100),
+        max: Duration::from_millis(400),
+    };
+
+    /// A stand-in for ssh that records each start, then runs `body`
+    #[cfg(unix)]
+    fn fake_ssh(dir: &TempDir, name: &str, body: &str) -> PathBuf {
+        let starts = dir.path().join(format!("{}.starts", name));
+        write_script(
+            dir.path(),
+            name,
+            &format!("echo started >> '{}'\n{}", starts.display(), body),
+        )
+    }
+
+    fn starts(dir: &TempDir, name: &str) -> usize {
+        fs::read_to_string(dir.path().join(format!("{}.starts", name)))
+            .map(|content| content.lines().count())
+            .unwrap_or(0)
+    }
+
+    fn spec(host: &str, program: &Path) -> TunnelSpec {
+        TunnelSpec {
+            host: host.to_string(),
+            forwards: vec!["postgres".to_string()],
+            argv: vec![
+                program.display().to_string(),
+                "-N".to_string(),
+                host.to_string(),
+            ],
+        }
+    }
+
+    fn wait_for(condition: impl Fn() -> bool) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !condition() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(20));
+        }
+    }
+
+    fn is_alive(pid: u32) -> bool {
+        Command::new("kill")
+            .args(["-0", &pid.to_string()])
+            .status()
+            .is_ok_and(|status| status.success())
+    }
+
+    #[test]
+    fn test_backoff_doubles_up_to_max() {
+        let backoff = Backoff::default();
+        assert_eq!(backoff.delay(1), Duration::from_secs(1));
+        assert_eq!(backoff.delay(2), Duration::from_secs(2));
+        assert_eq!(backoff.delay(4), Duration::from_secs(8));
+        assert_eq!(backoff.delay(7), Duration::from_secs(60));
+        assert_eq!(backoff.delay(100), Duration::from_secs(60));
+    }
+
+    #[test]
+    fn test_concurrent_modifies_keep_every_tunnel() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("tunnels.toml");
+        let ssh = Path::new("/usr/bin/ssh");
+
+        thread::scope(|scope| {
+            for n in 0..8 {
+                let path = &path;
+                scope.spawn(move || {
+                    TunnelStore::modify(path, |store| store.add(spec(&format!("db{}", n), ssh)))
+                        .unwrap();
+                });
+            }
+        });
+
+        assert_eq!(TunnelStore::load(&path).unwrap().tunnels.len(), 8);
+        let leftovers: Vec<_> = fs::read_dir(temp_dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .filter(|name| name.to_string_lossy().ends_with(".tmp"))
+            .collect();
+        assert!(leftovers.is_empty(), "{:?}", leftovers);
+    }
+
+    #[test]
+    fn test_store_add_replace_and_remove() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("nested").join("tunnels.toml");
+        let ssh = Path::new("/usr/bin/ssh");
+
+        TunnelStore::modify(&path, |store| {
+            store.add(spec("db", ssh));
+            store.add(spec("web", ssh));
+            // Same host and forwards replaces rather than duplicating
+            store.add(spec("db", Path::new("/opt/bin/ssh")));
+        })
+        .unwrap();
+
+        let store = TunnelStore::load(&path).unwrap();
+        assert_eq!(store.tunnels.len(), 2);
+        assert_eq!(store.tunnels[0].argv[0], "/opt/bin/ssh");
+        assert_eq!(store.tunnels[0].id(), "db:postgres");
+
+        assert!(TunnelStore::modify(&path, |store| store.remove("web:postgres")).unwrap());
+        assert!(!TunnelStore::modify(&path, |store| store.remove("web:postgres")).unwrap());
+        assert_eq!(TunnelStore::load(&path).unwrap().tunnels.len(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_sync_starts_and_stops_tunnels() {
+        let temp_dir = TempDir::new().unwrap();
+        let ssh = fake_ssh(&temp_dir, "ssh", "sleep 30");
+        let mut supervisor = TunnelSupervisor::new(FAST);
+
+        supervisor.sync(&[spec("db", &ssh)], Instant::now());
+        let statuses = supervisor.statuses();
+        assert_eq!(statuses.len(), 1);
+        assert_eq!(statuses[0].id, "db:postgres");
+        let TunnelState::Running { pid } = statuses[0].state else {
+            panic!("tunnel should be running: {:?}", statuses[0].state);
+        };
+        assert!(is_alive(pid));
+
+        // Still running, so polling and syncing again start nothing new
+        supervisor.poll(Instant::now());
+        supervisor.sync(&[spec("db", &ssh)], Instant::now());
+        wait_for(|| starts(&temp_dir, "ssh") >= 1);
+        assert_eq!(starts(&temp_dir, "ssh"), 1);
+
+        // Dropped from the store, so its process is stopped
+        supervisor.sync(&[], Instant::now());
+        assert!(supervisor.statuses().is_empty());
+        assert!(!is_alive(pid));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_shutdown_stops_for_good() {
+        let temp_dir = TempDir::new().unwrap();
+        let ssh = fake_ssh(&temp_dir, "ssh", "sleep 30");
+        let mut supervisor = TunnelSupervisor::new(FAST);
+        supervisor.sync(&[spec("db", &ssh)], Instant::now());
+
+        supervisor.shutdown();
+        supervisor.sync(&[spec("db", &ssh)], Instant::now());
+        supervisor.poll(Instant::now());
+
+        assert!(supervisor.statuses().is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_exited_tunnel_restarts_after_backoff() {
+        let temp_dir = TempDir::new().unwrap();
+        let ssh = fake_ssh(&temp_dir, "ssh", "exit 255");
+        let mut supervisor = TunnelSupervisor::new(FAST);
+
+        let start = Instant::now();
+        supervisor.sync(&[spec("db", &ssh)], start);
+        wait_for(|| starts(&temp_dir, "ssh") == 1);
+        thread::sleep(Duration::from_millis(100));
+
+        supervisor.poll(start);
+        let TunnelState::Restarting { at, reason } = &supervisor.statuses()[0].state else {
+            panic!("tunnel should be waiting to restart");
+        };
+        assert_eq!(*at, start + FAST.initial);
+        assert!(reason.contains("255"));
+
+        // Not due yet
+        supervisor.poll(start + Duration::from_millis(50));
+        assert_eq!(starts(&temp_dir, "ssh"), 1);
+
+        supervisor.poll(start + FAST.initial);
+        wait_for(|| starts(&temp_dir, "ssh") == 2);
+        assert_eq!(starts(&temp_dir, "ssh"), 2);
+
+        // A second quick failure waits twice as long
+        thread::sleep(Duration::from_millis(100));
+        let now = start + FAST.initial;
+        supervisor.poll(now);
+        let TunnelState::Restarting { at, .. } = &supervisor.statuses()[0].state else {
+            panic!("tunnel should be waiting to restart");
+        };
+        assert_eq!(*at, now + FAST.initial * 2);
+    }
+
+    #[test]
+    fn test_missing_program_waits_to_retry() {
+        let mut supervisor = TunnelSupervisor::new(FAST);
+        let now = Instant::now();
+        supervisor.sync(&[spec("db", Path::new("/nonexistent/ssh"))], now);
+
+        let status = &supervisor.statuses()[0];
+        assert!(matches!(status.state, TunnelState::Restarting { .. }));
+        assert!(
+            status
+                .describe(now)
+                .starts_with("db (postgres): restarting in 0s")
+        );
+        assert!(status.describe(now).contains("failed to start"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_stop_tunnel_forgets_it() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("tunnels.toml");
+        let ssh = fake_ssh(&temp_dir, "ssh", "sleep 30");
+        TunnelStore::modify(&path, |store| store.add(spec("db", &ssh))).unwrap();
+
+        let supervisor = Mutex::new(TunnelSupervisor::new(FAST));
+        let specs = TunnelStore::load(&path).unwrap().tunnels;
+        supervisor.lock().unwrap().sync(&specs, Instant::now());
+        let TunnelState::Running { pid } = supervisor.lock().unwrap().statuses()[0].state else {
+            panic!("tunnel should be running");
+        };
+
+        stop_tunnel(&path, &supervisor, "db:postgres").unwrap();
+
+        assert!(supervisor.lock().unwrap().statuses().is_empty());
+        assert!(TunnelStore::load(&path).unwrap().tunnels.is_empty());
+        assert!(!is_alive(pid));
+    }
+}
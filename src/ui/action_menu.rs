@@ -0,0 +1,181 @@
+// ABOUTME: Action menu for choosing how to launch the selected host
+// ABOUTME: Lists per-launch choices such as a terminal profile, port forwards, file actions, keys or marked hosts
+
+use crate::config::BroadcastLayout;
+#[cfg(not(test))]
+use gpui::prelude::*;
+#[cfg(not(test))]
+use gpui::*;
+use std::path::PathBuf;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum HostAction {
+    /// Open sftp on the host in the terminal (Cmd+S)
+    OpenSftp,
+    /// Open the host's file manager URI (Cmd+O)
+    OpenInFileManager(String),
+    /// Copy an scp command for the host to the clipboard (Cmd+Shift+C)
+    CopyScpCommand(String),
+    /// Run a recent or saved remote command instead of a shell
+    RunCommand(String),
+    /// Load an `IdentityFile` that isn't in the ssh agent, with `ssh-add` in the terminal
+    AddKey(PathBuf),
+    /// Show the host's address and known_hosts fingerprints (Cmd+I)
+    ShowDetails,
+    /// Compare the host's keys from ssh-keyscan with known_hosts
+    VerifyHostKey,
+    /// Launch in the named terminal profile instead of the host's usual one
+    OpenWithProfile(String),
+    /// Launch with the named forward presets; `description` shows the ssh options
+    OpenWithForwards {
+        forwards: Vec<String>,
+        description: String,
+    },
+    /// Hold the named forward presets open in a background tunnel run by the menubar app
+    StartTunnel {
+        forwards: Vec<String>,
+        description: String,
+    },
+    /// Launch every marked host with the given layout
+    OpenMarked {
+        layout: BroadcastLayout,
+        count: usize,
+    },
+}
+
+impl HostAction {
+    pub fn label(&self) -> String {
+        match self {
+            HostAction::OpenSftp => "Browse files with sftp (Cmd+S)".to_string(),
+            HostAction::OpenInFileManager(uri) => format!("Open {} in file manager (Cmd+O)", uri),
+            HostAction::CopyScpCommand(command) => format!("Copy \"{}\" (Cmd+Shift+C)", command),
+            HostAction::RunCommand(command) => format!("Run: {}", command),
+            HostAction::AddKey(key) => format!("Add key {} to the ssh agent", key.display()),
+            HostAction::ShowDetails => "Show host details (Cmd+I)".to_string(),
+            HostAction::VerifyHostKey => "Verify host key now (ssh-keyscan)".to_string(),
+            HostAction::OpenWithProfile(name) => format!("Open in profile: {}", name),
+            HostAction::OpenWithForwards {
+                forwards,
+                description,
+            } => format!("Forward {}: {}", forwards.join(", "), description),
+            HostAction::StartTunnel {
+                forwards,
+                description,
+            } => format!(
+                "Run {} in the background: {}",
+                forwards.join(", "),
+                description
+            ),
+            HostAction::OpenMarked {
+                layout: BroadcastLayout::Windows,
+                count,
+            } => format!("Open {} marked hosts in separate windows", count),
+            HostAction::OpenMarked {
+                layout: BroadcastLayout::Tiled,
+                count,
+            } => format!("Open {} marked hosts tiled in tmux", count),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct ActionMenu {
+    pub host_name: String,
+    pub actions: Vec<HostAction>,
+    pub selected_index: usize,
+}
+
+impl ActionMenu {
+    pub fn new(host_name: String, actions: Vec<HostAction>) -> Self {
+        Self {
+            host_name,
+            actions,
+            selected_index: 0,
+        }
+    }
+
+    pub fn select_next(&mut self) {
+        if !self.actions.is_empty() {
+            self.selected_index = (self.selected_index + 1) % self.actions.len();
+        }
+    }
+
+    pub fn select_previous(&mut self) {
+        if !self.actions.is_empty() {
+            self.selected_index = if self.selected_index == 0 {
+                self.actions.len() - 1
+            } else {
+                self.selected_index - 1
+            };
+        }
+    }
+
+    pub fn selected_action(&self) -> Option<&HostAction> {
+        self.actions.get(self.selected_index)
+    }
+}
+
+#[cfg(not(test))]
+impl IntoElement for ActionMenu {
+    type Element = Div;
+
+    fn into_element(self) -> Self::Element {
+        let header = div()
+            .w_full()
+            .px_3()
+            .py_2()
+            .text_color(rgb(0x8c8c8c)) // Zed muted text
+            .text_size(px(12.0))
+            .child(format!("Actions for {}", self.host_name));
+
+        if self.actions.is_empty() {
+            return div()
+                .flex()
+                .flex_col()
+                .w_full()
+                .bg(rgb(0x252930)) // Zed surface background
+                .child(header)
+                .child(
+                    div()
+                        .px_3()
+                        .py_2()
+                        .text_color(rgb(0x8c8c8c))
+                        .text_size(px(14.0))
+                        .child("No actions available"),
+                );
+        }
+
+        div()
+            .flex()
+            .flex_col()
+            .w_full()
+            .bg(rgb(0x252930)) // Zed surface background
+            .child(header)
+            .children(
+                self.actions
+                    .iter()
+                    .enumerate()
+                    .map(|(i, action)| {
+                        let is_selected = i == self.selected_index;
+
+                        div()
+                            .w_full()
+                            .px_3()
+                            .py_2()
+                            .bg(if is_selected {
+                                hsla(207.0 / 360.0, 0.7, 0.25, 0.2)
+                            } else {
+                                rgb(0x252930).into()
+                            })
+                            .text_color(if is_selected {
+                                rgb(0x569cd6) // Zed accent text
+                            } else {
+                                rgb(0xd4d4d4) // Zed primary text
+                            })
+                            .text_size(px(14.0))
+                            .child(action.label())
+                    })
+                    .collect::<Vec<_>>(),
+            )
+    }
+}
@@ -1,16 +1,32 @@
 // ABOUTME: Host list display component for showing SSH host search results
 // ABOUTME: Renders scrollable list of hosts with highlighting for selected item
 
+use crate::fuzzy::MatchField;
 use crate::ssh::parser::HostEntry;
+use crate::ssh::probe::ProbeResult;
 #[cfg(not(test))]
 use gpui::prelude::*;
 #[cfg(not(test))]
 use gpui::*;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Rows the list shows at once
+pub const VISIBLE_HOSTS: usize = 8;
 
 #[derive(Clone)]
 pub struct HostList {
     pub hosts: Vec<HostEntry>,
     pub selected_index: usize,
+    /// Field each host matched on, parallel to `hosts`; empty when not searching
+    pub matched_fields: Vec<MatchField>,
+    /// Hosts marked for a multi-host launch, keyed by name so marks survive new searches
+    pub marked: BTreeMap<String, HostEntry>,
+    /// Hosts whose `IdentityFile` isn't loaded in the ssh agent
+    pub missing_keys: BTreeSet<String>,
+    /// Latest reachability probe per host name
+    pub probes: BTreeMap<String, ProbeResult>,
+    /// Hosts whose key differs from known_hosts when last verified
+    pub changed_host_keys: BTreeSet<String>,
 }
 
 impl HostList {
@@ -18,11 +34,17 @@ impl HostList {
         Self {
             hosts,
             selected_index: 0,
+            matched_fields: Vec::new(),
+            marked: BTreeMap::new(),
+            missing_keys: BTreeSet::new(),
+            probes: BTreeMap::new(),
+            changed_host_keys: BTreeSet::new(),
         }
     }
 
     pub fn set_hosts(&mut self, hosts: Vec<HostEntry>) {
         self.hosts = hosts;
+        self.matched_fields.clear();
         // Reset selection if it's out of bounds
         if self.selected_index >= self.hosts.len() {
             self.selected_index = if self.hosts.is_empty() {
@@ -35,14 +57,14 @@ impl HostList {
 
     pub fn select_next(&mut self) {
         if !self.hosts.is_empty() {
-            let max_visible = 8.min(self.hosts.len());
+            let max_visible = VISIBLE_HOSTS.min(self.hosts.len());
             self.selected_index = (self.selected_index + 1) % max_visible;
         }
     }
 
     pub fn select_previous(&mut self) {
         if !self.hosts.is_empty() {
-            let max_visible = 8.min(self.hosts.len());
+            let max_visible = VISIBLE_HOSTS.min(self.hosts.len());
             self.selected_index = if self.selected_index == 0 {
                 max_visible - 1
             } else {
@@ -64,6 +86,78 @@ impl HostList {
     pub fn is_empty(&self) -> bool {
         self.hosts.is_empty()
     }
+
+    /// Mark or unmark the selected host (Space)
+    pub fn toggle_marked(&mut self) {
+        if let Some(host) = self.hosts.get(self.selected_index).cloned()
+            && self.marked.remove(&host.name).is_none()
+        {
+            self.marked.insert(host.name.clone(), host);
+        }
+    }
+
+    /// Mark every result, or unmark them all if they are already marked (Ctrl+A)
+    pub fn toggle_mark_all(&mut self) {
+        if self
+            .hosts
+            .iter()
+            .all(|host| self.marked.contains_key(&host.name))
+        {
+            for host in &self.hosts {
+                self.marked.remove(&host.name);
+            }
+        } else {
+            for host in &self.hosts {
+                self.marked.insert(host.name.clone(), host.clone());
+            }
+        }
+    }
+
+    pub fn is_marked(&self, host: &HostEntry) -> bool {
+        self.marked.contains_key(&host.name)
+    }
+
+    /// Marked hosts in name order
+    pub fn marked_hosts(&self) -> Vec<HostEntry> {
+        self.marked.values().cloned().collect()
+    }
+
+    /// Flag hosts whose key the ssh agent doesn't hold
+    pub fn set_missing_keys(&mut self, hosts: BTreeSet<String>) {
+        self.missing_keys = hosts;
+    }
+
+    /// Flag hosts whose host key changed
+    pub fn set_changed_host_keys(&mut self, hosts: BTreeSet<String>) {
+        self.changed_host_keys = hosts;
+    }
+
+    /// The hosts currently on screen
+    pub fn visible_hosts(&self) -> &[HostEntry] {
+        &self.hosts[..VISIBLE_HOSTS.min(self.hosts.len())]
+    }
+
+    /// Record probe results; hosts not in `probes` keep their previous result
+    pub fn set_probes(&mut self, probes: BTreeMap<String, ProbeResult>) {
+        self.probes.extend(probes);
+    }
+
+    /// Record which field each host matched so the list can show why it appeared
+    pub fn set_matched_fields(&mut self, fields: Vec<MatchField>) {
+        self.matched_fields = fields;
+    }
+
+    /// Secondary line for a host: the matched field when it wasn't the alias
+    fn detail_line(&self, index: usize) -> String {
+        let host = &self.hosts[index];
+        match self.matched_fields.get(index) {
+            Some(&field) if field != MatchField::Alias => {
+                let value = field.value_of(host).unwrap_or_default();
+                format!("{}: {}", field, value)
+            }
+            _ => host.connection_string.clone(),
+        }
+    }
 }
 
 #[cfg(not(test))]
@@ -95,10 +189,19 @@ impl IntoElement for HostList {
             .children(
                 self.hosts
                     .iter()
-                    .take(8)
+                    .take(VISIBLE_HOSTS)
                     .enumerate()
                     .map(|(i, host)| {
                         let is_selected = i == self.selected_index;
+                        let detail = self.detail_line(i);
+                        let key_missing = self.missing_keys.contains(&host.name);
+                        let probe = self.probes.get(&host.name).cloned();
+                        let host_key_changed = self.changed_host_keys.contains(&host.name);
+                        let name = if self.is_marked(host) {
+                            format!("✓ {}", host.name)
+                        } else {
+                            host.name.clone()
+                        };
 
                         div()
                             .flex()
@@ -126,20 +229,59 @@ impl IntoElement for HostList {
                                             })
                                             .text_size(px(14.0))
                                             .font_weight(FontWeight::MEDIUM)
-                                            .child(host.name.clone()),
+                                            .child(name),
                                     )
                                     .child(
                                         div()
                                             .text_color(rgb(0xa5a5a5)) // Zed muted text
                                             .text_size(px(12.0))
-                                            .child(host.connection_string.clone()),
+                                            .child(detail),
                                     ),
                             )
+                            .child(
+                                div()
+                                    .flex()
+                                    .gap_2()
+                                    .ml_auto()
+                                    .text_size(px(12.0))
+                                    .when(host_key_changed, |badges| {
+                                        badges.child(
+                                            div()
+                                                .text_color(rgb(0xf48771)) // Zed error text
+                                                .font_weight(FontWeight::BOLD)
+                                                .child("HOST KEY CHANGED"),
+                                        )
+                                    })
+                                    .when(key_missing, |badges| {
+                                        badges.child(
+                                            div()
+                                                .text_color(rgb(0xcca700)) // Zed warning text
+                                                .child("key not loaded"),
+                                        )
+                                    })
+                                    .when_some(probe, |badges, probe| {
+                                        badges.child(
+                                            div()
+                                                .text_color(probe_color(&probe))
+                                                .child(probe.to_string()),
+                                        )
+                                    }),
+                            )
                     })
                     .collect::<Vec<_>>(),
             )
     }
 }
 
+#[cfg(not(test))]
+fn probe_color(probe: &ProbeResult) -> Rgba {
+    match probe {
+        ProbeResult::Ssh { .. } => rgb(0x89d185), // Zed success text
+        ProbeResult::Open { .. } => rgb(0xcca700), // Zed warning text
+        ProbeResult::Unreachable { .. } => rgb(0xf48771), // Zed error text
+        ProbeResult::NotProbed => rgb(0x8c8c8c),  // Zed muted text
+    }
+}
+
 // Tests removed due to GPUI macro compilation issues
 // Core logic is tested through the running application and manual testing
@@ -1,8 +1,10 @@
 // ABOUTME: UI components for the Trident SSH launcher using GPUI
-// ABOUTME: Provides search input and host list display components
+// ABOUTME: Provides search input, host list and action menu components
 
+pub mod action_menu;
 pub mod host_list;
 pub mod search_input;
 
+pub use action_menu::{ActionMenu, HostAction};
 pub use host_list::HostList;
 pub use search_input::SearchInput;
@@ -7,12 +7,16 @@ use gpui::*;
 #[cfg(not(test))]
 use gpui::prelude::FluentBuilder;
 
+use crate::fuzzy::MatchOptions;
+
 #[derive(Clone)]
 pub struct SearchInput {
     pub query: String,
     pub placeholder: String,
     pub is_focused: bool,
     pub suggestion: Option<String>,
+    /// How the suggestion was matched, so the hint compares it with the query the same way
+    pub match_options: MatchOptions,
 }
 
 impl SearchInput {
@@ -22,6 +26,7 @@ impl SearchInput {
             placeholder,
             is_focused: false,
             suggestion: None,
+            match_options: MatchOptions::default(),
         }
     }
 
@@ -38,6 +43,29 @@ impl SearchInput {
         self.suggestion = None; // Clear suggestion when typing
     }
 
+    /// Type a key into the query, returning whether it was taken as text
+    ///
+    /// Space marks hosts while searching, so it only types a space once a `>` has started
+    /// a remote command (`web > journalctl -f`).
+    pub fn handle_key(&mut self, key: &str) -> bool {
+        let text = match key {
+            "space" if self.is_typing_command() => " ",
+            text if text.len() == 1
+                && text.chars().all(|ch| ch.is_ascii_graphic() || ch == ' ') =>
+            {
+                text
+            }
+            _ => return false,
+        };
+        self.handle_input(text);
+        true
+    }
+
+    /// Whether the query has moved past the host search into a remote command
+    pub fn is_typing_command(&self) -> bool {
+        self.query.contains('>')
+    }
+
     pub fn handle_backspace(&mut self) {
         self.query.pop();
         self.suggestion = None; // Clear suggestion when deleting
@@ -48,8 +76,23 @@ impl SearchInput {
         self.suggestion = None;
     }
 
-    pub fn set_suggestion(&mut self, suggestion: Option<String>) {
+    pub fn set_suggestion(&mut self, suggestion: Option<String>, options: MatchOptions) {
         self.suggestion = suggestion;
+        self.match_options = options;
+    }
+
+    /// The part of the suggestion that extends beyond the typed query, if the query is a prefix of it
+    ///
+    /// Compares by character so multi-byte host names are never split mid-character.
+    pub fn remaining_suggestion(&self) -> Option<String> {
+        let suggestion = self.suggestion.as_ref()?;
+        let query_len = self.query.chars().count();
+        if suggestion.chars().count() <= query_len
+            || !self.match_options.is_prefix(&self.query, suggestion)
+        {
+            return None;
+        }
+        Some(suggestion.chars().skip(query_len).collect())
     }
 
     pub fn accept_suggestion(&mut self) {
@@ -149,25 +192,15 @@ impl IntoElement for SearchInput {
                                                     )
                                                 },
                                             )
+                                            // Only show suggestion if it extends beyond current query
                                             .when_some(
-                                                self.suggestion.as_ref(),
-                                                |this, suggestion| {
-                                                    // Only show suggestion if it extends beyond current query
-                                                    if suggestion.len() > self.query.len()
-                                                        && suggestion
-                                                            .to_lowercase()
-                                                            .starts_with(&self.query.to_lowercase())
-                                                    {
-                                                        let remaining =
-                                                            &suggestion[self.query.len()..];
-                                                        this.child(
-                                                            div()
-                                                                .text_color(rgb(0x666666))
-                                                                .child(remaining.to_string()),
-                                                        )
-                                                    } else {
-                                                        this
-                                                    }
+                                                self.remaining_suggestion(),
+                                                |this, remaining| {
+                                                    this.child(
+                                                        div()
+                                                            .text_color(rgb(0x666666))
+                                                            .child(remaining),
+                                                    )
                                                 },
                                             ),
                                     )
@@ -178,5 +211,62 @@ impl IntoElement for SearchInput {
     }
 }
 
-// Tests removed due to GPUI macro compilation issues
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn type_keys(input: &mut SearchInput, keys: &[&str]) {
+        for key in keys {
+            input.handle_key(key);
+        }
+    }
+
+    #[test]
+    fn test_space_types_into_remote_command() {
+        let mut input = SearchInput::new(String::new());
+        type_keys(&mut input, &["w", "e", "b"]);
+        // Before a command, Space is left for marking hosts
+        assert!(!input.handle_key("space"));
+        assert_eq!(input.query, "web");
+
+        type_keys(
+            &mut input,
+            &[">", "space", "j", "o", "u", "r", "n", "a", "l"],
+        );
+        type_keys(
+            &mut input,
+            &["c", "t", "l", "space", "-", "f", "space", "-", "u"],
+        );
+        type_keys(&mut input, &["space", "a", "p", "p"]);
+
+        assert_eq!(input.query, "web> journalctl -f -u app");
+        assert_eq!(
+            crate::app::split_command_query(&input.query),
+            ("web", Some("journalctl -f -u app"))
+        );
+    }
+
+    #[test]
+    fn test_suggestion_hint_folds_diacritics_like_search() {
+        let mut input = SearchInput::new(String::new());
+        input.set_query("cafe".to_string());
+        let options = MatchOptions {
+            fold_diacritics: true,
+            ..Default::default()
+        };
+        input.set_suggestion(Some("Café-server".to_string()), options);
+        assert_eq!(input.remaining_suggestion().as_deref(), Some("-server"));
+
+        input.set_suggestion(Some("Café-server".to_string()), MatchOptions::default());
+        assert_eq!(input.remaining_suggestion(), None);
+    }
+
+    #[test]
+    fn test_ignores_named_keys() {
+        let mut input = SearchInput::new(String::new());
+        assert!(!input.handle_key("escape"));
+        assert!(!input.handle_key("é"));
+        assert_eq!(input.query, "");
+    }
+}
 // Core logic is tested through the running application and manual testing